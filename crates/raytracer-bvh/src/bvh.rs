@@ -0,0 +1,429 @@
+use glam::Vec3A;
+use raytracer_core::{aabb::Aabb, ray::Ray};
+
+/// number of buckets the centroids are binned into when evaluating split candidates
+const BIN_COUNT: usize = 16;
+/// leaves are always created below this number of primitives
+const MIN_LEAF_SIZE: usize = 2;
+/// leaves are never created above this number of primitives, unless all centroids coincide
+const MAX_LEAF_SIZE: usize = 8;
+/// cost of traversing an interior node, relative to the cost of intersecting a primitive
+const TRAVERSAL_COST: f32 = 1.0;
+
+/// A bounding volume hierarchy over an arbitrary list of primitives, built with the surface area
+/// heuristic (SAH).
+///
+/// The hierarchy only stores primitive indices; the caller is responsible for intersecting the
+/// primitives themselves, see [`Bvh::traverse`].
+#[derive(Debug, Clone, Default)]
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    primitive_indices: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BvhNode {
+    bounds: Bounds,
+    /// index of the first primitive for leaves, or of the left child for interior nodes;
+    /// the right child always follows the left child
+    offset: u32,
+    /// number of primitives in a leaf; zero for interior nodes
+    count: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bounds {
+    min: Vec3A,
+    max: Vec3A,
+}
+
+impl Bounds {
+    const EMPTY: Self = Self {
+        min: Vec3A::INFINITY,
+        max: Vec3A::NEG_INFINITY,
+    };
+
+    fn union(self, other: Self) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    fn grow(self, point: Vec3A) -> Self {
+        Self {
+            min: self.min.min(point),
+            max: self.max.max(point),
+        }
+    }
+
+    fn centroid(&self) -> Vec3A {
+        (self.min + self.max) * 0.5
+    }
+
+    fn surface_area(&self) -> f32 {
+        let extent = (self.max - self.min).max(Vec3A::ZERO);
+        2.0 * (extent.x * extent.y + extent.x * extent.z + extent.y * extent.z)
+    }
+
+    /// Returns the distance at which the ray enters the box, if it does so before `t_max`.
+    fn intersect(
+        &self,
+        origin: Vec3A,
+        inv_direction: Vec3A,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<f32> {
+        let t1 = (self.min - origin) * inv_direction;
+        let t2 = (self.max - origin) * inv_direction;
+
+        let t_near = t1.min(t2).max_element().max(t_min);
+        let t_far = t1.max(t2).min_element().min(t_max);
+
+        (t_near <= t_far).then_some(t_near)
+    }
+}
+
+impl From<&Aabb> for Bounds {
+    fn from(aabb: &Aabb) -> Self {
+        Self {
+            min: aabb.min,
+            max: aabb.max,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BuildPrimitive {
+    bounds: Bounds,
+    centroid: Vec3A,
+    index: u32,
+}
+
+impl Bvh {
+    /// Builds a hierarchy over the given bounding boxes.
+    /// The primitive indices reported by [`Bvh::traverse`] are indices into this slice.
+    pub fn build(bounding_boxes: &[Aabb]) -> Self {
+        let mut primitives: Vec<_> = bounding_boxes
+            .iter()
+            .enumerate()
+            .map(|(index, aabb)| {
+                let bounds = Bounds::from(aabb);
+                BuildPrimitive {
+                    bounds,
+                    centroid: bounds.centroid(),
+                    index: index as u32,
+                }
+            })
+            .collect();
+
+        let mut bvh = Self {
+            nodes: Vec::with_capacity(primitives.len() * 2),
+            primitive_indices: Vec::with_capacity(primitives.len()),
+        };
+
+        if !primitives.is_empty() {
+            bvh.nodes.push(BvhNode {
+                bounds: Bounds::EMPTY,
+                offset: 0,
+                count: 0,
+            });
+            bvh.build_node(0, &mut primitives);
+        }
+
+        bvh
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Visits the primitives whose bounding boxes are hit by the ray, roughly front to back.
+    ///
+    /// `intersect` is called with the index of a primitive and the distance of the closest hit
+    /// found so far, and must return the distance of its own hit if it is closer than that.
+    /// Subtrees that lie entirely behind the closest hit are skipped.
+    pub fn traverse(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        mut intersect: impl FnMut(usize, f32) -> Option<f32>,
+    ) {
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let origin = ray.origin;
+        let inv_direction = ray.direction.recip();
+        let mut t_closest = t_max;
+
+        let Some(t_root) = self.nodes[0]
+            .bounds
+            .intersect(origin, inv_direction, t_min, t_closest)
+        else {
+            return;
+        };
+
+        // nodes are stored with the distance at which the ray enters them, since a closer hit
+        // may have been found between the time they were pushed and the time they are visited
+        let mut stack = Vec::with_capacity(64);
+        stack.push((0u32, t_root));
+
+        while let Some((node_index, t_enter)) = stack.pop() {
+            if t_enter > t_closest {
+                continue;
+            }
+
+            let node = &self.nodes[node_index as usize];
+
+            if node.count != 0 {
+                let first = node.offset as usize;
+                let last = first + node.count as usize;
+
+                for &primitive_index in &self.primitive_indices[first..last] {
+                    if let Some(t) = intersect(primitive_index as usize, t_closest) {
+                        t_closest = t_closest.min(t);
+                    }
+                }
+
+                continue;
+            }
+
+            let left = node.offset;
+            let right = node.offset + 1;
+            let enter = |index: u32| {
+                self.nodes[index as usize]
+                    .bounds
+                    .intersect(origin, inv_direction, t_min, t_closest)
+            };
+            let t_left = enter(left);
+            let t_right = enter(right);
+
+            // push the farther child first, so that the nearer one is visited first
+            match (t_left, t_right) {
+                (Some(t_left), Some(t_right)) if t_left <= t_right => {
+                    stack.push((right, t_right));
+                    stack.push((left, t_left));
+                }
+                (Some(t_left), Some(t_right)) => {
+                    stack.push((left, t_left));
+                    stack.push((right, t_right));
+                }
+                (Some(t_left), None) => stack.push((left, t_left)),
+                (None, Some(t_right)) => stack.push((right, t_right)),
+                (None, None) => {}
+            }
+        }
+    }
+
+    fn build_node(&mut self, node_index: usize, primitives: &mut [BuildPrimitive]) {
+        let bounds = primitives.iter().fold(Bounds::EMPTY, |bounds, primitive| {
+            bounds.union(primitive.bounds)
+        });
+        let centroid_bounds = primitives.iter().fold(Bounds::EMPTY, |bounds, primitive| {
+            bounds.grow(primitive.centroid)
+        });
+
+        self.nodes[node_index].bounds = bounds;
+
+        if primitives.len() <= MIN_LEAF_SIZE {
+            self.make_leaf(node_index, primitives);
+            return;
+        }
+
+        let extent = centroid_bounds.max - centroid_bounds.min;
+        let axis = if extent.x > extent.y && extent.x > extent.z {
+            0
+        } else if extent.y > extent.z {
+            1
+        } else {
+            2
+        };
+
+        if extent[axis] <= 0.0 {
+            // all centroids coincide; no split can separate them
+            self.make_leaf(node_index, primitives);
+            return;
+        }
+
+        let (split_bin, split_cost) = find_sah_split(primitives, &centroid_bounds, axis);
+        let leaf_cost = primitives.len() as f32;
+        let split_cost = TRAVERSAL_COST + split_cost / bounds.surface_area().max(f32::EPSILON);
+
+        if split_cost >= leaf_cost && primitives.len() <= MAX_LEAF_SIZE {
+            self.make_leaf(node_index, primitives);
+            return;
+        }
+
+        let bin_of = |primitive: &BuildPrimitive| {
+            bin_index(primitive.centroid[axis], &centroid_bounds, axis)
+        };
+        let mut mid = partition(primitives, |primitive| bin_of(primitive) <= split_bin);
+
+        if mid == 0 || mid == primitives.len() {
+            // binning could not separate the primitives; fall back to a median split
+            mid = primitives.len() / 2;
+            primitives
+                .select_nth_unstable_by(mid, |a, b| a.centroid[axis].total_cmp(&b.centroid[axis]));
+        }
+
+        let left_index = self.nodes.len();
+        self.nodes.push(BvhNode {
+            bounds: Bounds::EMPTY,
+            offset: 0,
+            count: 0,
+        });
+        self.nodes.push(BvhNode {
+            bounds: Bounds::EMPTY,
+            offset: 0,
+            count: 0,
+        });
+        self.nodes[node_index].offset = left_index as u32;
+        self.nodes[node_index].count = 0;
+
+        let (left, right) = primitives.split_at_mut(mid);
+        self.build_node(left_index, left);
+        self.build_node(left_index + 1, right);
+    }
+
+    fn make_leaf(&mut self, node_index: usize, primitives: &[BuildPrimitive]) {
+        let node = &mut self.nodes[node_index];
+        node.offset = self.primitive_indices.len() as u32;
+        node.count = primitives.len() as u32;
+
+        self.primitive_indices
+            .extend(primitives.iter().map(|primitive| primitive.index));
+    }
+}
+
+fn bin_index(value: f32, centroid_bounds: &Bounds, axis: usize) -> usize {
+    let min = centroid_bounds.min[axis];
+    let extent = centroid_bounds.max[axis] - min;
+    let bin = ((value - min) / extent * BIN_COUNT as f32) as usize;
+    bin.min(BIN_COUNT - 1)
+}
+
+/// Finds the bin boundary with the lowest SAH cost along the given axis.
+///
+/// Returns the index of the last bin on the left side, and the unnormalized cost of the split,
+/// i.e. the sum of each side's surface area multiplied by its primitive count.
+fn find_sah_split(
+    primitives: &[BuildPrimitive],
+    centroid_bounds: &Bounds,
+    axis: usize,
+) -> (usize, f32) {
+    let mut bin_bounds = [Bounds::EMPTY; BIN_COUNT];
+    let mut bin_counts = [0usize; BIN_COUNT];
+
+    for primitive in primitives {
+        let bin = bin_index(primitive.centroid[axis], centroid_bounds, axis);
+        bin_bounds[bin] = bin_bounds[bin].union(primitive.bounds);
+        bin_counts[bin] += 1;
+    }
+
+    // sweep from the right to accumulate the cost of every possible right side
+    let mut right_costs = [0.0; BIN_COUNT];
+    let mut right_bounds = Bounds::EMPTY;
+    let mut right_count = 0;
+
+    for bin in (1..BIN_COUNT).rev() {
+        right_bounds = right_bounds.union(bin_bounds[bin]);
+        right_count += bin_counts[bin];
+        right_costs[bin - 1] = right_count as f32 * right_bounds.surface_area();
+    }
+
+    let mut best_bin = 0;
+    let mut best_cost = f32::INFINITY;
+    let mut left_bounds = Bounds::EMPTY;
+    let mut left_count = 0;
+
+    for bin in 0..BIN_COUNT - 1 {
+        left_bounds = left_bounds.union(bin_bounds[bin]);
+        left_count += bin_counts[bin];
+
+        let cost = left_count as f32 * left_bounds.surface_area() + right_costs[bin];
+
+        if cost < best_cost {
+            best_cost = cost;
+            best_bin = bin;
+        }
+    }
+
+    (best_bin, best_cost)
+}
+
+/// Moves every primitive satisfying the predicate to the front, returning how many there are.
+fn partition(
+    primitives: &mut [BuildPrimitive],
+    predicate: impl Fn(&BuildPrimitive) -> bool,
+) -> usize {
+    let mut mid = 0;
+
+    for i in 0..primitives.len() {
+        if predicate(&primitives[i]) {
+            primitives.swap(i, mid);
+            mid += 1;
+        }
+    }
+
+    mid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Boxes scattered in a cube of side 10, standing in for primitives.
+    fn random_boxes(count: usize) -> Vec<Aabb> {
+        (0..count)
+            .map(|_| {
+                let center = Vec3A::new(rand::random(), rand::random(), rand::random()) * 10.0;
+                let half_size = Vec3A::new(rand::random(), rand::random(), rand::random()) * 0.5;
+                Aabb {
+                    min: center - half_size,
+                    max: center + half_size,
+                }
+            })
+            .collect()
+    }
+
+    fn random_direction() -> Vec3A {
+        loop {
+            let direction = Vec3A::new(rand::random(), rand::random(), rand::random()) * 2.0 - 1.0;
+            let length_squared = direction.length_squared();
+
+            if length_squared > 1e-4 && length_squared <= 1.0 {
+                return direction / length_squared.sqrt();
+            }
+        }
+    }
+
+    #[test]
+    fn traversal_finds_the_same_closest_hit_as_a_brute_force_search() {
+        let boxes = random_boxes(500);
+        let bvh = Bvh::build(&boxes);
+        let hit = |aabb: &Aabb, ray: &Ray, t_max: f32| {
+            Bounds::from(aabb).intersect(ray.origin, ray.direction.recip(), 1e-4, t_max)
+        };
+
+        for _ in 0..1000 {
+            let origin = Vec3A::new(rand::random(), rand::random(), rand::random()) * 14.0 - 2.0;
+            let ray = Ray::new(origin, random_direction());
+
+            let expected = boxes
+                .iter()
+                .filter_map(|aabb| hit(aabb, &ray, f32::INFINITY))
+                .min_by(f32::total_cmp);
+
+            let mut closest = None;
+            bvh.traverse(&ray, 1e-4, f32::INFINITY, |index, t_closest| {
+                let t = hit(&boxes[index], &ray, t_closest)?;
+                closest = Some(t);
+                Some(t)
+            });
+
+            assert_eq!(closest, expected, "ray {ray:?}");
+        }
+    }
+}
@@ -0,0 +1,3 @@
+mod bvh;
+
+pub use bvh::*;
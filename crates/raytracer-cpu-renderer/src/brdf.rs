@@ -29,8 +29,67 @@ impl BrdfSample {
     };
 }
 
+/// The diffuse lobe at the bottom of a layered surface, under the specular layer that reflects
+/// part of the light before it reaches the lobe.
+///
+/// Subsurface scattering replaces this lobe by a random walk through the object, which light
+/// enters and leaves by crossing the specular layer.
+#[derive(Debug, Clone, Copy)]
+pub struct DiffuseLayer {
+    /// weight of the lobe in the response of the surface
+    pub weight: f32,
+    /// reflectance of the specular layer at normal incidence
+    pub f0: Vec3A,
+}
+
+impl DiffuseLayer {
+    /// Returns the fraction of the light crossing the specular layer at `cos_theta` from the
+    /// normal, following Schlick's approximation of the Fresnel equations.
+    pub fn transmittance(&self, cos_theta: f32) -> Vec3A {
+        let fresnel = self.f0 + (Vec3A::ONE - self.f0) * (1.0 - cos_theta.clamp(0.0, 1.0)).powi(5);
+
+        Vec3A::ONE - fresnel
+    }
+}
+
 pub trait Brdf: Send + Sync {
     fn is_delta_surface(&self, material: &Material) -> bool;
-    fn eval(&self, view: Vec3A, normal: Vec3A, light: Vec3A, material: &Material) -> BrdfEval;
-    fn sample(&self, view: Vec3A, normal: Vec3A, material: &Material) -> BrdfSample;
+
+    /// Whether light can pass through the surface.
+    ///
+    /// Transmissive surfaces are visible from both sides, and are given the outward normal of the
+    /// surface rather than the one facing the ray, so that they can tell whether light is entering
+    /// or leaving the object from the sign of `normal.dot(view)`.
+    fn is_transmissive(&self, _material: &Material) -> bool {
+        false
+    }
+
+    /// `tangent` is perpendicular to `normal` and follows the `u` direction of the surface
+    /// parameterization, which anisotropic materials stretch their highlights along.
+    fn eval(
+        &self,
+        view: Vec3A,
+        normal: Vec3A,
+        tangent: Vec3A,
+        light: Vec3A,
+        material: &Material,
+    ) -> BrdfEval;
+    fn sample(&self, view: Vec3A, normal: Vec3A, tangent: Vec3A, material: &Material)
+    -> BrdfSample;
+
+    /// Returns the diffuse lobe of the surface, if it has one.
+    fn diffuse_layer(&self, _material: &Material) -> Option<DiffuseLayer> {
+        None
+    }
+
+    /// Evaluates the diffuse lobe alone, with the weight it has in [`Brdf::eval`].
+    fn eval_diffuse(
+        &self,
+        _view: Vec3A,
+        _normal: Vec3A,
+        _light: Vec3A,
+        _material: &Material,
+    ) -> Vec3A {
+        Vec3A::ZERO
+    }
 }
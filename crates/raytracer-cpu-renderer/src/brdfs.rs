@@ -1,14 +1,17 @@
+pub mod conductor;
+pub mod dielectric;
 pub mod disney;
 pub mod lambertian;
 
 use glam::{Mat3A, Vec3A};
+use raytracer_core::material::Material;
 use std::f32::consts::PI;
 
 fn lerp(a: f32, b: f32, t: f32) -> f32 {
     a + (b - a) * t
 }
 
-fn random_cosine_direction(normal: Vec3A) -> Vec3A {
+pub(crate) fn random_cosine_direction(normal: Vec3A) -> Vec3A {
     let r1 = rand::random::<f32>();
     let r2 = rand::random::<f32>();
 
@@ -23,7 +26,29 @@ fn random_cosine_direction(normal: Vec3A) -> Vec3A {
     tbn.mul_vec3a(Vec3A::new(x, y, z)).normalize()
 }
 
-fn create_orthonormal_basis(normal: Vec3A) -> Mat3A {
+fn gtr2_importance_sample(normal: Vec3A, roughness: f32) -> Vec3A {
+    let r1 = rand::random::<f32>();
+    let r2 = rand::random::<f32>();
+
+    let alpha = roughness * roughness;
+    let alpha2 = alpha * alpha;
+
+    let cos_theta = ((1.0 - r1) / (r1 * (alpha2 - 1.0) + 1.0)).sqrt();
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+
+    let phi = 2.0 * PI * r2;
+    let cos_phi = phi.cos();
+    let sin_phi = phi.sin();
+
+    let x = sin_theta * cos_phi;
+    let y = sin_theta * sin_phi;
+    let z = cos_theta;
+
+    let tbn = create_orthonormal_basis(normal);
+    tbn.mul_vec3a(Vec3A::new(x, y, z))
+}
+
+pub(crate) fn create_orthonormal_basis(normal: Vec3A) -> Mat3A {
     let n = normal;
     let tangent = if n.x.abs() > n.y.abs() {
         Vec3A::new(n.z, 0.0, -n.x) / (n.x * n.x + n.z * n.z).sqrt()
@@ -34,3 +59,69 @@ fn create_orthonormal_basis(normal: Vec3A) -> Mat3A {
 
     Mat3A::from_cols(tangent, bitangent, n)
 }
+
+/// Builds a tangent frame around the normal, with the tangent and bitangent in the first two
+/// columns, and the tangent following the given direction of the surface.
+///
+/// Surfaces whose tangent degenerates fall back to [`create_orthonormal_basis`].
+fn create_tangent_frame(normal: Vec3A, tangent: Vec3A) -> Mat3A {
+    let tangent = (tangent - normal * normal.dot(tangent)).normalize_or_zero();
+
+    if tangent == Vec3A::ZERO {
+        return create_orthonormal_basis(normal);
+    }
+
+    Mat3A::from_cols(tangent, normal.cross(tangent), normal)
+}
+
+/// Returns the roughness of a specular lobe along the tangent and the bitangent, which
+/// `anisotropic` stretches apart.
+fn specular_alphas(material: &Material) -> (f32, f32) {
+    let aspect = (1.0 - 0.9 * material.anisotropic).sqrt();
+    let alpha = material.roughness * material.roughness;
+
+    ((alpha / aspect).max(1e-3), (alpha * aspect).max(1e-3))
+}
+
+/// Evaluates the anisotropic GTR2 (GGX) distribution for a half vector in tangent space.
+fn distribution_term_specular(half: Vec3A, alpha_x: f32, alpha_y: f32) -> f32 {
+    let x = half.x / alpha_x;
+    let y = half.y / alpha_y;
+    let denom_core = x * x + y * y + half.z * half.z;
+    let denom = std::f32::consts::PI * alpha_x * alpha_y * denom_core * denom_core;
+
+    1.0 / denom.max(1e-5)
+}
+
+/// Evaluates the anisotropic Smith masking-shadowing term for directions in tangent space.
+fn geometry_term_specular(view: Vec3A, light: Vec3A, alpha_x: f32, alpha_y: f32) -> f32 {
+    fn g1(w: Vec3A, alpha_x: f32, alpha_y: f32) -> f32 {
+        let n_dot_w = w.z.max(0.0);
+        let denom =
+            n_dot_w + ((w.x * alpha_x).powi(2) + (w.y * alpha_y).powi(2) + w.z * w.z).sqrt();
+        2.0 * n_dot_w / denom.max(1e-5)
+    }
+
+    g1(view, alpha_x, alpha_y) * g1(light, alpha_x, alpha_y)
+}
+
+/// Samples a half vector proportionally to the anisotropic GTR2 distribution times `n_dot_h`,
+/// around the normal in the last column of `tbn`.
+fn gtr2_anisotropic_importance_sample(tbn: Mat3A, alpha_x: f32, alpha_y: f32) -> Vec3A {
+    let r1 = rand::random::<f32>();
+    let r2 = rand::random::<f32>();
+
+    // the distribution is a stretched isotropic one, so sample its slope and stretch it
+    let tan_theta = (r1 / (1.0 - r1)).sqrt();
+    let phi = 2.0 * PI * r2;
+
+    let x = alpha_x * tan_theta * phi.cos();
+    let y = alpha_y * tan_theta * phi.sin();
+    let z = 1.0;
+
+    tbn.mul_vec3a(Vec3A::new(x, y, z).normalize())
+}
+
+fn ggx_pdf_specular(half: Vec3A, v_dot_h: f32, alpha_x: f32, alpha_y: f32) -> f32 {
+    distribution_term_specular(half, alpha_x, alpha_y) * half.z / (4.0 * v_dot_h)
+}
@@ -0,0 +1,135 @@
+use crate::{
+    brdf::{Brdf, BrdfEval, BrdfSample},
+    brdfs::{
+        create_tangent_frame, distribution_term_specular, geometry_term_specular, ggx_pdf_specular,
+        gtr2_anisotropic_importance_sample, specular_alphas,
+    },
+};
+use glam::Vec3A;
+use raytracer_core::material::Material;
+
+/// A metal, reflecting light according to the Fresnel equations of its complex index of
+/// refraction `eta + i k`.
+///
+/// Smooth surfaces (`roughness` close to zero) are perfect mirrors; rough surfaces use the
+/// anisotropic GGX microfacet model, stretched along the tangent by `anisotropic` like the
+/// specular lobe of the Disney BRDF. The material `albedo` tints the reflected light.
+#[derive(Debug, Clone, Copy)]
+pub struct ConductorBrdf {
+    /// real part of the index of refraction of the metal, relative to the outside
+    pub eta: Vec3A,
+    /// imaginary part of the index of refraction of the metal, which absorbs the light that is not
+    /// reflected
+    pub k: Vec3A,
+}
+
+impl ConductorBrdf {
+    pub fn new(eta: Vec3A, k: Vec3A) -> Self {
+        Self { eta, k }
+    }
+
+    /// Returns the fraction of unpolarized light reflected at `cos_theta` from the normal.
+    fn fresnel(&self, cos_theta: f32) -> Vec3A {
+        let cos2 = cos_theta.clamp(0.0, 1.0).powi(2);
+        let sin2 = 1.0 - cos2;
+        let eta2 = self.eta * self.eta;
+        let k2 = self.k * self.k;
+
+        let t0 = eta2 - k2 - sin2;
+        let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * k2).powf(0.5);
+        let a = (0.5 * (a2_plus_b2 + t0)).max(Vec3A::ZERO).powf(0.5);
+
+        let t1 = a2_plus_b2 + cos2;
+        let t2 = 2.0 * cos_theta * a;
+        let r_s = (t1 - t2) / (t1 + t2).max(Vec3A::splat(1e-5));
+
+        let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+        let t4 = t2 * sin2;
+        let r_p = r_s * (t3 - t4) / (t3 + t4).max(Vec3A::splat(1e-5));
+
+        (0.5 * (r_s + r_p)).clamp(Vec3A::ZERO, Vec3A::ONE)
+    }
+}
+
+impl Brdf for ConductorBrdf {
+    fn is_delta_surface(&self, material: &Material) -> bool {
+        material.roughness < 1e-5
+    }
+
+    fn eval(
+        &self,
+        view: Vec3A,
+        normal: Vec3A,
+        tangent: Vec3A,
+        light: Vec3A,
+        material: &Material,
+    ) -> BrdfEval {
+        if self.is_delta_surface(material) {
+            return BrdfEval::ZERO;
+        }
+
+        let n_dot_v = normal.dot(view);
+        let n_dot_l = normal.dot(light);
+
+        if n_dot_v < 1e-5 || n_dot_l < 1e-5 {
+            return BrdfEval::ZERO;
+        }
+
+        let half = (view + light).normalize();
+        let v_dot_h = view.dot(half);
+
+        let world_to_tangent = create_tangent_frame(normal, tangent).transpose();
+        let half_local = world_to_tangent.mul_vec3a(half);
+        let view_local = world_to_tangent.mul_vec3a(view);
+        let light_local = world_to_tangent.mul_vec3a(light);
+        let (alpha_x, alpha_y) = specular_alphas(material);
+
+        let d = distribution_term_specular(half_local, alpha_x, alpha_y);
+        let g = geometry_term_specular(view_local, light_local, alpha_x, alpha_y);
+        let f = self.fresnel(v_dot_h);
+
+        let f_r = material.albedo * f * d * g / (4.0 * n_dot_v * n_dot_l);
+        let pdf = ggx_pdf_specular(half_local, v_dot_h, alpha_x, alpha_y);
+
+        if pdf < 1e-5 {
+            return BrdfEval::ZERO;
+        }
+
+        BrdfEval { f_r, pdf }
+    }
+
+    fn sample(
+        &self,
+        view: Vec3A,
+        normal: Vec3A,
+        tangent: Vec3A,
+        material: &Material,
+    ) -> BrdfSample {
+        if self.is_delta_surface(material) {
+            return BrdfSample {
+                direction: (-view).reflect(normal),
+                attenuation: material.albedo * self.fresnel(normal.dot(view)),
+                pdf: 1.0,
+            };
+        }
+
+        let (alpha_x, alpha_y) = specular_alphas(material);
+        let half = gtr2_anisotropic_importance_sample(
+            create_tangent_frame(normal, tangent),
+            alpha_x,
+            alpha_y,
+        );
+        let light = (-view).reflect(half);
+        let BrdfEval { f_r, pdf } = self.eval(view, normal, tangent, light, material);
+
+        if pdf < 1e-5 {
+            return BrdfSample::ZERO;
+        }
+
+        BrdfSample {
+            direction: light,
+            attenuation: f_r * normal.dot(light) / pdf,
+            pdf,
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use crate::{
+    brdf::{Brdf, BrdfEval, BrdfSample},
+    brdfs::gtr2_importance_sample,
+};
+use glam::Vec3A;
+use raytracer_core::material::Material;
+use std::f32::consts::PI;
+
+/// A glass-like surface that both reflects and refracts light, such as glass or water.
+///
+/// Smooth surfaces (`roughness` close to zero) are perfect specular interfaces; rough surfaces
+/// use the GGX microfacet model for reflection and transmission from
+/// Walter et al., "Microfacet Models for Refraction through Rough Surfaces".
+/// The material `albedo` tints transmitted light.
+///
+/// `normal` must be the outward normal of the surface, see [`Brdf::is_transmissive`].
+#[derive(Debug, Clone, Copy)]
+pub struct DielectricBrdf {
+    /// index of refraction of the inside of the object, relative to the outside
+    pub ior: f32,
+}
+
+impl DielectricBrdf {
+    pub fn new(ior: f32) -> Self {
+        Self { ior }
+    }
+
+    /// Returns the ratio of the index of refraction on the far side of the surface to the one on
+    /// the side `direction` points to.
+    fn relative_ior(&self, normal: Vec3A, direction: Vec3A) -> f32 {
+        if normal.dot(direction) >= 0.0 {
+            self.ior
+        } else {
+            self.ior.recip()
+        }
+    }
+}
+
+impl Default for DielectricBrdf {
+    fn default() -> Self {
+        Self::new(1.5)
+    }
+}
+
+impl Brdf for DielectricBrdf {
+    fn is_delta_surface(&self, material: &Material) -> bool {
+        material.roughness < 1e-5
+    }
+
+    fn is_transmissive(&self, _material: &Material) -> bool {
+        true
+    }
+
+    fn eval(
+        &self,
+        view: Vec3A,
+        normal: Vec3A,
+        _tangent: Vec3A,
+        light: Vec3A,
+        material: &Material,
+    ) -> BrdfEval {
+        if self.is_delta_surface(material) {
+            return BrdfEval::ZERO;
+        }
+
+        let n_dot_v = normal.dot(view);
+        let n_dot_l = normal.dot(light);
+
+        if n_dot_v.abs() < 1e-5 || n_dot_l.abs() < 1e-5 {
+            return BrdfEval::ZERO;
+        }
+
+        let is_reflection = n_dot_v * n_dot_l > 0.0;
+        let eta = self.relative_ior(normal, view);
+
+        // the generalized half vector, facing the outside of the object
+        let half = if is_reflection {
+            view + light
+        } else {
+            view + light * eta
+        };
+        let half = half.normalize_or_zero();
+        let half = if half.dot(normal) < 0.0 { -half } else { half };
+
+        if half == Vec3A::ZERO {
+            return BrdfEval::ZERO;
+        }
+
+        let n_dot_h = normal.dot(half);
+        let v_dot_h = view.dot(half);
+        let l_dot_h = light.dot(half);
+
+        // discard microfacets that are back-facing with respect to either direction
+        if v_dot_h * n_dot_v <= 0.0 || l_dot_h * n_dot_l <= 0.0 {
+            return BrdfEval::ZERO;
+        }
+
+        let alpha = material.roughness * material.roughness;
+        let d = distribution_term(n_dot_h, alpha);
+        let g = smith_g1(n_dot_v, alpha) * smith_g1(n_dot_l, alpha);
+        let f = fresnel_dielectric(v_dot_h, self.ior);
+        // the density of the sampled microfacet normals
+        let pdf_half = d * n_dot_h;
+
+        let (f_r, pdf) = if is_reflection {
+            let f_r = Vec3A::splat(d * g * f / (4.0 * n_dot_v.abs() * n_dot_l.abs()));
+            let pdf = pdf_half / (4.0 * v_dot_h.abs()) * f;
+            (f_r, pdf)
+        } else {
+            let denom = (l_dot_h + v_dot_h / eta).powi(2);
+            let t = 1.0 - f;
+            // radiance is compressed into a smaller solid angle when entering a denser medium
+            let f_t =
+                d * g * t * (l_dot_h * v_dot_h / (n_dot_l * n_dot_v * denom)).abs() / (eta * eta);
+            let pdf = pdf_half * l_dot_h.abs() / denom * t;
+            (material.albedo * f_t, pdf)
+        };
+
+        if pdf < 1e-5 {
+            return BrdfEval::ZERO;
+        }
+
+        BrdfEval { f_r, pdf }
+    }
+
+    fn sample(
+        &self,
+        view: Vec3A,
+        normal: Vec3A,
+        tangent: Vec3A,
+        material: &Material,
+    ) -> BrdfSample {
+        let eta = self.relative_ior(normal, view);
+        // the normal on the same side as the view direction
+        let facing_normal = if normal.dot(view) >= 0.0 {
+            normal
+        } else {
+            -normal
+        };
+
+        if self.is_delta_surface(material) {
+            let f = fresnel_dielectric(normal.dot(view), self.ior);
+            let refracted = (-view).refract(facing_normal, eta.recip());
+
+            // choose between reflection and refraction proportionally to the Fresnel term,
+            // so that the attenuation does not include it
+            let (direction, attenuation) = if refracted == Vec3A::ZERO || rand::random::<f32>() < f
+            {
+                ((-view).reflect(facing_normal), Vec3A::ONE)
+            } else {
+                (refracted, material.albedo / (eta * eta))
+            };
+
+            return BrdfSample {
+                direction,
+                attenuation,
+                pdf: 1.0,
+            };
+        }
+
+        let half = gtr2_importance_sample(facing_normal, material.roughness);
+        let v_dot_h = view.dot(half);
+
+        if v_dot_h <= 0.0 {
+            return BrdfSample::ZERO;
+        }
+
+        let f = fresnel_dielectric(normal.dot(half).signum() * v_dot_h, self.ior);
+        let refracted = (-view).refract(half, eta.recip());
+
+        let is_reflection = refracted == Vec3A::ZERO || rand::random::<f32>() < f;
+        let light = if is_reflection {
+            (-view).reflect(half)
+        } else {
+            refracted.normalize()
+        };
+
+        if (normal.dot(view) * normal.dot(light) > 0.0) != is_reflection {
+            // the microfacet sent the light to the other side of the macro surface,
+            // which the microfacet model cannot account for
+            return BrdfSample::ZERO;
+        }
+
+        let BrdfEval { f_r, pdf } = self.eval(view, normal, tangent, light, material);
+
+        if pdf < 1e-5 {
+            return BrdfSample::ZERO;
+        }
+
+        let n_dot_l = normal.dot(light).abs();
+        let attenuation = f_r * n_dot_l / pdf;
+
+        BrdfSample {
+            direction: light,
+            attenuation,
+            pdf,
+        }
+    }
+}
+
+/// Computes the fraction of light reflected by a smooth dielectric interface.
+///
+/// `cos_theta` is measured against the outward normal, so it is negative for light arriving from
+/// inside the object.
+fn fresnel_dielectric(cos_theta: f32, ior: f32) -> f32 {
+    let (cos_theta, eta) = if cos_theta < 0.0 {
+        (-cos_theta, ior.recip())
+    } else {
+        (cos_theta, ior)
+    };
+    let cos_theta = cos_theta.min(1.0);
+
+    let sin2_theta_t = (1.0 - cos_theta * cos_theta) / (eta * eta);
+
+    if sin2_theta_t >= 1.0 {
+        // total internal reflection
+        return 1.0;
+    }
+
+    let cos_theta_t = (1.0 - sin2_theta_t).sqrt();
+    let r_parallel = (eta * cos_theta - cos_theta_t) / (eta * cos_theta + cos_theta_t);
+    let r_perpendicular = (cos_theta - eta * cos_theta_t) / (cos_theta + eta * cos_theta_t);
+
+    (r_parallel * r_parallel + r_perpendicular * r_perpendicular) / 2.0
+}
+
+fn distribution_term(n_dot_h: f32, alpha: f32) -> f32 {
+    let alpha2 = alpha * alpha;
+
+    let denom_core = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
+    let denom = PI * denom_core * denom_core;
+
+    alpha2 / denom.max(1e-5)
+}
+
+fn smith_g1(n_dot_x: f32, alpha: f32) -> f32 {
+    let cos2_theta = n_dot_x * n_dot_x;
+    let tan2_theta = (1.0 - cos2_theta).max(0.0) / cos2_theta.max(1e-8);
+
+    2.0 / (1.0 + (1.0 + alpha * alpha * tan2_theta).sqrt())
+}
@@ -1,6 +1,10 @@
 use crate::{
-    brdf::{Brdf, BrdfEval, BrdfSample},
-    brdfs::{create_orthonormal_basis, lerp, random_cosine_direction},
+    brdf::{Brdf, BrdfEval, BrdfSample, DiffuseLayer},
+    brdfs::{
+        create_orthonormal_basis, create_tangent_frame, distribution_term_specular,
+        geometry_term_specular, ggx_pdf_specular, gtr2_anisotropic_importance_sample, lerp,
+        random_cosine_direction, specular_alphas,
+    },
 };
 use glam::Vec3A;
 use raytracer_core::material::Material;
@@ -30,6 +34,18 @@ impl DisneyBrdf {
 
         (p_clearcoat_lobe, p_specular_lobe, p_diffuse_lobe)
     }
+
+    /// Returns the weight of the diffuse lobe, which only dielectrics have.
+    fn compute_diffuse_weight(material: &Material) -> f32 {
+        (1.0 - material.metallic) * (1.0 - material.specular)
+    }
+
+    /// Returns the specular reflectance of a dielectric at normal incidence,
+    /// tinted towards the hue of the base color by `specular_tint`.
+    fn compute_dielectric_f0(material: &Material) -> Vec3A {
+        let tint = tint_color(compute_tint(material.albedo), material.specular_tint);
+        material.specular * 0.08 * tint
+    }
 }
 
 impl Brdf for DisneyBrdf {
@@ -37,9 +53,24 @@ impl Brdf for DisneyBrdf {
         material.roughness < 1e-5
     }
 
-    fn eval(&self, view: Vec3A, normal: Vec3A, light: Vec3A, material: &Material) -> BrdfEval {
+    fn eval(
+        &self,
+        view: Vec3A,
+        normal: Vec3A,
+        tangent: Vec3A,
+        light: Vec3A,
+        material: &Material,
+    ) -> BrdfEval {
         let half = (view + light).normalize();
 
+        // the specular lobe is stretched along the tangent when the material is anisotropic
+        let tbn = create_tangent_frame(normal, tangent);
+        let world_to_tangent = tbn.transpose();
+        let half_local = world_to_tangent.mul_vec3a(half);
+        let view_local = world_to_tangent.mul_vec3a(view);
+        let light_local = world_to_tangent.mul_vec3a(light);
+        let (alpha_x, alpha_y) = specular_alphas(material);
+
         let n_dot_h = normal.dot(half);
         let n_dot_l = normal.dot(light);
         let n_dot_v = normal.dot(view);
@@ -51,31 +82,44 @@ impl Brdf for DisneyBrdf {
         }
 
         let pdf_clearcoat: f32 = ggx_pdf_clearcoat(n_dot_h, v_dot_h, material.clearcoat_gloss);
-        let pdf_specular = ggx_pdf_specular(n_dot_h, v_dot_h, material.roughness);
+        let pdf_specular = ggx_pdf_specular(half_local, v_dot_h, alpha_x, alpha_y);
         let pdf_diffuse = n_dot_l.max(0.0) * FRAC_1_PI;
 
-        let dielectric_f0 = Vec3A::splat(material.specular * 0.08);
+        let dielectric_f0 = Self::compute_dielectric_f0(material);
         let metallic_f0 = material.albedo;
         let f0 = dielectric_f0.lerp(metallic_f0, material.metallic);
 
         let clearcoat_term =
             clearcoat_term(n_dot_h, n_dot_v, n_dot_l, l_dot_h, material.clearcoat_gloss);
-        let specular_term =
-            specular_term(n_dot_h, n_dot_v, n_dot_l, l_dot_h, material.roughness, f0);
+        let specular_term = specular_term(
+            half_local,
+            view_local,
+            light_local,
+            l_dot_h,
+            alpha_x,
+            alpha_y,
+            f0,
+        );
         let diffuse_term = diffuse_term(
             n_dot_v,
             n_dot_l,
             l_dot_h,
             material.roughness,
+            material.subsurface,
             material.albedo,
         );
+        let sheen_color = tint_color(compute_tint(material.albedo), material.sheen_tint);
+        let sheen_term = sheen_term(l_dot_h, material.sheen, sheen_color);
 
         let (p_clearcoat_lobe, p_specular_lobe, p_diffuse_lobe) =
             Self::compute_lobe_weights(material);
 
-        let diffuse_weight = (1.0 - material.metallic) * (1.0 - material.specular);
-        let f_r =
-            clearcoat_term + (1.0 - diffuse_weight) * specular_term + diffuse_weight * diffuse_term;
+        let diffuse_weight = Self::compute_diffuse_weight(material);
+        // sheen is a grazing retro-reflection of the diffuse layer, so metals do not have it
+        let f_r = clearcoat_term
+            + (1.0 - diffuse_weight) * specular_term
+            + diffuse_weight * diffuse_term
+            + (1.0 - material.metallic) * sheen_term;
         let pdf = p_clearcoat_lobe * pdf_clearcoat
             + p_specular_lobe * pdf_specular
             + p_diffuse_lobe * pdf_diffuse;
@@ -87,14 +131,20 @@ impl Brdf for DisneyBrdf {
         BrdfEval { f_r, pdf }
     }
 
-    fn sample(&self, view: Vec3A, normal: Vec3A, material: &Material) -> BrdfSample {
+    fn sample(
+        &self,
+        view: Vec3A,
+        normal: Vec3A,
+        tangent: Vec3A,
+        material: &Material,
+    ) -> BrdfSample {
         if self.is_delta_surface(material) {
             let light = (-view).reflect(normal);
             let n_dot_v = normal.dot(view).max(0.0);
 
             let metallic_attenuation = material.albedo;
             let dielectric_attenuation =
-                fresnel_term(n_dot_v, Vec3A::splat(material.specular * 0.08));
+                fresnel_term(n_dot_v, Self::compute_dielectric_f0(material));
             let attenuation = dielectric_attenuation.lerp(metallic_attenuation, material.metallic);
 
             return BrdfSample {
@@ -112,12 +162,17 @@ impl Brdf for DisneyBrdf {
             let half = gtr1_importance_sample(normal, material.clearcoat_gloss);
             (-view).reflect(half)
         } else if dice < p_clearcoat_lobe + p_specular_lobe {
-            let half = gtr2_importance_sample(normal, material.roughness);
+            let (alpha_x, alpha_y) = specular_alphas(material);
+            let half = gtr2_anisotropic_importance_sample(
+                create_tangent_frame(normal, tangent),
+                alpha_x,
+                alpha_y,
+            );
             (-view).reflect(half)
         } else {
             random_cosine_direction(normal)
         };
-        let BrdfEval { f_r, pdf } = self.eval(view, normal, light, material);
+        let BrdfEval { f_r, pdf } = self.eval(view, normal, tangent, light, material);
 
         if pdf < 1e-5 {
             return BrdfSample::ZERO;
@@ -132,16 +187,33 @@ impl Brdf for DisneyBrdf {
             pdf,
         }
     }
-}
 
-fn distribution_term_specular(n_dot_h: f32, roughness: f32) -> f32 {
-    let alpha = roughness * roughness;
-    let alpha2 = alpha * alpha;
+    fn diffuse_layer(&self, material: &Material) -> Option<DiffuseLayer> {
+        Some(DiffuseLayer {
+            weight: Self::compute_diffuse_weight(material),
+            f0: Self::compute_dielectric_f0(material),
+        })
+    }
 
-    let denom_core = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
-    let denom = std::f32::consts::PI * denom_core * denom_core;
+    fn eval_diffuse(&self, view: Vec3A, normal: Vec3A, light: Vec3A, material: &Material) -> Vec3A {
+        let n_dot_l = normal.dot(light);
+
+        if n_dot_l < 1e-5 {
+            return Vec3A::ZERO;
+        }
 
-    alpha2 / denom.max(1e-5)
+        let l_dot_h = light.dot((view + light).normalize());
+        let diffuse_term = diffuse_term(
+            normal.dot(view),
+            n_dot_l,
+            l_dot_h,
+            material.roughness,
+            material.subsurface,
+            material.albedo,
+        );
+
+        Self::compute_diffuse_weight(material) * diffuse_term
+    }
 }
 
 fn distribution_term_clearcoat(n_dot_h: f32, gloss: f32) -> f32 {
@@ -166,6 +238,26 @@ fn fresnel_term(l_dot_h: f32, f0: Vec3A) -> Vec3A {
     f0 + (Vec3A::ONE - f0) * (1.0 - l_dot_h).powf(5.0)
 }
 
+fn sheen_term(l_dot_h: f32, sheen: f32, sheen_color: Vec3A) -> Vec3A {
+    sheen_color * sheen * (1.0 - l_dot_h).powf(5.0)
+}
+
+/// Returns the hue and saturation of the color, with its luminance normalized to one.
+fn compute_tint(color: Vec3A) -> Vec3A {
+    let luminance = color.dot(Vec3A::new(0.3, 0.6, 0.1));
+
+    if luminance > 0.0 {
+        color / luminance
+    } else {
+        Vec3A::ONE
+    }
+}
+
+/// Blends white towards the tint, by a separate amount for each channel.
+fn tint_color(tint: Vec3A, amount: Vec3A) -> Vec3A {
+    Vec3A::ONE + (tint - Vec3A::ONE) * amount
+}
+
 fn geometry_term(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
     fn g1(n_dot_x: f32, k: f32) -> f32 {
         n_dot_x / (n_dot_x * (1.0 - k) + k).max(1e-5)
@@ -181,28 +273,39 @@ fn diffuse_term(
     n_dot_l: f32,
     l_dot_h: f32,
     roughness: f32,
+    subsurface: f32,
     base_color: Vec3A,
 ) -> Vec3A {
+    let fl = (1.0 - n_dot_l).powf(5.0);
+    let fv = (1.0 - n_dot_v).powf(5.0);
+
     let fd90 = 0.5 + 2.0 * roughness * l_dot_h * l_dot_h;
-    let fdv = 1.0 + (fd90 - 1.0) * (1.0 - n_dot_v).powf(5.0);
-    let fdl = 1.0 + (fd90 - 1.0) * (1.0 - n_dot_l).powf(5.0);
+    let fd = lerp(1.0, fd90, fl) * lerp(1.0, fd90, fv);
 
-    base_color * (fdv * fdl * FRAC_1_PI).max(0.0)
+    // Hanrahan-Krueger inspired approximation of subsurface scattering,
+    // which flattens the diffuse response and brightens its rim
+    let fss90 = roughness * l_dot_h * l_dot_h;
+    let fss = lerp(1.0, fss90, fl) * lerp(1.0, fss90, fv);
+    let ss = 1.25 * (fss * ((n_dot_l + n_dot_v).max(1e-5).recip() - 0.5) + 0.5);
+
+    base_color * (lerp(fd, ss, subsurface) * FRAC_1_PI).max(0.0)
 }
 
+/// `half`, `view` and `light` are in tangent space.
 fn specular_term(
-    n_dot_h: f32,
-    n_dot_v: f32,
-    n_dot_l: f32,
+    half: Vec3A,
+    view: Vec3A,
+    light: Vec3A,
     l_dot_h: f32,
-    roughness: f32,
+    alpha_x: f32,
+    alpha_y: f32,
     f0: Vec3A,
 ) -> Vec3A {
-    let denom = (4.0 * n_dot_v * n_dot_l).max(1e-5);
+    let denom = (4.0 * view.z * light.z).max(1e-5);
 
-    distribution_term_specular(n_dot_h, roughness)
+    distribution_term_specular(half, alpha_x, alpha_y)
         * fresnel_term(l_dot_h, f0)
-        * geometry_term(n_dot_v, n_dot_l, roughness)
+        * geometry_term_specular(view, light, alpha_x, alpha_y)
         / denom
 }
 
@@ -215,28 +318,6 @@ fn clearcoat_term(n_dot_h: f32, n_dot_v: f32, n_dot_l: f32, l_dot_h: f32, gloss:
         / denom
 }
 
-fn gtr2_importance_sample(normal: Vec3A, roughness: f32) -> Vec3A {
-    let r1 = rand::random::<f32>();
-    let r2 = rand::random::<f32>();
-
-    let alpha = roughness * roughness;
-    let alpha2 = alpha * alpha;
-
-    let cos_theta = ((1.0 - r1) / (r1 * (alpha2 - 1.0) + 1.0)).sqrt();
-    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
-
-    let phi = 2.0 * PI * r2;
-    let cos_phi = phi.cos();
-    let sin_phi = phi.sin();
-
-    let x = sin_theta * cos_phi;
-    let y = sin_theta * sin_phi;
-    let z = cos_theta;
-
-    let tbn = create_orthonormal_basis(normal);
-    tbn.mul_vec3a(Vec3A::new(x, y, z))
-}
-
 fn gtr1_importance_sample(normal: Vec3A, gloss: f32) -> Vec3A {
     let r1: f32 = rand::random::<f32>();
     let r2 = rand::random::<f32>();
@@ -260,10 +341,175 @@ fn gtr1_importance_sample(normal: Vec3A, gloss: f32) -> Vec3A {
     tbn.mul_vec3a(Vec3A::new(x, y, z))
 }
 
-fn ggx_pdf_specular(n_dot_h: f32, v_dot_h: f32, roughness: f32) -> f32 {
-    distribution_term_specular(n_dot_h, roughness) * n_dot_h / (4.0 * v_dot_h)
-}
-
 fn ggx_pdf_clearcoat(n_dot_h: f32, v_dot_h: f32, gloss: f32) -> f32 {
     distribution_term_clearcoat(n_dot_h, gloss) * n_dot_h / (4.0 * v_dot_h)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VIEW_ANGLES: [f32; 4] = [1.0, 0.5, 0.2, 0.05];
+    const ROUGHNESSES: [f32; 3] = [0.2, 0.5, 1.0];
+
+    fn white_material(roughness: f32) -> Material {
+        Material {
+            is_emissive: false,
+            emission: Vec3A::ZERO,
+            albedo: Vec3A::ONE,
+            subsurface: 0.0,
+            metallic: 0.0,
+            specular: 0.0,
+            specular_tint: Vec3A::ZERO,
+            roughness,
+            anisotropic: 0.0,
+            sheen: 0.0,
+            sheen_tint: Vec3A::ZERO,
+            clearcoat: 0.0,
+            clearcoat_gloss: 0.0,
+        }
+    }
+
+    fn luminance(color: Vec3A) -> f32 {
+        color.dot(Vec3A::new(0.3, 0.6, 0.1))
+    }
+
+    /// Integrates the light reflected towards a view at `cos_view` from the normal, under a
+    /// uniform white environment, on a fixed grid of light directions.
+    fn directional_albedo(material: &Material, cos_view: f32) -> Vec3A {
+        const STEPS: usize = 128;
+
+        let view = Vec3A::new((1.0 - cos_view * cos_view).sqrt(), 0.0, cos_view);
+        let mut albedo = Vec3A::ZERO;
+
+        // light directions are uniform in solid angle, in rings of equal cosine
+        for i in 0..STEPS {
+            let cos_light = (i as f32 + 0.5) / STEPS as f32;
+            let sin_light = (1.0 - cos_light * cos_light).sqrt();
+
+            for j in 0..STEPS {
+                let phi = 2.0 * PI * (j as f32 + 0.5) / STEPS as f32;
+                let light = Vec3A::new(sin_light * phi.cos(), sin_light * phi.sin(), cos_light);
+                let BrdfEval { f_r, .. } =
+                    DisneyBrdf.eval(view, Vec3A::Z, Vec3A::X, light, material);
+
+                albedo += f_r * cos_light;
+            }
+        }
+
+        albedo * 2.0 * PI / (STEPS * STEPS) as f32
+    }
+
+    /// Returns the light that adding `lobe` to `material` reflects towards the view.
+    fn lobe_albedo(material: &Material, lobe: &Material, cos_view: f32) -> Vec3A {
+        directional_albedo(lobe, cos_view) - directional_albedo(material, cos_view)
+    }
+
+    #[test]
+    fn sheen_only_brightens_grazing_angles() {
+        for roughness in ROUGHNESSES {
+            let material = white_material(roughness);
+            let sheen = Material {
+                sheen: 1.0,
+                ..material.clone()
+            };
+            let albedos = VIEW_ANGLES.map(|cos_view| lobe_albedo(&material, &sheen, cos_view).x);
+
+            assert!(
+                albedos[0].abs() < 1e-3,
+                "sheen at normal incidence: {albedos:?}"
+            );
+            assert!(
+                albedos.is_sorted() && albedos[3] < 0.25,
+                "sheen towards grazing angles: {albedos:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn sheen_is_missing_from_metals() {
+        let metal = Material {
+            metallic: 1.0,
+            ..white_material(0.5)
+        };
+        let sheen = Material {
+            sheen: 1.0,
+            ..metal.clone()
+        };
+
+        for cos_view in VIEW_ANGLES {
+            let albedo = lobe_albedo(&metal, &sheen, cos_view);
+            assert!(albedo.abs().max_element() < 1e-5, "metal sheen: {albedo}");
+        }
+    }
+
+    #[test]
+    fn sheen_tint_keeps_the_luminance_of_the_sheen() {
+        let material = Material {
+            albedo: Vec3A::new(0.8, 0.2, 0.1),
+            ..white_material(0.5)
+        };
+        let sheen = Material {
+            sheen: 1.0,
+            ..material.clone()
+        };
+        let tinted = Material {
+            sheen_tint: Vec3A::ONE,
+            ..sheen.clone()
+        };
+
+        for cos_view in VIEW_ANGLES {
+            let white = lobe_albedo(&material, &sheen, cos_view);
+            let colored = lobe_albedo(&material, &tinted, cos_view);
+
+            assert!((luminance(white) - luminance(colored)).abs() < 1e-4);
+            assert!(
+                colored.x > colored.y && colored.y > colored.z,
+                "tinted sheen: {colored}"
+            );
+        }
+    }
+
+    #[test]
+    fn specular_lobe_does_not_create_energy() {
+        for roughness in ROUGHNESSES {
+            let specular = Material {
+                specular: 1.0,
+                albedo: Vec3A::new(0.8, 0.2, 0.1),
+                specular_tint: Vec3A::ONE,
+                ..white_material(roughness)
+            };
+
+            for cos_view in VIEW_ANGLES {
+                let albedo = directional_albedo(&specular, cos_view);
+                assert!(albedo.max_element() <= 1.0, "specular albedo: {albedo}");
+            }
+        }
+    }
+
+    #[test]
+    fn specular_tint_keeps_the_luminance_of_the_reflection() {
+        for roughness in ROUGHNESSES {
+            let specular = Material {
+                specular: 1.0,
+                albedo: Vec3A::new(0.8, 0.2, 0.1),
+                ..white_material(roughness)
+            };
+            let tinted = Material {
+                specular_tint: Vec3A::ONE,
+                ..specular.clone()
+            };
+
+            for cos_view in VIEW_ANGLES {
+                let white = directional_albedo(&specular, cos_view);
+                let colored = directional_albedo(&tinted, cos_view);
+
+                assert!((luminance(white) - luminance(colored)).abs() < 1e-4);
+                assert!(
+                    colored.x > colored.y && colored.y > colored.z,
+                    "tinted: {colored}"
+                );
+            }
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{
-    brdf::{Brdf, BrdfEval, BrdfSample},
+    brdf::{Brdf, BrdfEval, BrdfSample, DiffuseLayer},
     brdfs::random_cosine_direction,
 };
 use glam::Vec3A;
@@ -14,7 +14,14 @@ impl Brdf for LambertianBrdf {
         false
     }
 
-    fn eval(&self, _view: Vec3A, normal: Vec3A, light: Vec3A, material: &Material) -> BrdfEval {
+    fn eval(
+        &self,
+        _view: Vec3A,
+        normal: Vec3A,
+        _tangent: Vec3A,
+        light: Vec3A,
+        material: &Material,
+    ) -> BrdfEval {
         if normal.dot(light) <= 0.0 {
             return BrdfEval::ZERO;
         }
@@ -25,7 +32,13 @@ impl Brdf for LambertianBrdf {
         }
     }
 
-    fn sample(&self, _view: Vec3A, normal: Vec3A, material: &Material) -> BrdfSample {
+    fn sample(
+        &self,
+        _view: Vec3A,
+        normal: Vec3A,
+        _tangent: Vec3A,
+        material: &Material,
+    ) -> BrdfSample {
         let light = random_cosine_direction(normal);
         let pdf = normal.dot(light).max(0.0) * FRAC_1_PI;
         let attenuation = material.albedo;
@@ -36,4 +49,15 @@ impl Brdf for LambertianBrdf {
             pdf,
         }
     }
+
+    fn diffuse_layer(&self, _material: &Material) -> Option<DiffuseLayer> {
+        Some(DiffuseLayer {
+            weight: 1.0,
+            f0: Vec3A::ZERO,
+        })
+    }
+
+    fn eval_diffuse(&self, view: Vec3A, normal: Vec3A, light: Vec3A, material: &Material) -> Vec3A {
+        self.eval(view, normal, Vec3A::ZERO, light, material).f_r
+    }
 }
@@ -0,0 +1,240 @@
+use crate::hdr::{HdrError, HdrImage, load_hdr};
+use glam::Vec3A;
+use std::{f32::consts::PI, path::Path};
+
+/// Light arriving from infinitely far away, which is seen by rays that escape the scene.
+pub trait Environment: Send + Sync {
+    /// Returns the radiance arriving along the opposite of `direction`,
+    /// i.e. the radiance seen when looking towards `direction`.
+    fn radiance(&self, direction: Vec3A) -> Vec3A;
+    /// Returns the probability density of [`Environment::sample`] choosing `direction`.
+    fn pdf(&self, direction: Vec3A) -> f32;
+    fn sample(&self) -> EnvironmentSample;
+}
+
+/// Light arriving from infinitely far away in every direction, such as the sky,
+/// described by an equirectangular (latitude-longitude) image.
+///
+/// The top row of the image is straight up (`+Y`), and the center of the image looks towards `-Z`.
+/// Directions are importance sampled proportionally to the luminance of the image.
+#[derive(Debug, Clone)]
+pub struct EnvironmentMap {
+    image: HdrImage,
+    intensity: f32,
+    distribution: Distribution2D,
+}
+
+#[derive(Debug, Clone)]
+pub struct EnvironmentSample {
+    pub direction: Vec3A,
+    pub radiance: Vec3A,
+    /// probability density of the direction, with respect to solid angle
+    pub pdf: f32,
+}
+
+impl EnvironmentMap {
+    pub fn new(image: HdrImage) -> Self {
+        let distribution = Distribution2D::new(
+            (0..image.height)
+                .map(|y| {
+                    // rows near the poles cover a smaller solid angle
+                    let sin_theta = ((y as f32 + 0.5) / image.height as f32 * PI).sin();
+
+                    (0..image.width)
+                        .map(|x| luminance(image.pixel(x, y)) * sin_theta)
+                        .collect()
+                })
+                .collect(),
+        );
+
+        Self {
+            image,
+            intensity: 1.0,
+            distribution,
+        }
+    }
+
+    /// Loads an equirectangular Radiance `.hdr` image.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, HdrError> {
+        load_hdr(path).map(Self::new)
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    pub fn intensity(&self) -> f32 {
+        self.intensity
+    }
+
+    /// Returns the total power arriving from the environment, weighted by luminance.
+    pub fn luminous_power(&self) -> f32 {
+        // the distribution is defined over the unit square, which maps to 2π² of (θ, φ) space
+        self.distribution.integral() * 2.0 * PI * PI * self.intensity
+    }
+}
+
+impl Environment for EnvironmentMap {
+    fn radiance(&self, direction: Vec3A) -> Vec3A {
+        let (u, v) = direction_to_uv(direction);
+        let x = ((u * self.image.width as f32) as usize).min(self.image.width - 1);
+        let y = ((v * self.image.height as f32) as usize).min(self.image.height - 1);
+
+        self.image.pixel(x, y) * self.intensity
+    }
+
+    fn pdf(&self, direction: Vec3A) -> f32 {
+        let (u, v) = direction_to_uv(direction);
+        let sin_theta = (v * PI).sin();
+
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+
+        self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta)
+    }
+
+    fn sample(&self) -> EnvironmentSample {
+        let (u, v, pdf_uv) = self
+            .distribution
+            .sample(rand::random::<f32>(), rand::random::<f32>());
+        let sin_theta = (v * PI).sin();
+        let direction = uv_to_direction(u, v);
+
+        if sin_theta <= 0.0 || pdf_uv <= 0.0 {
+            return EnvironmentSample {
+                direction,
+                radiance: Vec3A::ZERO,
+                pdf: 0.0,
+            };
+        }
+
+        EnvironmentSample {
+            direction,
+            radiance: self.radiance(direction),
+            pdf: pdf_uv / (2.0 * PI * PI * sin_theta),
+        }
+    }
+}
+
+pub(crate) fn luminance(color: Vec3A) -> f32 {
+    color.dot(Vec3A::new(0.2126, 0.7152, 0.0722))
+}
+
+fn direction_to_uv(direction: Vec3A) -> (f32, f32) {
+    let phi = direction.x.atan2(-direction.z);
+    let theta = direction.y.clamp(-1.0, 1.0).acos();
+
+    (0.5 + phi / (2.0 * PI), theta / PI)
+}
+
+pub(crate) fn uv_to_direction(u: f32, v: f32) -> Vec3A {
+    let phi = (u - 0.5) * 2.0 * PI;
+    let theta = v * PI;
+    let (sin_theta, cos_theta) = theta.sin_cos();
+
+    Vec3A::new(sin_theta * phi.sin(), cos_theta, -sin_theta * phi.cos())
+}
+
+/// A piecewise constant distribution over `[0, 1)`.
+#[derive(Debug, Clone)]
+struct Distribution1D {
+    func: Vec<f32>,
+    cdf: Vec<f32>,
+    /// integral of `func` over `[0, 1)`
+    integral: f32,
+}
+
+impl Distribution1D {
+    fn new(func: Vec<f32>) -> Self {
+        let n = func.len() as f32;
+        let mut cdf = Vec::with_capacity(func.len() + 1);
+        let mut sum = 0.0;
+
+        cdf.push(0.0);
+        for value in &func {
+            sum += value.max(0.0) / n;
+            cdf.push(sum);
+        }
+
+        if sum > 0.0 {
+            cdf.iter_mut().for_each(|value| *value /= sum);
+        } else {
+            // nothing to importance sample; fall back to a uniform distribution
+            cdf.iter_mut()
+                .enumerate()
+                .for_each(|(i, value)| *value = i as f32 / n);
+        }
+
+        Self {
+            func,
+            cdf,
+            integral: sum,
+        }
+    }
+
+    /// Returns the value of the density at the given bucket.
+    fn pdf(&self, index: usize) -> f32 {
+        if self.integral > 0.0 {
+            self.func[index].max(0.0) / self.integral
+        } else {
+            1.0
+        }
+    }
+
+    fn index_of(&self, x: f32) -> usize {
+        ((x * self.func.len() as f32) as usize).min(self.func.len() - 1)
+    }
+
+    /// Maps a uniform random number to a sample, returning the sample, its density and its bucket.
+    fn sample(&self, random: f32) -> (f32, f32, usize) {
+        let index =
+            (self.cdf.partition_point(|&value| value <= random) - 1).min(self.func.len() - 1);
+        let width = self.cdf[index + 1] - self.cdf[index];
+        let offset = if width > 0.0 {
+            (random - self.cdf[index]) / width
+        } else {
+            0.0
+        };
+        let x = ((index as f32 + offset) / self.func.len() as f32).min(1.0 - f32::EPSILON);
+
+        (x, self.pdf(index), index)
+    }
+}
+
+/// A piecewise constant distribution over `[0, 1)²`, sampled by first choosing a row and then a
+/// column within it.
+#[derive(Debug, Clone)]
+struct Distribution2D {
+    rows: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    fn new(func: Vec<Vec<f32>>) -> Self {
+        let rows: Vec<_> = func.into_iter().map(Distribution1D::new).collect();
+        let marginal = Distribution1D::new(rows.iter().map(|row| row.integral).collect());
+
+        Self { rows, marginal }
+    }
+
+    fn integral(&self) -> f32 {
+        self.marginal.integral
+    }
+
+    fn pdf(&self, u: f32, v: f32) -> f32 {
+        let y = self.marginal.index_of(v);
+        let row = &self.rows[y];
+
+        self.marginal.pdf(y) * row.pdf(row.index_of(u))
+    }
+
+    /// Returns the sampled `(u, v)` along with its density.
+    fn sample(&self, random_u: f32, random_v: f32) -> (f32, f32, f32) {
+        let (v, pdf_v, y) = self.marginal.sample(random_v);
+        let (u, pdf_u, _) = self.rows[y].sample(random_u);
+
+        (u, v, pdf_u * pdf_v)
+    }
+}
@@ -0,0 +1,318 @@
+use glam::Vec3A;
+use std::{
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// A floating point RGB image, stored row by row from the top.
+#[derive(Debug, Clone)]
+pub struct HdrImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Vec3A>,
+}
+
+impl HdrImage {
+    pub fn pixel(&self, x: usize, y: usize) -> Vec3A {
+        self.pixels[y * self.width + x]
+    }
+}
+
+#[derive(Debug)]
+pub enum HdrError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Format {
+        path: PathBuf,
+        message: String,
+    },
+}
+
+impl Display for HdrError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io { path, source } => {
+                write!(f, "failed to read `{}`: {}", path.display(), source)
+            }
+            Self::Format { path, message } => {
+                write!(
+                    f,
+                    "`{}` is not a valid HDR image: {}",
+                    path.display(),
+                    message
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for HdrError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Format { .. } => None,
+        }
+    }
+}
+
+/// Loads a Radiance RGBE (`.hdr`) image.
+///
+/// Only the standard `-Y height +X width` orientation is supported,
+/// which is what virtually every tool writes.
+pub fn load_hdr(path: impl AsRef<Path>) -> Result<HdrImage, HdrError> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|source| HdrError::Io {
+        path: path.to_owned(),
+        source,
+    })?;
+
+    parse_hdr(&bytes).map_err(|message| HdrError::Format {
+        path: path.to_owned(),
+        message,
+    })
+}
+
+fn parse_hdr(bytes: &[u8]) -> Result<HdrImage, String> {
+    let mut reader = ByteReader { bytes, offset: 0 };
+
+    let magic = reader.read_line()?;
+    if magic != "#?RADIANCE" && magic != "#?RGBE" {
+        return Err("missing `#?RADIANCE` signature".to_owned());
+    }
+
+    // the header ends with an empty line
+    loop {
+        let line = reader.read_line()?;
+
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some(format) = line.strip_prefix("FORMAT=")
+            && format != "32-bit_rle_rgbe"
+        {
+            return Err(format!("unsupported pixel format `{}`", format));
+        }
+    }
+
+    let resolution = reader.read_line()?;
+    let (height, width) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", height, "+X", width] => (
+            height.parse::<usize>().map_err(|_| "invalid height")?,
+            width.parse::<usize>().map_err(|_| "invalid width")?,
+        ),
+        _ => {
+            return Err(format!(
+                "unsupported resolution line `{}`, expected `-Y <height> +X <width>`",
+                resolution
+            ));
+        }
+    };
+
+    if width == 0 || height == 0 {
+        return Err("image is empty".to_owned());
+    }
+
+    // a corrupted resolution line must not make us allocate more than the file could hold; old
+    // style repeat markers can encode long runs in a few bytes, so the allocations are capped and
+    // the reader is left to fail when the data actually runs out
+    let pixel_count = width
+        .checked_mul(height)
+        .ok_or_else(|| format!("image of {}x{} pixels is too large", width, height))?;
+    let mut pixels = Vec::with_capacity(pixel_count.min(reader.remaining()));
+    let mut scanline = Vec::new();
+    scanline
+        .try_reserve_exact(width)
+        .map_err(|_| format!("image of {}x{} pixels is too large", width, height))?;
+    scanline.resize(width, [0u8; 4]);
+
+    for _ in 0..height {
+        reader.read_scanline(&mut scanline)?;
+        pixels.extend(scanline.iter().map(|&rgbe| rgbe_to_rgb(rgbe)));
+    }
+
+    Ok(HdrImage {
+        width,
+        height,
+        pixels,
+    })
+}
+
+fn rgbe_to_rgb([r, g, b, e]: [u8; 4]) -> Vec3A {
+    if e == 0 {
+        return Vec3A::ZERO;
+    }
+
+    let scale = 2f32.powi(e as i32 - (128 + 8));
+    (Vec3A::new(r as f32, g as f32, b as f32) + 0.5) * scale
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl ByteReader<'_> {
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.offset
+    }
+
+    fn read_byte(&mut self) -> Result<u8, String> {
+        let byte = *self
+            .bytes
+            .get(self.offset)
+            .ok_or("unexpected end of file")?;
+        self.offset += 1;
+        Ok(byte)
+    }
+
+    fn read_line(&mut self) -> Result<String, String> {
+        let rest = &self.bytes[self.offset..];
+        let length = rest
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .ok_or("unexpected end of file in header")?;
+        self.offset += length + 1;
+
+        Ok(String::from_utf8_lossy(&rest[..length]).trim().to_owned())
+    }
+
+    fn read_pixel(&mut self) -> Result<[u8; 4], String> {
+        Ok([
+            self.read_byte()?,
+            self.read_byte()?,
+            self.read_byte()?,
+            self.read_byte()?,
+        ])
+    }
+
+    fn read_scanline(&mut self, scanline: &mut [[u8; 4]]) -> Result<(), String> {
+        let width = scanline.len();
+
+        if !(8..0x8000).contains(&width) {
+            // run-length encoding is only used for scanlines of reasonable width
+            return self.read_flat_scanline(scanline);
+        }
+
+        let first = self.read_pixel()?;
+
+        if first[0] != 2 || first[1] != 2 || first[2] & 0x80 != 0 {
+            scanline[0] = first;
+            return self.read_flat_scanline_from(scanline, 1);
+        }
+
+        if ((first[2] as usize) << 8 | first[3] as usize) != width {
+            return Err("scanline width mismatch".to_owned());
+        }
+
+        // each channel is run-length encoded separately
+        for channel in 0..4 {
+            let mut x = 0;
+
+            while x < width {
+                let count = self.read_byte()? as usize;
+
+                if count > 128 {
+                    let count = count - 128;
+                    if x + count > width {
+                        return Err("run overflows the scanline".to_owned());
+                    }
+
+                    let value = self.read_byte()?;
+                    for pixel in &mut scanline[x..x + count] {
+                        pixel[channel] = value;
+                    }
+                    x += count;
+                } else {
+                    if count == 0 || x + count > width {
+                        return Err("invalid run length".to_owned());
+                    }
+
+                    for pixel in &mut scanline[x..x + count] {
+                        pixel[channel] = self.read_byte()?;
+                    }
+                    x += count;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_flat_scanline(&mut self, scanline: &mut [[u8; 4]]) -> Result<(), String> {
+        self.read_flat_scanline_from(scanline, 0)
+    }
+
+    /// Reads uncompressed pixels, expanding the old-style `(1, 1, 1, n)` repeat markers.
+    fn read_flat_scanline_from(
+        &mut self,
+        scanline: &mut [[u8; 4]],
+        start: usize,
+    ) -> Result<(), String> {
+        let mut x = start;
+        let mut shift = 0;
+
+        while x < scanline.len() {
+            let pixel = self.read_pixel()?;
+
+            if pixel[0] == 1 && pixel[1] == 1 && pixel[2] == 1 && x > 0 {
+                // consecutive markers hold the next byte of the count, so an empty one would only
+                // grow the shift
+                if pixel[3] == 0 {
+                    return Err("empty run".to_owned());
+                }
+
+                let count = (pixel[3] as usize) << shift;
+                if x + count > scanline.len() {
+                    return Err("run overflows the scanline".to_owned());
+                }
+
+                let previous = scanline[x - 1];
+                scanline[x..x + count].fill(previous);
+                x += count;
+                shift += 8;
+            } else {
+                scanline[x] = pixel;
+                x += 1;
+                shift = 0;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A header for an image of `width` by `height` pixels, followed by its pixel data.
+    fn hdr_file(width: usize, height: usize, pixels: &[[u8; 4]]) -> Vec<u8> {
+        let mut bytes =
+            format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {height} +X {width}\n").into_bytes();
+        bytes.extend(pixels.iter().flatten());
+        bytes
+    }
+
+    #[test]
+    fn old_style_repeat_markers_expand_the_previous_pixel() {
+        // 1 pixel followed by a run of 3 and a run of 2 << 8, for 516 pixels in 12 bytes
+        let bytes = hdr_file(516, 1, &[[128, 64, 32, 129], [1, 1, 1, 3], [1, 1, 1, 2]]);
+        let image = parse_hdr(&bytes).unwrap();
+
+        assert_eq!(image.pixels.len(), 516);
+        assert!(image.pixels.iter().all(|&pixel| pixel == image.pixels[0]));
+    }
+
+    #[test]
+    fn empty_repeat_markers_are_rejected() {
+        let mut pixels = vec![[128, 64, 32, 129]];
+        pixels.extend([[1, 1, 1, 0]; 8]);
+        pixels.push([1, 1, 1, 1]);
+
+        assert!(parse_hdr(&hdr_file(2, 1, &pixels)).is_err());
+    }
+}
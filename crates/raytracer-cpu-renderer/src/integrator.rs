@@ -0,0 +1,24 @@
+use crate::{
+    light_sampler::LightSampler, lights::SceneLights, scene_bvh::SceneBvh, shading::SceneShading,
+};
+use glam::Vec3A;
+use raytracer_core::ray::Ray;
+
+/// Everything about the scene that stays the same for every ray, prepared once per render.
+pub struct RenderContext<'a> {
+    pub scene: SceneBvh<'a>,
+    pub shading: &'a SceneShading,
+    pub lights: &'a SceneLights,
+    pub light_sampler: LightSampler,
+}
+
+/// A light transport algorithm.
+///
+/// The renderer asks it for one estimate per sample of every pixel, from many threads at once,
+/// and averages the estimates of each pixel.
+pub trait Integrator: Send + Sync {
+    /// Estimates the radiance arriving at the camera along `ray`.
+    ///
+    /// `max_bounces` limits how many times light may scatter on its way to the camera.
+    fn radiance(&self, ray: Ray, context: &RenderContext, max_bounces: u32) -> Vec3A;
+}
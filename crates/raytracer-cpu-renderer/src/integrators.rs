@@ -0,0 +1,257 @@
+pub mod ambient_occlusion;
+pub mod naive;
+pub mod path;
+
+use crate::{
+    brdf::{Brdf, BrdfEval, BrdfSample, DiffuseLayer},
+    shading::SceneShading,
+    subsurface::{SubsurfaceEntryBrdf, SubsurfaceExitBrdf},
+    texture::NormalPerturbation,
+};
+use glam::Vec3A;
+use raytracer_core::{hit_record::HitRecord, material::Material, ray::Ray};
+use raytracer_primitives::SurfaceFrame;
+use std::borrow::Cow;
+
+/// How the surface under a hit scatters light, with its textures, subsurface scattering and
+/// normal maps taken into account.
+struct SurfaceScattering<'a> {
+    /// where the light leaves the surface, which a subsurface walk moves away from the hit
+    hit: HitRecord<'a>,
+    /// the true normal of the surface, facing the side the hit came from
+    geometric_normal: Vec3A,
+    brdf: SurfaceBrdf<'a>,
+    material: Cow<'a, Material>,
+    /// the shading normal, facing the viewer except on transmissive surfaces
+    normal: Vec3A,
+    /// the `u` direction of the surface parameterization, for anisotropic BRDFs
+    tangent: Vec3A,
+    /// throughput of the subsurface walk that led to `hit`, if any
+    throughput: Vec3A,
+}
+
+/// Prepares the surface under a hit for scattering light towards `view`.
+///
+/// Returns `None` if the light was absorbed inside the object.
+fn surface_scattering<'a>(
+    hit: HitRecord<'a>,
+    view: Vec3A,
+    shading: &'a SceneShading,
+) -> Option<SurfaceScattering<'a>> {
+    let object_shading = shading.get(hit.object_index);
+    let frame = shading.surface_frame(hit.object_index, hit.point, hit.normal);
+    let material = shading.material_at(hit.object, hit.object_index, hit.point, &frame);
+
+    // objects with subsurface scattering randomly choose between walking through their volume
+    // and scattering at the surface, in proportion to the light entering the diffuse lobe that
+    // the walk replaces; the specular layer reflects the rest before it gets there
+    let brdf = object_shading.brdf.as_ref();
+    let layer = brdf.diffuse_layer(&material);
+    let (hit, frame, brdf, material, throughput) = match (&object_shading.subsurface, layer) {
+        (Some(subsurface), Some(layer)) if !brdf.is_delta_surface(&material) => {
+            let entering =
+                material.subsurface * layer.weight * layer.transmittance(hit.normal.dot(view));
+            // the surface keeps a chance of being chosen for its specular lobes
+            let walk_probability = (entering.element_sum() / 3.0).min(0.95);
+            let subsurface_fraction = material.subsurface;
+
+            // the walk replaces the subsurface approximation of the diffuse lobe
+            let material: Cow<Material> = Cow::Owned(Material {
+                subsurface: 0.0,
+                ..material.into_owned()
+            });
+
+            if rand::random::<f32>() < walk_probability {
+                let (exit_hit, throughput) = subsurface.walk(&hit, material.albedo)?;
+                let exit_frame =
+                    shading.surface_frame(exit_hit.object_index, exit_hit.point, exit_hit.normal);
+
+                (
+                    exit_hit,
+                    exit_frame,
+                    SurfaceBrdf::SubsurfaceExit(SubsurfaceExitBrdf { layer }),
+                    material,
+                    entering / walk_probability * throughput,
+                )
+            } else {
+                let brdf = SubsurfaceEntryBrdf {
+                    brdf,
+                    subsurface: subsurface_fraction,
+                };
+                (
+                    hit,
+                    frame,
+                    SurfaceBrdf::SubsurfaceEntry(brdf),
+                    material,
+                    Vec3A::splat((1.0 - walk_probability).recip()),
+                )
+            }
+        }
+        _ => (hit, frame, SurfaceBrdf::Surface(brdf), material, Vec3A::ONE),
+    };
+
+    // light reaches the exit of a walk from inside the object rather than from the viewer
+    let view = match brdf {
+        SurfaceBrdf::SubsurfaceExit(_) => hit.normal,
+        _ => view,
+    };
+
+    // transmissive surfaces need to know which side of the surface the ray is on,
+    // so they are given the outward normal instead of the one facing the ray
+    let view_side = if brdf.is_transmissive(&material) && !hit.front_face {
+        -1.0
+    } else {
+        1.0
+    };
+    let normal = hit.normal * view_side;
+
+    // interpolated normals and normal maps only change the normal used for shading;
+    // the geometric normal still decides where rays can go
+    let geometric_normal = if hit.front_face {
+        frame.geometric_normal
+    } else {
+        -frame.geometric_normal
+    };
+    let normal = match &object_shading.textures.normal {
+        Some(perturbation) => perturbed_shading_normal(&frame, hit.point, normal, perturbation),
+        None => normal,
+    };
+    let normal = facing_viewer(normal, view * view_side);
+
+    Some(SurfaceScattering {
+        hit,
+        geometric_normal,
+        brdf,
+        material,
+        normal,
+        tangent: frame.dpdu,
+        throughput,
+    })
+}
+
+/// The BRDF scattering light at a surface, which is the object's own BRDF unless a subsurface
+/// walk takes part of it over.
+#[derive(Clone, Copy)]
+enum SurfaceBrdf<'a> {
+    Surface(&'a dyn Brdf),
+    SubsurfaceEntry(SubsurfaceEntryBrdf<'a>),
+    SubsurfaceExit(SubsurfaceExitBrdf),
+}
+
+impl SurfaceBrdf<'_> {
+    fn as_dyn(&self) -> &dyn Brdf {
+        match self {
+            Self::Surface(brdf) => *brdf,
+            Self::SubsurfaceEntry(brdf) => brdf,
+            Self::SubsurfaceExit(brdf) => brdf,
+        }
+    }
+}
+
+impl Brdf for SurfaceBrdf<'_> {
+    fn is_delta_surface(&self, material: &Material) -> bool {
+        self.as_dyn().is_delta_surface(material)
+    }
+
+    fn is_transmissive(&self, material: &Material) -> bool {
+        self.as_dyn().is_transmissive(material)
+    }
+
+    fn eval(
+        &self,
+        view: Vec3A,
+        normal: Vec3A,
+        tangent: Vec3A,
+        light: Vec3A,
+        material: &Material,
+    ) -> BrdfEval {
+        self.as_dyn().eval(view, normal, tangent, light, material)
+    }
+
+    fn sample(
+        &self,
+        view: Vec3A,
+        normal: Vec3A,
+        tangent: Vec3A,
+        material: &Material,
+    ) -> BrdfSample {
+        self.as_dyn().sample(view, normal, tangent, material)
+    }
+
+    fn diffuse_layer(&self, material: &Material) -> Option<DiffuseLayer> {
+        self.as_dyn().diffuse_layer(material)
+    }
+
+    fn eval_diffuse(&self, view: Vec3A, normal: Vec3A, light: Vec3A, material: &Material) -> Vec3A {
+        self.as_dyn().eval_diffuse(view, normal, light, material)
+    }
+}
+
+impl SurfaceScattering<'_> {
+    /// Shading normals can put a direction on one side of the surface while it really is on the
+    /// other. Following such directions would let light leak through the surface, so they are
+    /// rejected.
+    fn is_on_consistent_side(&self, direction: Vec3A) -> bool {
+        // transmissive surfaces shade with the outward normal, which may face away from the hit
+        let orientation = self.geometric_normal.dot(self.normal).signum();
+
+        direction.dot(self.geometric_normal) * direction.dot(self.normal) * orientation > 0.0
+    }
+
+    /// Starts a ray leaving the surface in `direction`.
+    fn ray_towards(&self, direction: Vec3A) -> Ray {
+        Ray::new(
+            offset_ray_origin(self.hit.point, self.geometric_normal, direction),
+            direction,
+        )
+    }
+}
+
+/// Opaque surfaces are only visible from the front, while transmissive surfaces are also visible
+/// from the inside of the object.
+fn is_surface_visible(hit: &HitRecord, shading: &SceneShading) -> bool {
+    hit.front_face
+        || shading
+            .brdf(hit.object_index)
+            .is_transmissive(hit.object.material())
+}
+
+/// Evaluates a normal or bump map at a hit, returning a shading normal on the same side as `normal`.
+fn perturbed_shading_normal(
+    frame: &SurfaceFrame,
+    point: Vec3A,
+    normal: Vec3A,
+    perturbation: &NormalPerturbation,
+) -> Vec3A {
+    let perturbed = perturbation.perturb(frame, point);
+
+    if frame.shading_normal.dot(normal) < 0.0 {
+        -perturbed
+    } else {
+        perturbed
+    }
+}
+
+/// Interpolated and perturbed normals can face away from the viewer even where the surface does
+/// not. The BRDF would then see the view as coming from inside the surface, so the normal is bent
+/// back just enough to face `view`.
+fn facing_viewer(normal: Vec3A, view: Vec3A) -> Vec3A {
+    let cos_view = normal.dot(view);
+    const MIN_COS_VIEW: f32 = 1e-2;
+
+    if cos_view < MIN_COS_VIEW {
+        (normal + view * (MIN_COS_VIEW - cos_view)).normalize()
+    } else {
+        normal
+    }
+}
+
+/// Moves a ray origin off the surface, towards the side the ray is leaving to,
+/// so that the ray does not intersect the surface it starts from.
+fn offset_ray_origin(point: Vec3A, normal: Vec3A, direction: Vec3A) -> Vec3A {
+    if normal.dot(direction) >= 0.0 {
+        point + normal * 1e-5
+    } else {
+        point - normal * 1e-5
+    }
+}
@@ -0,0 +1,60 @@
+use super::{is_surface_visible, offset_ray_origin};
+use crate::{
+    brdfs::random_cosine_direction,
+    integrator::{Integrator, RenderContext},
+};
+use glam::Vec3A;
+use raytracer_core::ray::Ray;
+
+/// Shades every surface by how much of the hemisphere above it is left open by nearby geometry,
+/// ignoring materials and lights, which is useful to inspect the geometry of a scene.
+///
+/// Directions are weighted by their cosine with the normal, and the background is white.
+#[derive(Debug, Clone, Copy)]
+pub struct AmbientOcclusion {
+    /// how far away geometry still occludes a point
+    pub distance: f32,
+}
+
+impl AmbientOcclusion {
+    pub fn new(distance: f32) -> Self {
+        Self { distance }
+    }
+}
+
+impl Integrator for AmbientOcclusion {
+    fn radiance(&self, ray: Ray, context: &RenderContext, _max_bounces: u32) -> Vec3A {
+        let Some(hit) = context.scene.hit(&ray, 1e-5, f32::INFINITY) else {
+            return Vec3A::ONE;
+        };
+
+        if !is_surface_visible(&hit, context.shading) {
+            return Vec3A::ZERO;
+        }
+
+        let frame = context
+            .shading
+            .surface_frame(hit.object_index, hit.point, hit.normal);
+        let geometric_normal = if hit.front_face {
+            frame.geometric_normal
+        } else {
+            -frame.geometric_normal
+        };
+        let direction = random_cosine_direction(hit.normal);
+
+        if direction.dot(geometric_normal) <= 0.0 {
+            // the shading normal sent the ray into the surface, which occludes it
+            return Vec3A::ZERO;
+        }
+
+        let occlusion_ray = Ray::new(
+            offset_ray_origin(hit.point, geometric_normal, direction),
+            direction,
+        );
+
+        match context.scene.hit(&occlusion_ray, 1e-5, self.distance) {
+            Some(_) => Vec3A::ZERO,
+            None => Vec3A::ONE,
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use super::{is_surface_visible, surface_scattering};
+use crate::{
+    brdf::Brdf,
+    integrator::{Integrator, RenderContext},
+};
+use glam::Vec3A;
+use raytracer_core::ray::Ray;
+
+/// A path tracer that only follows the directions sampled from BRDFs, and collects light when a
+/// path happens to hit an emissive surface or escape to the environment.
+///
+/// It converges much more slowly than [`PathTracer`](super::path::PathTracer), especially towards
+/// small lights, but is simple enough to serve as a reference for it.
+/// Delta lights cannot be hit by chance, so they do not light anything.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NaivePathTracer;
+
+impl Integrator for NaivePathTracer {
+    fn radiance(&self, mut ray: Ray, context: &RenderContext, max_bounces: u32) -> Vec3A {
+        let RenderContext {
+            scene,
+            shading,
+            lights,
+            ..
+        } = context;
+        let mut result = Vec3A::ZERO;
+        let mut attenuation = Vec3A::ONE;
+        let mut hit = scene.hit(&ray, 1e-5, f32::INFINITY);
+
+        for bounce in 0..=max_bounces {
+            let current_hit = match hit.take() {
+                Some(hit) if is_surface_visible(&hit, shading) => hit,
+                Some(_) => break,
+                None => {
+                    if let Some(environment) = &lights.environment {
+                        result += attenuation * environment.radiance(ray.direction);
+                    }
+                    break;
+                }
+            };
+
+            if current_hit.object.material().is_emissive {
+                result += attenuation
+                    * shading.emission_at(
+                        current_hit.object,
+                        current_hit.object_index,
+                        current_hit.point,
+                        current_hit.normal,
+                    );
+                break;
+            }
+
+            if bounce == max_bounces {
+                break;
+            }
+
+            let Some(surface) = surface_scattering(current_hit, -ray.direction, shading) else {
+                break;
+            };
+
+            let brdf_sample = surface.brdf.sample(
+                -ray.direction,
+                surface.normal,
+                surface.tangent,
+                &surface.material,
+            );
+
+            if brdf_sample.attenuation.length_squared() < 1e-5 || brdf_sample.pdf < 1e-5 {
+                break;
+            }
+
+            if !surface.is_on_consistent_side(brdf_sample.direction) {
+                break;
+            }
+
+            attenuation *= surface.throughput * brdf_sample.attenuation;
+            ray = surface.ray_towards(brdf_sample.direction);
+            hit = scene.hit(&ray, 1e-5, f32::INFINITY);
+        }
+
+        result
+    }
+}
@@ -0,0 +1,463 @@
+use super::{SurfaceScattering, is_surface_visible, surface_scattering};
+use crate::{
+    brdf::{Brdf, BrdfEval},
+    environment::Environment,
+    integrator::{Integrator, RenderContext},
+    lights::{DeltaLight, SceneLights},
+    scene_bvh::SceneBvh,
+};
+use glam::Vec3A;
+use raytracer_core::{hit_record::HitRecord, material::Material, object::Object, ray::Ray};
+use raytracer_primitives::DirectionToObject;
+
+/// A path tracer that samples light sources at every bounce (next event estimation), and combines
+/// those samples with the directions sampled from BRDFs by multiple importance sampling.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PathTracer;
+
+impl Integrator for PathTracer {
+    /// Solves the rendering equation for a given ray, using the BRDF assigned to each hit object:
+    ///
+    /// `L_o = L_e + f_r * L_i * (N dot L) / pdf`
+    ///
+    /// where:
+    /// - `L_o` is the outgoing radiance
+    /// - `L_e` is the emitted radiance
+    /// - `f_r` is the BRDF
+    /// - `L_i` is the incoming radiance
+    /// - `N` is the surface normal
+    /// - `L` is the light direction
+    /// - `pdf` is the probability density function of the BRDF
+    ///
+    /// The function returns the outgoing radiance `L_o`.
+    ///
+    /// The function terminates when the bounce limit is reached.
+    ///
+    /// Note that the BRDF is responsible for computing `attenuation`, which represents:
+    ///
+    /// `attenuation = f_r * cos_theta / pdf`
+    fn radiance<'a>(&self, mut ray: Ray, context: &RenderContext<'a>, max_bounces: u32) -> Vec3A {
+        let RenderContext {
+            scene,
+            shading,
+            lights,
+            light_sampler,
+        } = context;
+        let mut result = Vec3A::ZERO;
+        let mut attenuation = Vec3A::ONE;
+        let mut hit: Option<HitRecord<'a>> = scene.hit(&ray, 1e-5, f32::INFINITY);
+
+        for _ in 0..max_bounces {
+            let current_hit = match hit.take() {
+                Some(hit) if is_surface_visible(&hit, shading) => hit,
+                Some(_) => {
+                    // the ray hit the back of an opaque surface, which does not reflect any light.
+                    break;
+                }
+                None => {
+                    // the ray escaped the scene; return the emission of the environment.
+                    if let Some(environment) = &lights.environment {
+                        result += attenuation * environment.radiance(ray.direction);
+                    }
+                    break;
+                }
+            };
+
+            if current_hit.object.material().is_emissive {
+                // the ray hit an emissive surface; return the emission.
+                // ideal light sources do not reflect light, so we can skip the rest of the computation.
+                result += attenuation
+                    * shading.emission_at(
+                        current_hit.object,
+                        current_hit.object_index,
+                        current_hit.point,
+                        current_hit.normal,
+                    );
+                break;
+            }
+
+            let Some(surface) = surface_scattering(current_hit, -ray.direction, shading) else {
+                // the light was absorbed inside the object
+                break;
+            };
+            let current_hit = &surface.hit;
+            let brdf = &surface.brdf;
+            let material: &Material = &surface.material;
+            let normal = surface.normal;
+
+            attenuation *= surface.throughput;
+
+            // is the surface a delta surface(perfect mirror)?
+            let is_delta_surface = brdf.is_delta_surface(material);
+            let direct_term = if is_delta_surface {
+                // direct term is zero for delta surfaces.
+                // this is because there is no chance of the direct light being reflected back to the ray shooter.
+                Vec3A::ZERO
+            } else {
+                // compute the contribution of the direct light sources.
+                compute_nee_contribution(&surface, context, -ray.direction)
+            };
+
+            result += attenuation * direct_term;
+
+            let brdf_sample = brdf.sample(-ray.direction, normal, surface.tangent, material);
+
+            if brdf_sample.attenuation.length_squared() < 1e-5 || brdf_sample.pdf < 1e-5 {
+                // indirect term is too small; ignore it
+                break;
+            }
+
+            if !surface.is_on_consistent_side(brdf_sample.direction) {
+                // the shading normal sent the ray through the surface; stop rather than leak light
+                break;
+            }
+
+            ray = surface.ray_towards(brdf_sample.direction);
+            hit = scene.hit(&ray, 1e-5, f32::INFINITY);
+
+            let should_trace_next = match &hit {
+                Some(next_hit)
+                    if next_hit.front_face
+                        && next_hit.object.material().is_emissive
+                        && is_delta_surface =>
+                {
+                    // indirect term is coming from a direct light source, and MIS is not needed (because the surface is perfect mirror)
+                    let emission = shading.emission_at(
+                        next_hit.object,
+                        next_hit.object_index,
+                        next_hit.point,
+                        next_hit.normal,
+                    );
+                    let indirect_term = emission * brdf_sample.attenuation;
+                    result += attenuation * indirect_term;
+                    false
+                }
+                Some(next_hit)
+                    if next_hit.front_face
+                        && next_hit.object.material().is_emissive
+                        && !is_delta_surface =>
+                {
+                    // indirect term is coming from a direct light source, and MIS is needed
+                    let pdf_brdf = brdf_sample.pdf;
+                    let cos_theta_l = next_hit.normal.dot(-ray.direction).max(0.0);
+
+                    let indirect_term = if cos_theta_l < 1e-5 {
+                        Vec3A::ZERO
+                    } else {
+                        // the light sampler must be asked about the same point and normal as when it
+                        // sampled a light for this surface
+                        let selection_pdf =
+                            light_sampler.pmf(current_hit.point, normal, next_hit.object_index);
+                        let pdf_light = light_direction_pdf(lights, current_hit.point, next_hit)
+                            * selection_pdf;
+
+                        let mis_weight_brdf = pdf_brdf / (pdf_light + pdf_brdf);
+                        let emission = shading.emission_at(
+                            next_hit.object,
+                            next_hit.object_index,
+                            next_hit.point,
+                            next_hit.normal,
+                        );
+                        emission * brdf_sample.attenuation * mis_weight_brdf
+                    };
+
+                    result += attenuation * indirect_term;
+                    false
+                }
+                Some(next_hit) if is_surface_visible(next_hit, shading) => {
+                    // indirect term is coming from a non-direct light source
+                    true
+                }
+                None => {
+                    // the ray escaped the scene, so the indirect term is coming from the environment
+                    if let Some(environment) = &lights.environment {
+                        let mis_weight_brdf = if is_delta_surface {
+                            1.0
+                        } else {
+                            let pdf_brdf = brdf_sample.pdf;
+                            let pdf_light = environment.pdf(ray.direction);
+                            pdf_brdf / (pdf_light + pdf_brdf)
+                        };
+                        let indirect_term = environment.radiance(ray.direction)
+                            * brdf_sample.attenuation
+                            * mis_weight_brdf;
+                        result += attenuation * indirect_term;
+                    }
+                    false
+                }
+                _ => {
+                    // the next hit is the back of an opaque surface; ignore it
+                    false
+                }
+            };
+
+            if !should_trace_next {
+                break;
+            }
+
+            attenuation *= brdf_sample.attenuation;
+        }
+
+        result
+    }
+}
+
+/// Samples every kind of light source once: emissive objects, delta lights and the environment.
+fn compute_nee_contribution(
+    surface: &SurfaceScattering,
+    context: &RenderContext,
+    view: Vec3A,
+) -> Vec3A {
+    let RenderContext { scene, lights, .. } = context;
+    let object_term = compute_area_light_contribution(surface, context, view);
+    let delta_term: Vec3A = lights
+        .delta_lights
+        .iter()
+        .map(|light| compute_delta_light_contribution(surface, scene, view, light))
+        .sum();
+    let environment_term = match &lights.environment {
+        Some(environment) => {
+            compute_environment_contribution(surface, scene, view, environment.as_ref())
+        }
+        None => Vec3A::ZERO,
+    };
+
+    object_term + delta_term + environment_term
+}
+
+/// Samples one emissive object, chosen by the light sampler.
+///
+/// Objects with a registered shape are sampled by the solid angle they cover; others by area.
+fn compute_area_light_contribution(
+    surface: &SurfaceScattering,
+    context: &RenderContext,
+    view: Vec3A,
+) -> Vec3A {
+    let RenderContext {
+        scene,
+        shading,
+        lights,
+        light_sampler,
+    } = context;
+    let SurfaceScattering {
+        hit,
+        brdf,
+        material,
+        normal,
+        tangent,
+        ..
+    } = surface;
+    let normal = *normal;
+    let Some((light_object_index, selection_pdf)) = light_sampler.sample(hit.point, normal) else {
+        // no light objects, or none that can reach the point; ignore it
+        return Vec3A::ZERO;
+    };
+    let light_object = scene.scene().objects()[light_object_index].as_ref();
+
+    let light_sample = match lights.light_shapes.get(&light_object_index) {
+        Some(shape) if shape.supports_solid_angle_sampling(hit.point) => {
+            shape.sample_direction(hit.point)
+        }
+        _ => sample_light_area(light_object, hit.point),
+    };
+    let Some(light_sample) = light_sample else {
+        // light is not visible from the point; ignore it
+        return Vec3A::ZERO;
+    };
+
+    let light_direction = light_sample.direction;
+
+    if !surface.is_on_consistent_side(light_direction) {
+        // the shading normal disagrees with the surface about the side of the light
+        return Vec3A::ZERO;
+    }
+
+    // transmissive surfaces can receive light from behind; for any other surface,
+    // the BRDF evaluates to zero in that case
+    let cos_theta = normal.dot(light_direction).abs();
+    let cos_theta_l = light_sample.normal.dot(-light_direction).max(0.0);
+
+    if cos_theta_l < 1e-5 || light_sample.pdf <= 0.0 {
+        // light is not visible; ignore it
+        return Vec3A::ZERO;
+    }
+
+    let shadow_ray = surface.ray_towards(light_direction);
+    let is_visible = match scene.hit(&shadow_ray, 1e-5, light_sample.distance) {
+        Some(hit) => hit.object_index == light_object_index,
+        None => true,
+    };
+
+    if !is_visible {
+        // light is not visible; ignore it
+        return Vec3A::ZERO;
+    }
+
+    let BrdfEval { f_r, pdf: pdf_brdf } =
+        brdf.eval(view, normal, *tangent, light_direction, material);
+    let pdf_light = light_sample.pdf * selection_pdf;
+
+    if pdf_brdf < 1e-5 && pdf_light < 1e-5 {
+        // pdf is too small; ignore it
+        return Vec3A::ZERO;
+    }
+
+    let mis_weight = pdf_light / (pdf_brdf + pdf_light);
+    let emission = shading.emission_at(
+        light_object,
+        light_object_index,
+        light_sample.point,
+        light_sample.normal,
+    );
+    let contribution = emission * f_r * cos_theta;
+
+    (contribution / pdf_light) * mis_weight
+}
+
+/// Samples a point uniformly on the surface of a light, converting its density to solid angle.
+fn sample_light_area(light_object: &dyn Object, reference: Vec3A) -> Option<DirectionToObject> {
+    let area = light_object.area();
+
+    if area < 1e-5 {
+        // area is too small; ignore it
+        return None;
+    }
+
+    let light_point = light_object.sample_point();
+    let diff = light_point.point - reference;
+    let r_squared = diff.length_squared();
+
+    if r_squared < 1e-5 {
+        // light is too close; ignore it, treating the light as if it is behind the surface
+        return None;
+    }
+
+    let r = r_squared.sqrt();
+    let direction = diff / r;
+    let cos_theta_l = light_point.normal.dot(-direction).max(0.0);
+
+    Some(DirectionToObject {
+        point: light_point.point,
+        normal: light_point.normal,
+        direction,
+        distance: r,
+        pdf: r_squared / (cos_theta_l * area),
+    })
+}
+
+/// Returns the density, with respect to solid angle, of [`compute_area_light_contribution`]
+/// sampling the given direction towards a light that was hit from the reference point.
+fn light_direction_pdf(lights: &SceneLights, reference: Vec3A, light_hit: &HitRecord) -> f32 {
+    match lights.light_shapes.get(&light_hit.object_index) {
+        Some(shape) if shape.supports_solid_angle_sampling(reference) => {
+            let direction = (light_hit.point - reference).normalize();
+            shape.direction_pdf(reference, direction)
+        }
+        _ => {
+            let diff = light_hit.point - reference;
+            let cos_theta_l = light_hit.normal.dot(-diff.normalize()).max(0.0);
+            diff.length_squared() / (cos_theta_l * light_hit.object.area())
+        }
+    }
+}
+
+/// Delta lights can only be reached by sampling them explicitly, so there is no MIS involved.
+fn compute_delta_light_contribution(
+    surface: &SurfaceScattering,
+    scene: &SceneBvh,
+    view: Vec3A,
+    light: &DeltaLight,
+) -> Vec3A {
+    let SurfaceScattering {
+        hit,
+        brdf,
+        material,
+        normal,
+        tangent,
+        ..
+    } = surface;
+    let normal = *normal;
+    let Some(incidence) = light.illuminate(hit.point) else {
+        // the point is outside of the light's cone, or too close to it; ignore it
+        return Vec3A::ZERO;
+    };
+
+    let light_direction = incidence.direction;
+
+    if !surface.is_on_consistent_side(light_direction) {
+        // the shading normal disagrees with the surface about the side of the light
+        return Vec3A::ZERO;
+    }
+
+    let BrdfEval { f_r, .. } = brdf.eval(view, normal, *tangent, light_direction, material);
+
+    if f_r.length_squared() < 1e-10 {
+        // the surface does not reflect light in that direction; ignore it
+        return Vec3A::ZERO;
+    }
+
+    let shadow_ray = surface.ray_towards(light_direction);
+
+    if scene
+        .hit(&shadow_ray, 1e-5, incidence.distance - 1e-4)
+        .is_some()
+    {
+        // the light is occluded; ignore it
+        return Vec3A::ZERO;
+    }
+
+    let cos_theta = normal.dot(light_direction).abs();
+
+    incidence.irradiance * f_r * cos_theta
+}
+
+/// Samples a direction towards the environment, weighted by MIS against the BRDF.
+fn compute_environment_contribution(
+    surface: &SurfaceScattering,
+    scene: &SceneBvh,
+    view: Vec3A,
+    environment: &dyn Environment,
+) -> Vec3A {
+    let SurfaceScattering {
+        brdf,
+        material,
+        normal,
+        tangent,
+        ..
+    } = surface;
+    let normal = *normal;
+    let light_sample = environment.sample();
+
+    if light_sample.pdf < 1e-5 {
+        // the environment is black in that direction; ignore it
+        return Vec3A::ZERO;
+    }
+
+    let light_direction = light_sample.direction;
+
+    if !surface.is_on_consistent_side(light_direction) {
+        // the shading normal disagrees with the surface about the side of the light
+        return Vec3A::ZERO;
+    }
+
+    let BrdfEval { f_r, pdf: pdf_brdf } =
+        brdf.eval(view, normal, *tangent, light_direction, material);
+
+    if f_r.length_squared() < 1e-10 {
+        // the surface does not reflect light in that direction; ignore it
+        return Vec3A::ZERO;
+    }
+
+    let shadow_ray = surface.ray_towards(light_direction);
+
+    if scene.hit(&shadow_ray, 1e-5, f32::INFINITY).is_some() {
+        // the environment is occluded; ignore it
+        return Vec3A::ZERO;
+    }
+
+    let pdf_light = light_sample.pdf;
+    let mis_weight = pdf_light / (pdf_brdf + pdf_light);
+    let cos_theta = normal.dot(light_direction).abs();
+
+    light_sample.radiance * f_r * cos_theta / pdf_light * mis_weight
+}
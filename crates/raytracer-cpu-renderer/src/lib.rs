@@ -1,3 +1,15 @@
 pub mod brdf;
 pub mod brdfs;
+pub mod environment;
+pub mod hdr;
+pub mod integrator;
+pub mod integrators;
+pub mod light_sampler;
+pub mod lights;
 pub mod renderer;
+pub mod scene_bvh;
+pub mod shading;
+pub mod sky;
+pub mod subsurface;
+pub mod texture;
+pub mod textures;
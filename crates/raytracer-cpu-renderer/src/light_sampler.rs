@@ -0,0 +1,351 @@
+use crate::environment::luminance;
+use glam::Vec3A;
+use raytracer_core::scene::Scene;
+use std::collections::HashMap;
+
+/// How next event estimation chooses which emissive object to sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LightSampling {
+    /// every emissive object is equally likely
+    Uniform,
+    /// objects are chosen proportionally to the power they emit
+    #[default]
+    Power,
+    /// objects are chosen by descending a light BVH, preferring emitters that are powerful and
+    /// close to the shading point; best for scenes with many emitters
+    Bvh,
+}
+
+/// Chooses emissive objects for next event estimation.
+///
+/// This is built once per scene, and answers both which light to sample from a shading point and
+/// how likely a given light was to be sampled from there, which MIS needs when a BRDF sample
+/// happens to hit an emitter.
+pub struct LightSampler {
+    /// object indices of the emissive objects
+    lights: Vec<usize>,
+    /// position of each emissive object in `lights`, by object index
+    slots: HashMap<usize, usize>,
+    selection: Selection,
+}
+
+enum Selection {
+    Uniform,
+    Power { cdf: Vec<f32>, pmf: Vec<f32> },
+    Bvh(LightBvh),
+}
+
+impl LightSampler {
+    pub fn build(scene: &Scene, strategy: LightSampling) -> Self {
+        let emitters: Vec<_> = scene
+            .objects()
+            .iter()
+            .enumerate()
+            .filter(|(_, object)| object.material().is_emissive)
+            .map(|(index, object)| {
+                let power = luminance(object.material().emission) * object.area();
+                (index, object.as_ref(), power)
+            })
+            .filter(|(_, _, power)| *power > 0.0)
+            .collect();
+
+        let lights: Vec<_> = emitters.iter().map(|(index, _, _)| *index).collect();
+        let slots = lights
+            .iter()
+            .enumerate()
+            .map(|(slot, &index)| (index, slot))
+            .collect();
+
+        let selection = match strategy {
+            _ if lights.is_empty() => Selection::Uniform,
+            LightSampling::Uniform => Selection::Uniform,
+            LightSampling::Power => {
+                let total_power: f32 = emitters.iter().map(|(_, _, power)| power).sum();
+                let pmf: Vec<_> = emitters
+                    .iter()
+                    .map(|(_, _, power)| power / total_power)
+                    .collect();
+                let cdf = pmf
+                    .iter()
+                    .scan(0.0, |sum, probability| {
+                        *sum += probability;
+                        Some(*sum)
+                    })
+                    .collect();
+
+                Selection::Power { cdf, pmf }
+            }
+            LightSampling::Bvh => {
+                let primitives = emitters
+                    .iter()
+                    .enumerate()
+                    .map(|(slot, (_, object, power))| {
+                        let aabb = object.bounding_box();
+                        LightPrimitive {
+                            slot,
+                            min: aabb.min,
+                            max: aabb.max,
+                            power: *power,
+                        }
+                    })
+                    .collect();
+
+                Selection::Bvh(LightBvh::build(primitives))
+            }
+        };
+
+        Self {
+            lights,
+            slots,
+            selection,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    /// Chooses an emissive object to illuminate the given point.
+    ///
+    /// Returns the object index of the light along with the probability of choosing it.
+    pub fn sample(&self, point: Vec3A, normal: Vec3A) -> Option<(usize, f32)> {
+        if self.lights.is_empty() {
+            return None;
+        }
+
+        let random = rand::random::<f32>();
+        let (slot, probability) = match &self.selection {
+            Selection::Uniform => {
+                let slot =
+                    ((random * self.lights.len() as f32) as usize).min(self.lights.len() - 1);
+                (slot, (self.lights.len() as f32).recip())
+            }
+            Selection::Power { cdf, pmf } => {
+                let slot = cdf
+                    .partition_point(|&value| value <= random)
+                    .min(self.lights.len() - 1);
+                (slot, pmf[slot])
+            }
+            Selection::Bvh(bvh) => bvh.sample(point, normal, random)?,
+        };
+
+        Some((self.lights[slot], probability))
+    }
+
+    /// Returns the probability of [`LightSampler::sample`] choosing the object from the given point.
+    pub fn pmf(&self, point: Vec3A, normal: Vec3A, object_index: usize) -> f32 {
+        let Some(&slot) = self.slots.get(&object_index) else {
+            return 0.0;
+        };
+
+        match &self.selection {
+            Selection::Uniform => (self.lights.len() as f32).recip(),
+            Selection::Power { pmf, .. } => pmf[slot],
+            Selection::Bvh(bvh) => bvh.pmf(point, normal, slot),
+        }
+    }
+}
+
+struct LightPrimitive {
+    slot: usize,
+    min: Vec3A,
+    max: Vec3A,
+    power: f32,
+}
+
+impl LightPrimitive {
+    fn centroid(&self) -> Vec3A {
+        (self.min + self.max) * 0.5
+    }
+}
+
+/// A bounding volume hierarchy over emitters, after Conty Estevez and Kulla,
+/// "Importance Sampling of Many Lights with Adaptive Tree Splitting".
+///
+/// Objects do not expose which way they emit, so unlike the original, every node is assumed to
+/// emit in all directions, and importance only accounts for power, distance and the orientation
+/// of the receiving surface.
+struct LightBvh {
+    nodes: Vec<LightBvhNode>,
+    /// path from the root to the leaf of each light, one bit per level, set for right children
+    trails: Vec<u64>,
+}
+
+struct LightBvhNode {
+    min: Vec3A,
+    max: Vec3A,
+    power: f32,
+    /// the left child always directly follows its parent
+    kind: LightBvhNodeKind,
+}
+
+enum LightBvhNodeKind {
+    Leaf { slot: usize },
+    Interior { right: usize },
+}
+
+impl LightBvh {
+    fn build(mut primitives: Vec<LightPrimitive>) -> Self {
+        let mut bvh = Self {
+            nodes: Vec::with_capacity(primitives.len() * 2),
+            trails: vec![0; primitives.len()],
+        };
+        bvh.build_node(&mut primitives, 0, 0);
+        bvh
+    }
+
+    fn build_node(&mut self, primitives: &mut [LightPrimitive], trail: u64, depth: u32) -> usize {
+        let (min, max, power) = primitives.iter().fold(
+            (Vec3A::INFINITY, Vec3A::NEG_INFINITY, 0.0),
+            |(min, max, power), primitive| {
+                (
+                    min.min(primitive.min),
+                    max.max(primitive.max),
+                    power + primitive.power,
+                )
+            },
+        );
+        let index = self.nodes.len();
+
+        if let [primitive] = primitives {
+            self.trails[primitive.slot] = trail;
+            self.nodes.push(LightBvhNode {
+                min,
+                max,
+                power,
+                kind: LightBvhNodeKind::Leaf {
+                    slot: primitive.slot,
+                },
+            });
+            return index;
+        }
+
+        self.nodes.push(LightBvhNode {
+            min,
+            max,
+            power,
+            kind: LightBvhNodeKind::Interior { right: 0 },
+        });
+
+        // split at the median centroid along the widest axis, which keeps the tree balanced
+        let (centroid_min, centroid_max) = primitives.iter().fold(
+            (Vec3A::INFINITY, Vec3A::NEG_INFINITY),
+            |(min, max), primitive| (min.min(primitive.centroid()), max.max(primitive.centroid())),
+        );
+        let axis = (centroid_max - centroid_min).max_position();
+        let mid = primitives.len() / 2;
+        primitives.select_nth_unstable_by(mid, |a, b| {
+            a.centroid()[axis].total_cmp(&b.centroid()[axis])
+        });
+
+        let (left, right) = primitives.split_at_mut(mid);
+        self.build_node(left, trail, depth + 1);
+        let right_index = self.build_node(right, trail | (1 << depth), depth + 1);
+        self.nodes[index].kind = LightBvhNodeKind::Interior { right: right_index };
+
+        index
+    }
+
+    fn sample(&self, point: Vec3A, normal: Vec3A, mut random: f32) -> Option<(usize, f32)> {
+        let mut index = 0;
+        let mut probability = 1.0;
+
+        loop {
+            let right = match self.nodes[index].kind {
+                LightBvhNodeKind::Leaf { slot } => return Some((slot, probability)),
+                LightBvhNodeKind::Interior { right } => right,
+            };
+
+            let importance_left = self.nodes[index + 1].importance(point, normal);
+            let importance_right = self.nodes[right].importance(point, normal);
+            let total = importance_left + importance_right;
+
+            if total <= 0.0 {
+                return None;
+            }
+
+            let p_left = importance_left / total;
+
+            // reuse the random number, rescaled to the chosen side
+            if random < p_left {
+                random = (random / p_left).min(1.0 - f32::EPSILON);
+                probability *= p_left;
+                index += 1;
+            } else {
+                random = ((random - p_left) / (1.0 - p_left)).min(1.0 - f32::EPSILON);
+                probability *= 1.0 - p_left;
+                index = right;
+            }
+        }
+    }
+
+    fn pmf(&self, point: Vec3A, normal: Vec3A, slot: usize) -> f32 {
+        let trail = self.trails[slot];
+        let mut index = 0;
+        let mut probability = 1.0;
+        let mut depth = 0;
+
+        loop {
+            let right = match self.nodes[index].kind {
+                LightBvhNodeKind::Leaf { .. } => return probability,
+                LightBvhNodeKind::Interior { right } => right,
+            };
+
+            let importance_left = self.nodes[index + 1].importance(point, normal);
+            let importance_right = self.nodes[right].importance(point, normal);
+            let total = importance_left + importance_right;
+
+            if total <= 0.0 {
+                return 0.0;
+            }
+
+            if trail & (1 << depth) == 0 {
+                probability *= importance_left / total;
+                index += 1;
+            } else {
+                probability *= importance_right / total;
+                index = right;
+            }
+
+            depth += 1;
+        }
+    }
+}
+
+impl LightBvhNode {
+    /// Estimates how much light the node contributes to the point.
+    fn importance(&self, point: Vec3A, normal: Vec3A) -> f32 {
+        let center = (self.min + self.max) * 0.5;
+        let radius_squared = ((self.max - self.min) * 0.5).length_squared();
+        let to_light = center - point;
+        let distance_squared = to_light.length_squared();
+
+        // points close to or inside the bounds would otherwise get an unbounded importance
+        let importance = self.power / distance_squared.max(radius_squared);
+
+        if distance_squared <= radius_squared {
+            // the bounds surround the point, so light may arrive from any direction
+            return importance;
+        }
+
+        // the surface receives light from the cone of directions subtended by the bounding sphere;
+        // use the cosine of the angle closest to the normal, for either side of the surface
+        let sin2_bounds = radius_squared / distance_squared;
+        let cos_bounds = (1.0 - sin2_bounds).sqrt();
+        let sin_bounds = sin2_bounds.sqrt();
+
+        let cos_normal = normal
+            .dot(to_light / distance_squared.sqrt())
+            .abs()
+            .min(1.0);
+        let sin_normal = (1.0 - cos_normal * cos_normal).max(0.0).sqrt();
+
+        let cos_closest = if cos_normal > cos_bounds {
+            1.0
+        } else {
+            cos_normal * cos_bounds + sin_normal * sin_bounds
+        };
+
+        importance * cos_closest.max(0.0)
+    }
+}
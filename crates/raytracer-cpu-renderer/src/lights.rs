@@ -0,0 +1,205 @@
+use crate::{environment::Environment, shading::SceneShading};
+use glam::Vec3A;
+use raytracer_core::scene::Scene;
+use raytracer_primitives::{SolidAngleSampling, SurfaceParameterization};
+use std::{collections::HashMap, sync::Arc};
+
+/// Light sources that are not objects of the scene,
+/// along with what is known about the shape of the emissive objects that are.
+#[derive(Clone, Default)]
+pub struct SceneLights {
+    pub environment: Option<Arc<dyn Environment>>,
+    pub delta_lights: Vec<DeltaLight>,
+    /// emissive objects whose directions can be sampled from the point being lit, by object index;
+    /// any other emissive object is sampled by area
+    pub light_shapes: HashMap<usize, Arc<dyn SolidAngleSampling>>,
+}
+
+impl SceneLights {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_environment(mut self, environment: Arc<dyn Environment>) -> Self {
+        self.environment = Some(environment);
+        self
+    }
+
+    pub fn add_delta_light(&mut self, light: impl Into<DeltaLight>) {
+        self.delta_lights.push(light.into());
+    }
+
+    /// Adds an emissive object to the scene through the shading, sharing it so that it is lit by
+    /// sampling the solid angle it covers rather than its area.
+    pub fn add_light_object<T>(&mut self, scene: &mut Scene, shading: &mut SceneShading, object: T)
+    where
+        T: SolidAngleSampling + SurfaceParameterization + 'static,
+    {
+        let object = Arc::new(object);
+        let object_index = shading.add_shared_object(scene, object.clone());
+        self.light_shapes.insert(object_index, object);
+    }
+}
+
+/// A light that emits from a single point or a single direction.
+///
+/// Delta lights have no surface, so rays never hit them;
+/// they only contribute through next event estimation.
+#[derive(Debug, Clone)]
+pub enum DeltaLight {
+    Point(PointLight),
+    Spot(SpotLight),
+    Directional(DirectionalLight),
+}
+
+/// The light a delta light casts on a point.
+#[derive(Debug, Clone)]
+pub struct LightIncidence {
+    /// direction from the point towards the light
+    pub direction: Vec3A,
+    /// distance to the light, which is infinite for directional lights
+    pub distance: f32,
+    /// irradiance on a surface facing the light
+    pub irradiance: Vec3A,
+}
+
+impl DeltaLight {
+    pub fn illuminate(&self, point: Vec3A) -> Option<LightIncidence> {
+        match self {
+            Self::Point(light) => light.illuminate(point),
+            Self::Spot(light) => light.illuminate(point),
+            Self::Directional(light) => Some(light.illuminate()),
+        }
+    }
+}
+
+/// Emits light equally in all directions.
+#[derive(Debug, Clone)]
+pub struct PointLight {
+    pub position: Vec3A,
+    /// radiant intensity, i.e. power per solid angle
+    pub intensity: Vec3A,
+}
+
+impl PointLight {
+    fn illuminate(&self, point: Vec3A) -> Option<LightIncidence> {
+        let diff = self.position - point;
+        let distance_squared = diff.length_squared();
+
+        if distance_squared < 1e-10 {
+            return None;
+        }
+
+        let distance = distance_squared.sqrt();
+
+        Some(LightIncidence {
+            direction: diff / distance,
+            distance,
+            irradiance: self.intensity / distance_squared,
+        })
+    }
+}
+
+/// Emits light in a cone, fading out smoothly towards its edge.
+#[derive(Debug, Clone)]
+pub struct SpotLight {
+    pub position: Vec3A,
+    /// axis of the cone, pointing away from the light
+    pub direction: Vec3A,
+    /// radiant intensity along the axis of the cone
+    pub intensity: Vec3A,
+    /// cosine of the angle from the axis where the light starts to fade out
+    pub cos_falloff_start: f32,
+    /// cosine of the angle from the axis beyond which there is no light
+    pub cos_cone_angle: f32,
+}
+
+impl SpotLight {
+    /// Angles are measured from the axis of the cone, in degrees.
+    pub fn new(
+        position: Vec3A,
+        direction: Vec3A,
+        intensity: Vec3A,
+        falloff_start: f32,
+        cone_angle: f32,
+    ) -> Self {
+        Self {
+            position,
+            direction: direction.normalize(),
+            intensity,
+            cos_falloff_start: falloff_start.to_radians().cos(),
+            cos_cone_angle: cone_angle.to_radians().cos(),
+        }
+    }
+
+    fn illuminate(&self, point: Vec3A) -> Option<LightIncidence> {
+        let diff = self.position - point;
+        let distance_squared = diff.length_squared();
+
+        if distance_squared < 1e-10 {
+            return None;
+        }
+
+        let distance = distance_squared.sqrt();
+        let direction = diff / distance;
+        let cos_theta = self.direction.dot(-direction);
+
+        if cos_theta <= self.cos_cone_angle {
+            return None;
+        }
+
+        let falloff = smoothstep(self.cos_cone_angle, self.cos_falloff_start, cos_theta);
+
+        Some(LightIncidence {
+            direction,
+            distance,
+            irradiance: self.intensity * falloff / distance_squared,
+        })
+    }
+}
+
+/// Emits parallel light from infinitely far away, like the sun.
+#[derive(Debug, Clone)]
+pub struct DirectionalLight {
+    /// direction the light travels in
+    pub direction: Vec3A,
+    /// irradiance on a surface facing the light
+    pub irradiance: Vec3A,
+}
+
+impl DirectionalLight {
+    fn illuminate(&self) -> LightIncidence {
+        LightIncidence {
+            direction: -self.direction.normalize(),
+            distance: f32::INFINITY,
+            irradiance: self.irradiance,
+        }
+    }
+}
+
+impl From<PointLight> for DeltaLight {
+    fn from(light: PointLight) -> Self {
+        Self::Point(light)
+    }
+}
+
+impl From<SpotLight> for DeltaLight {
+    fn from(light: SpotLight) -> Self {
+        Self::Spot(light)
+    }
+}
+
+impl From<DirectionalLight> for DeltaLight {
+    fn from(light: DirectionalLight) -> Self {
+        Self::Directional(light)
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    if edge0 >= edge1 {
+        return if x >= edge1 { 1.0 } else { 0.0 };
+    }
+
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
@@ -1,8 +1,15 @@
-use crate::brdf::{Brdf, BrdfEval};
+use crate::{
+    integrator::{Integrator, RenderContext},
+    integrators::path::PathTracer,
+    light_sampler::{LightSampler, LightSampling},
+    lights::SceneLights,
+    scene_bvh::SceneBvh,
+    shading::SceneShading,
+};
 use glam::Vec3A;
-use rand::prelude::*;
 use rayon::prelude::*;
-use raytracer_core::{camera::Camera, hit_record::HitRecord, ray::Ray, scene::Scene};
+use raytracer_core::{camera::Camera, ray::Ray, scene::Scene};
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub struct CpuRendererConfig {
@@ -12,23 +19,40 @@ pub struct CpuRendererConfig {
     pub max_ray_bounces: u32,
     pub exposure: f32,
     pub gamma: f32,
+    pub light_sampling: LightSampling,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct CpuRenderer {
     config: CpuRendererConfig,
+    integrator: Arc<dyn Integrator>,
 }
 
 impl CpuRenderer {
+    /// Creates a renderer that uses a [`PathTracer`] until another integrator is set.
     pub fn new(config: CpuRendererConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            integrator: Arc::new(PathTracer),
+        }
+    }
+
+    pub fn with_integrator(mut self, integrator: Arc<dyn Integrator>) -> Self {
+        self.integrator = integrator;
+        self
     }
 
     pub fn config(&self) -> &CpuRendererConfig {
         &self.config
     }
 
-    pub fn render(&self, scene: &Scene, camera: &Camera, brdf: &dyn Brdf) -> Vec<u8> {
+    pub fn render(
+        &self,
+        scene: &Scene,
+        camera: &Camera,
+        shading: &SceneShading,
+        lights: &SceneLights,
+    ) -> Vec<u8> {
         let screen_width = self.config.screen_width;
         let screen_height = self.config.screen_height;
         let aspect_ratio = screen_width as f32 / screen_height as f32;
@@ -37,6 +61,12 @@ impl CpuRenderer {
         let exposure = self.config.exposure;
         let gamma = self.config.gamma;
 
+        let context = RenderContext {
+            scene: SceneBvh::build(scene),
+            shading,
+            lights,
+            light_sampler: LightSampler::build(scene, self.config.light_sampling),
+        };
         let mut buffer = vec![Vec3A::ZERO; (screen_width * screen_height) as usize];
 
         buffer
@@ -52,7 +82,7 @@ impl CpuRenderer {
                     let pixel_x = (x as f32 + rand::random::<f32>()) / screen_width as f32;
                     let pixel_y = (y as f32 + rand::random::<f32>()) / screen_height as f32;
                     let ray = cast_ray(camera, aspect_ratio, pixel_x, pixel_y);
-                    let energy = trace_ray(ray, scene, brdf, max_ray_bounces);
+                    let energy = self.integrator.radiance(ray, &context, max_ray_bounces);
                     color += energy;
                 }
 
@@ -100,206 +130,3 @@ fn map_hdr_to_sdr(color: Vec3A, exposure: f32, gamma: f32) -> Vec3A {
     let color = color / (color + 1f32);
     color.powf(1f32 / gamma)
 }
-
-/// Solves the rendering equation for a given ray, using the BRDF:
-///
-/// `L_o = L_e + f_r * L_i * (N dot L) / pdf`
-///
-/// where:
-/// - `L_o` is the outgoing radiance
-/// - `L_e` is the emitted radiance
-/// - `f_r` is the BRDF
-/// - `L_i` is the incoming radiance
-/// - `N` is the surface normal
-/// - `L` is the light direction
-/// - `pdf` is the probability density function of the BRDF
-///
-/// The function returns the outgoing radiance `L_o`.
-///
-/// The function is recursive, and terminates when the depth limit is reached.
-///
-/// Note that the BRDF is responsible for computing `attenuation`, which represents:
-///
-/// `attenuation = f_r * cos_theta / pdf`
-fn trace_ray<'a>(mut ray: Ray, scene: &'a Scene, brdf: &dyn Brdf, depth: u32) -> Vec3A {
-    let mut result = Vec3A::ZERO;
-    let mut attenuation = Vec3A::ONE;
-    let mut hit: Option<HitRecord<'a>> = scene.hit(&ray, 1e-5, f32::INFINITY);
-
-    for _ in 0..depth {
-        let current_hit = match hit.take() {
-            Some(hit) if hit.front_face => hit,
-            _ => {
-                // the ray did not hit any valid surface. sample environment and return the emission.
-                // TODO: add environment sampling later
-                result += attenuation * Vec3A::ZERO;
-                break;
-            }
-        };
-
-        if current_hit.object.material().is_emissive {
-            // the ray hit an emissive surface; return the emission.
-            // ideal light sources do not reflect light, so we can skip the rest of the computation.
-            result += attenuation * current_hit.object.material().emission;
-            break;
-        }
-
-        // is the surface a delta surface(perfect mirror)?
-        let is_delta_surface = brdf.is_delta_surface(current_hit.object.material());
-        let direct_term = if is_delta_surface {
-            // direct term is zero for delta surfaces.
-            // this is because there is no chance of the direct light being reflected back to the ray shooter.
-            Vec3A::ZERO
-        } else {
-            // compute the contribution of the direct light source.
-            compute_nee_contribution(&current_hit, scene, brdf, -ray.direction)
-        };
-
-        result += attenuation * direct_term;
-
-        let brdf_sample = brdf.sample(
-            -ray.direction,
-            current_hit.normal,
-            current_hit.object.material(),
-        );
-
-        if brdf_sample.attenuation.length_squared() < 1e-5 || brdf_sample.pdf < 1e-5 {
-            // indirect term is too small; ignore it
-            break;
-        }
-
-        ray = Ray::new(
-            current_hit.point + current_hit.normal * 1e-5,
-            brdf_sample.direction,
-        );
-        hit = scene.hit(&ray, 1e-5, f32::INFINITY);
-
-        let should_trace_next = match &hit {
-            Some(next_hit)
-                if next_hit.front_face
-                    && next_hit.object.material().is_emissive
-                    && is_delta_surface =>
-            {
-                // indirect term is coming from a direct light source, and MIS is not needed (because the surface is perfect mirror)
-                let indirect_term = next_hit.object.material().emission * brdf_sample.attenuation;
-                result += attenuation * indirect_term;
-                false
-            }
-            Some(next_hit)
-                if next_hit.front_face
-                    && next_hit.object.material().is_emissive
-                    && !is_delta_surface =>
-            {
-                // indirect term is coming from a direct light source, and MIS is needed
-                let pdf_brdf = brdf_sample.pdf;
-                let r_squared = (next_hit.point - current_hit.point).length_squared();
-                let cos_theta_l = next_hit.normal.dot(-ray.direction).max(0.0);
-
-                let indirect_term = if cos_theta_l < 1e-5 {
-                    Vec3A::ZERO
-                } else {
-                    let light_area = next_hit.object.area();
-                    let n_light = scene.light_count() as f32;
-                    let pdf_light = (r_squared / (cos_theta_l * light_area)) / n_light;
-
-                    let mis_weight_brdf = pdf_brdf / (pdf_light + pdf_brdf);
-                    next_hit.object.material().emission * brdf_sample.attenuation * mis_weight_brdf
-                };
-
-                result += attenuation * indirect_term;
-                false
-            }
-            Some(next_hit) if next_hit.front_face => {
-                // indirect term is coming from a non-direct light source
-                true
-            }
-            _ => {
-                // there is no next hit; ignore it
-                false
-            }
-        };
-
-        if !should_trace_next {
-            break;
-        }
-
-        attenuation *= brdf_sample.attenuation;
-    }
-
-    result
-}
-
-fn compute_nee_contribution(hit: &HitRecord, scene: &Scene, brdf: &dyn Brdf, view: Vec3A) -> Vec3A {
-    let total_light_objects: Vec<_> = scene
-        .objects()
-        .iter()
-        .enumerate()
-        .filter(|(_, object)| object.material().is_emissive)
-        .collect();
-    let chosen_light_object = total_light_objects.choose(&mut rand::rng());
-    let (light_object_index, light_object) = match chosen_light_object {
-        Some((index, object)) => (*index, object.as_ref()),
-        None => {
-            // no light objects; ignore it
-            return Vec3A::ZERO;
-        }
-    };
-
-    let n_light = scene.light_count();
-    let n_light_inv = (n_light as f32).recip();
-
-    let area = light_object.area();
-    let area_inv = area.recip();
-
-    if area < 1e-5 {
-        // area is too small; ignore it
-        return Vec3A::ZERO;
-    }
-
-    let light_point = light_object.sample_point();
-    let diff = light_point.point - hit.point;
-
-    if diff.length_squared() < 1e-5 {
-        // light is too close; ignore it, treating the light as if it is behind the surface
-        return Vec3A::ZERO;
-    }
-
-    let r_squared = diff.length_squared();
-    let r = diff.length();
-    let light_direction = diff / r;
-
-    let cos_theta = hit.normal.dot(light_direction).max(0.0);
-    let cos_theta_l = light_point.normal.dot(-light_direction).max(0.0);
-
-    if cos_theta_l < 1e-5 {
-        // light is not visible; ignore it
-        return Vec3A::ZERO;
-    }
-
-    let shadow_ray = Ray::new(hit.point + hit.normal * 1e-5, light_direction);
-    let is_visible = match scene.hit(&shadow_ray, 1e-5, r) {
-        Some(hit) => hit.object_index == light_object_index,
-        None => true,
-    };
-
-    if !is_visible {
-        // light is not visible; ignore it
-        return Vec3A::ZERO;
-    }
-
-    let BrdfEval { f_r, pdf: pdf_brdf } =
-        brdf.eval(view, hit.normal, light_direction, hit.object.material());
-    let pdf_light = r_squared / cos_theta_l * area_inv * n_light_inv;
-
-    if pdf_brdf < 1e-5 && pdf_light < 1e-5 {
-        // pdf is too small; ignore it
-        return Vec3A::ZERO;
-    }
-
-    let mis_weight = pdf_light / (pdf_brdf + pdf_light);
-    let geometry_term = cos_theta * cos_theta_l / r_squared;
-    let contribution = light_object.material().emission * f_r * geometry_term;
-    let pdf_area = area_inv * n_light_inv;
-
-    (contribution / pdf_area) * mis_weight
-}
@@ -0,0 +1,45 @@
+use raytracer_bvh::Bvh;
+use raytracer_core::{hit_record::HitRecord, ray::Ray, scene::Scene};
+
+/// A scene indexed by a bounding volume hierarchy over its objects.
+///
+/// This answers the same queries as [`Scene::hit`], but in logarithmic rather than linear time.
+/// The hierarchy is built once, so the scene must not change while it is being rendered.
+pub struct SceneBvh<'a> {
+    scene: &'a Scene,
+    bvh: Bvh,
+}
+
+impl<'a> SceneBvh<'a> {
+    pub fn build(scene: &'a Scene) -> Self {
+        let bounding_boxes: Vec<_> = scene
+            .objects()
+            .iter()
+            .map(|object| object.bounding_box())
+            .collect();
+
+        Self {
+            scene,
+            bvh: Bvh::build(&bounding_boxes),
+        }
+    }
+
+    pub fn scene(&self) -> &'a Scene {
+        self.scene
+    }
+
+    /// Finds the closest hit along the ray within `[t_min, t_max]`.
+    pub fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'a>> {
+        let objects = self.scene.objects();
+        let mut closest_hit = None;
+
+        self.bvh.traverse(ray, t_min, t_max, |object_index, t_max| {
+            let hit = objects[object_index].intersect(ray, t_min, t_max, object_index)?;
+            let t = hit.t;
+            closest_hit = Some(hit);
+            Some(t)
+        });
+
+        closest_hit
+    }
+}
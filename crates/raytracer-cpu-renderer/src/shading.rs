@@ -0,0 +1,169 @@
+use crate::{
+    brdf::Brdf,
+    subsurface::RandomWalkSubsurface,
+    texture::{MaterialTextures, TextureCoordinates},
+};
+use glam::Vec3A;
+use raytracer_core::{material::Material, object::Object, scene::Scene};
+use raytracer_primitives::{SharedObject, SurfaceFrame, SurfaceParameterization};
+use std::{borrow::Cow, collections::HashMap, sync::Arc};
+
+/// How a single object scatters light.
+#[derive(Clone)]
+pub struct ObjectShading {
+    pub brdf: Arc<dyn Brdf>,
+    /// replaces the diffuse response of the surface with a random walk through the object's volume
+    pub subsurface: Option<RandomWalkSubsurface>,
+    pub textures: MaterialTextures,
+}
+
+impl ObjectShading {
+    pub fn new(brdf: Arc<dyn Brdf>) -> Self {
+        Self {
+            brdf,
+            subsurface: None,
+            textures: MaterialTextures::default(),
+        }
+    }
+
+    pub fn with_subsurface(mut self, subsurface: RandomWalkSubsurface) -> Self {
+        self.subsurface = Some(subsurface);
+        self
+    }
+
+    pub fn with_textures(mut self, textures: MaterialTextures) -> Self {
+        self.textures = textures;
+        self
+    }
+}
+
+/// Assigns a scattering model to every object of a scene.
+///
+/// Objects are identified by their index in [`Scene::objects`](raytracer_core::scene::Scene::objects),
+/// which is also the `object_index` reported in hit records.
+/// Objects without an explicit assignment fall back to the default shading.
+///
+/// Textures and shading frames need to know where on its surface an object was hit, which the
+/// scene does not tell, so objects should be added through [`SceneShading::add_object`].
+/// Objects added to the scene directly are shaded as if their surface had no parameterization.
+#[derive(Clone)]
+pub struct SceneShading {
+    default: ObjectShading,
+    objects: HashMap<usize, ObjectShading>,
+    surfaces: HashMap<usize, Arc<dyn SurfaceParameterization>>,
+}
+
+impl SceneShading {
+    pub fn new(default_brdf: Arc<dyn Brdf>) -> Self {
+        Self {
+            default: ObjectShading::new(default_brdf),
+            objects: HashMap::new(),
+            surfaces: HashMap::new(),
+        }
+    }
+
+    pub fn default_shading(&self) -> &ObjectShading {
+        &self.default
+    }
+
+    pub fn set(&mut self, object_index: usize, shading: ObjectShading) {
+        self.objects.insert(object_index, shading);
+    }
+
+    pub fn set_brdf(&mut self, object_index: usize, brdf: Arc<dyn Brdf>) {
+        self.set(object_index, ObjectShading::new(brdf));
+    }
+
+    pub fn get(&self, object_index: usize) -> &ObjectShading {
+        self.objects.get(&object_index).unwrap_or(&self.default)
+    }
+
+    pub fn brdf(&self, object_index: usize) -> &dyn Brdf {
+        self.get(object_index).brdf.as_ref()
+    }
+
+    /// Adds an object to the scene, sharing it so that its surface parameterization is available
+    /// while shading. Returns the index of the object.
+    pub fn add_object<T>(&mut self, scene: &mut Scene, object: T) -> usize
+    where
+        T: SurfaceParameterization + 'static,
+    {
+        self.add_shared_object(scene, Arc::new(object))
+    }
+
+    /// Like [`SceneShading::add_object`], for objects that something else needs to share.
+    pub fn add_shared_object<T>(&mut self, scene: &mut Scene, object: Arc<T>) -> usize
+    where
+        T: SurfaceParameterization + 'static,
+    {
+        let object_index = scene.objects().len();
+
+        scene.add_object(SharedObject(object.clone()));
+        self.set_surface(object_index, object);
+
+        object_index
+    }
+
+    pub fn set_surface(&mut self, object_index: usize, surface: Arc<dyn SurfaceParameterization>) {
+        self.surfaces.insert(object_index, surface);
+    }
+
+    /// Returns the frame of an object's surface at a point and normal reported for it.
+    ///
+    /// Objects without a known parameterization get a frame built from the normal alone.
+    pub fn surface_frame(&self, object_index: usize, point: Vec3A, normal: Vec3A) -> SurfaceFrame {
+        match self.surfaces.get(&object_index) {
+            Some(surface) => surface.surface_frame(point, normal),
+            None => SurfaceFrame::from_normal(normal),
+        }
+    }
+
+    /// Returns the material of an object at a point, with its textures evaluated.
+    ///
+    /// `frame` is the [`SceneShading::surface_frame`] of the object at that point.
+    pub fn material_at<'a>(
+        &self,
+        object: &'a dyn Object,
+        object_index: usize,
+        point: Vec3A,
+        frame: &SurfaceFrame,
+    ) -> Cow<'a, Material> {
+        let textures = &self.get(object_index).textures;
+
+        if textures.is_empty() {
+            return Cow::Borrowed(object.material());
+        }
+
+        let coordinates = TextureCoordinates {
+            uv: frame.uv,
+            point,
+            color: frame.color,
+        };
+
+        Cow::Owned(textures.apply(object.material(), &coordinates))
+    }
+
+    /// Returns the radiance emitted by an object at a point.
+    ///
+    /// Emissive surfaces do not scatter light, so this only computes the frame of the surface
+    /// if an emission texture needs it.
+    pub fn emission_at(
+        &self,
+        object: &dyn Object,
+        object_index: usize,
+        point: Vec3A,
+        normal: Vec3A,
+    ) -> Vec3A {
+        match &self.get(object_index).textures.emission {
+            Some(emission) => {
+                let frame = self.surface_frame(object_index, point, normal);
+                emission.evaluate(&TextureCoordinates {
+                    uv: frame.uv,
+                    point,
+                    color: frame.color,
+                })
+            }
+            None => object.material().emission,
+        }
+    }
+}
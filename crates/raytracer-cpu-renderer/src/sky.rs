@@ -0,0 +1,312 @@
+use crate::{
+    brdfs::create_orthonormal_basis,
+    environment::{Environment, EnvironmentMap, EnvironmentSample, luminance, uv_to_direction},
+    hdr::HdrImage,
+};
+use glam::{Mat3A, Vec3A};
+use std::f32::consts::{FRAC_PI_2, PI};
+
+/// angular radius of the sun as seen from the earth, in degrees
+pub const SUN_ANGULAR_RADIUS: f32 = 0.265;
+
+/// maps the luminance of the model, in kcd/m², to radiance;
+/// a clear sky is then roughly one at the zenith
+const LUMINANCE_SCALE: f32 = 0.125;
+/// luminance of the sun before it passes through the atmosphere, in kcd/m²
+const EXTRATERRESTRIAL_SUN_LUMINANCE: f32 = 2.0e6;
+/// resolution of the image the sky is baked into
+const SKY_WIDTH: usize = 512;
+const SKY_HEIGHT: usize = 256;
+
+#[derive(Debug, Clone)]
+pub struct SunSkyConfig {
+    /// direction towards the sun; `+Y` is straight up
+    pub sun_direction: Vec3A,
+    /// haziness of the atmosphere, from about 2 for a very clear sky to 10 for a hazy one
+    pub turbidity: f32,
+    /// scales the brightness of both the sun and the sky
+    pub intensity: f32,
+    /// in degrees
+    pub sun_angular_radius: f32,
+}
+
+impl SunSkyConfig {
+    /// Returns the direction towards a sun at the given elevation above the horizon,
+    /// and azimuth clockwise from `-Z` towards `+X`, both in degrees.
+    pub fn sun_direction_from_angles(elevation: f32, azimuth: f32) -> Vec3A {
+        let (sin_elevation, cos_elevation) = elevation.to_radians().sin_cos();
+        let (sin_azimuth, cos_azimuth) = azimuth.to_radians().sin_cos();
+
+        Vec3A::new(
+            cos_elevation * sin_azimuth,
+            sin_elevation,
+            -cos_elevation * cos_azimuth,
+        )
+    }
+}
+
+/// An outdoor daylight environment: the Preetham sky model and the disk of the sun.
+///
+/// See Preetham et al., "A Practical Analytic Model for Daylight".
+/// The sky is baked into an [`EnvironmentMap`] so that it can be importance sampled, while the
+/// sun is too small for that and is sampled separately. Below the horizon, the environment is
+/// black, so outdoor scenes need a ground of their own.
+#[derive(Debug, Clone)]
+pub struct SunSky {
+    sky: EnvironmentMap,
+    sun_frame: Mat3A,
+    cos_sun_radius: f32,
+    sun_radiance: Vec3A,
+    /// probability of sampling the sun rather than the sky
+    sun_probability: f32,
+}
+
+impl SunSky {
+    pub fn new(config: SunSkyConfig) -> Self {
+        let sun_direction = config.sun_direction.normalize();
+        let turbidity = config.turbidity.max(1.0);
+        let sun_theta = sun_direction.y.clamp(-1.0, 1.0).acos();
+
+        let model = PreethamModel::new(sun_direction, turbidity);
+        let pixels = (0..SKY_HEIGHT)
+            .flat_map(|y| (0..SKY_WIDTH).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let u = (x as f32 + 0.5) / SKY_WIDTH as f32;
+                let v = (y as f32 + 0.5) / SKY_HEIGHT as f32;
+                model.radiance(uv_to_direction(u, v)) * LUMINANCE_SCALE
+            })
+            .collect();
+        let sky = EnvironmentMap::new(HdrImage {
+            width: SKY_WIDTH,
+            height: SKY_HEIGHT,
+            pixels,
+        })
+        .with_intensity(config.intensity);
+
+        let cos_sun_radius = config.sun_angular_radius.to_radians().cos();
+        let sun_radiance = if sun_theta < FRAC_PI_2 {
+            sun_transmittance(sun_theta, turbidity)
+                * EXTRATERRESTRIAL_SUN_LUMINANCE
+                * LUMINANCE_SCALE
+                * config.intensity
+        } else {
+            // the sun has set
+            Vec3A::ZERO
+        };
+
+        let sun_power = luminance(sun_radiance) * cone_solid_angle(cos_sun_radius);
+        let sky_power = sky.luminous_power();
+        let sun_probability = if sun_power > 0.0 {
+            // keep sampling both, so that neither is left to BRDF sampling alone
+            (sun_power / (sun_power + sky_power)).clamp(0.1, 0.9)
+        } else {
+            0.0
+        };
+
+        Self {
+            sky,
+            sun_frame: create_orthonormal_basis(sun_direction),
+            cos_sun_radius,
+            sun_radiance,
+            sun_probability,
+        }
+    }
+
+    pub fn sun_direction(&self) -> Vec3A {
+        self.sun_frame.z_axis
+    }
+
+    fn is_in_sun(&self, direction: Vec3A) -> bool {
+        direction.dot(self.sun_direction()) >= self.cos_sun_radius
+    }
+}
+
+impl Environment for SunSky {
+    fn radiance(&self, direction: Vec3A) -> Vec3A {
+        let sky = self.sky.radiance(direction);
+
+        if self.is_in_sun(direction) {
+            sky + self.sun_radiance
+        } else {
+            sky
+        }
+    }
+
+    fn pdf(&self, direction: Vec3A) -> f32 {
+        let pdf_sun = if self.is_in_sun(direction) {
+            cone_solid_angle(self.cos_sun_radius).recip()
+        } else {
+            0.0
+        };
+
+        self.sun_probability * pdf_sun + (1.0 - self.sun_probability) * self.sky.pdf(direction)
+    }
+
+    fn sample(&self) -> EnvironmentSample {
+        let direction = if rand::random::<f32>() < self.sun_probability {
+            // uniformly sample the cone of directions covered by the sun
+            let cos_theta = 1.0 - rand::random::<f32>() * (1.0 - self.cos_sun_radius);
+            let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+            let phi = 2.0 * PI * rand::random::<f32>();
+
+            self.sun_frame
+                .mul_vec3a(Vec3A::new(
+                    sin_theta * phi.cos(),
+                    sin_theta * phi.sin(),
+                    cos_theta,
+                ))
+                .normalize()
+        } else {
+            self.sky.sample().direction
+        };
+
+        // the density accounts for both strategies, since either could produce the direction
+        EnvironmentSample {
+            direction,
+            radiance: self.radiance(direction),
+            pdf: self.pdf(direction),
+        }
+    }
+}
+
+fn cone_solid_angle(cos_radius: f32) -> f32 {
+    2.0 * PI * (1.0 - cos_radius)
+}
+
+/// The Perez sky luminance distribution, fitted to turbidity by Preetham et al.
+struct PreethamModel {
+    sun_direction: Vec3A,
+    /// luminance and chromaticity at the zenith
+    zenith: Vec3A,
+    /// Perez coefficients for luminance and each chromaticity coordinate
+    coefficients: [[f32; 5]; 3],
+    /// the Perez function at the zenith, which normalizes the distribution
+    zenith_perez: Vec3A,
+}
+
+impl PreethamModel {
+    fn new(sun_direction: Vec3A, turbidity: f32) -> Self {
+        let t = turbidity;
+        // the fits are only valid for the sun above the horizon
+        let theta_s = sun_direction.y.clamp(0.0, 1.0).acos();
+
+        let coefficients = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+
+        let theta = Vec3A::new(theta_s.powi(3), theta_s.powi(2), theta_s);
+        let chromaticity = |t2: [f32; 4], t1: [f32; 4], t0: [f32; 4]| {
+            let row = |c: [f32; 4]| theta.dot(Vec3A::new(c[0], c[1], c[2])) + c[3];
+            t * t * row(t2) + t * row(t1) + row(t0)
+        };
+        let zenith_x = chromaticity(
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        );
+        let zenith_y = chromaticity(
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        );
+
+        let zenith_perez = Vec3A::new(
+            perez(coefficients[0], 0.0, theta_s),
+            perez(coefficients[1], 0.0, theta_s),
+            perez(coefficients[2], 0.0, theta_s),
+        );
+
+        Self {
+            sun_direction,
+            zenith: Vec3A::new(zenith_luminance.max(0.0), zenith_x, zenith_y),
+            coefficients,
+            zenith_perez,
+        }
+    }
+
+    fn radiance(&self, direction: Vec3A) -> Vec3A {
+        if direction.y <= 0.0 {
+            return Vec3A::ZERO;
+        }
+
+        let theta = direction.y.min(1.0).acos();
+        let gamma = direction.dot(self.sun_direction).clamp(-1.0, 1.0).acos();
+
+        let perez = Vec3A::new(
+            perez(self.coefficients[0], theta, gamma),
+            perez(self.coefficients[1], theta, gamma),
+            perez(self.coefficients[2], theta, gamma),
+        );
+        let [luminance, x, y] = (self.zenith * perez / self.zenith_perez).to_array();
+
+        xyy_to_linear_srgb(x, y, luminance)
+    }
+}
+
+/// The Perez formula for the relative distribution of sky luminance,
+/// for a view at `theta` from the zenith and `gamma` from the sun.
+fn perez([a, b, c, d, e]: [f32; 5], theta: f32, gamma: f32) -> f32 {
+    let cos_theta = theta.cos().max(1e-3);
+    let cos_gamma = gamma.cos();
+
+    (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * cos_gamma * cos_gamma)
+}
+
+fn xyy_to_linear_srgb(x: f32, y: f32, luminance: f32) -> Vec3A {
+    if y <= 0.0 {
+        return Vec3A::ZERO;
+    }
+
+    let xyz = Vec3A::new(x / y * luminance, luminance, (1.0 - x - y) / y * luminance);
+    let rgb = Vec3A::new(
+        Vec3A::new(3.2406, -1.5372, -0.4986).dot(xyz),
+        Vec3A::new(-0.9689, 1.8758, 0.0415).dot(xyz),
+        Vec3A::new(0.0557, -0.2040, 1.0570).dot(xyz),
+    );
+
+    rgb.max(Vec3A::ZERO)
+}
+
+/// Computes the fraction of sunlight that reaches the ground through Rayleigh and aerosol
+/// scattering, at the wavelengths of the red, green and blue primaries.
+///
+/// This is the scattering part of the sun attenuation in the appendix of Preetham et al.
+fn sun_transmittance(theta_s: f32, turbidity: f32) -> Vec3A {
+    // wavelengths in micrometers
+    let wavelengths = Vec3A::new(0.65, 0.57, 0.475);
+
+    // relative optical mass of the atmosphere, which grows towards the horizon
+    let theta_degrees = theta_s.to_degrees();
+    let optical_mass = (theta_s.cos() + 0.15 * (93.885 - theta_degrees).powf(-1.253)).recip();
+
+    let rayleigh = (-0.008735 * optical_mass * wavelengths.powf(-4.08)).exp();
+
+    let alpha = 1.3;
+    let beta = 0.04608 * turbidity - 0.04586;
+    let aerosol = (-beta * optical_mass * wavelengths.powf(-alpha)).exp();
+
+    rayleigh * aerosol
+}
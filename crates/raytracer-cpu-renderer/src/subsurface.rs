@@ -0,0 +1,253 @@
+use crate::{
+    brdf::{Brdf, BrdfEval, BrdfSample, DiffuseLayer},
+    brdfs::random_cosine_direction,
+};
+use glam::Vec3A;
+use raytracer_core::{hit_record::HitRecord, material::Material, ray::Ray};
+use std::f32::consts::{FRAC_1_PI, PI};
+
+/// maximum number of scattering events before a random walk is considered absorbed
+const MAX_WALK_STEPS: u32 = 256;
+
+/// Simulates light scattering beneath the surface of an object by random walking through its
+/// volume, for translucent materials such as skin, wax or marble.
+///
+/// The walk replaces the `subsurface` fraction of the diffuse lobe of the surface, which light
+/// enters and leaves through the specular layer above it; the material `albedo` is the color of
+/// the object after all scattering, following Chiang et al., "Practical and Controllable
+/// Subsurface Scattering for Production Path Tracing".
+/// The object must be closed, and other objects inside of it are ignored by the walk.
+#[derive(Debug, Clone, Copy)]
+pub struct RandomWalkSubsurface {
+    /// average distance light travels between scattering events, for each color channel
+    pub scattering_distance: Vec3A,
+}
+
+impl RandomWalkSubsurface {
+    pub fn new(scattering_distance: Vec3A) -> Self {
+        Self {
+            scattering_distance,
+        }
+    }
+
+    /// Walks from the point where light enters the object to the point where it leaves it.
+    ///
+    /// Returns the exit point along with the throughput of the walk,
+    /// or `None` if the light was absorbed.
+    pub(crate) fn walk<'a>(
+        &self,
+        entry: &HitRecord<'a>,
+        albedo: Vec3A,
+    ) -> Option<(HitRecord<'a>, Vec3A)> {
+        let outward_normal = if entry.front_face {
+            entry.normal
+        } else {
+            -entry.normal
+        };
+        let sigma_t = self.scattering_distance.max(Vec3A::splat(1e-6)).recip();
+        let single_scattering_albedo = Vec3A::new(
+            single_scattering_albedo(albedo.x),
+            single_scattering_albedo(albedo.y),
+            single_scattering_albedo(albedo.z),
+        );
+
+        let mut throughput = Vec3A::ONE;
+        let mut ray = Ray::new(
+            entry.point - outward_normal * 1e-5,
+            random_cosine_direction(-outward_normal),
+        );
+
+        for _ in 0..MAX_WALK_STEPS {
+            // distances are sampled from a randomly chosen channel; the throughput is weighted by
+            // the average density over all channels, so that every channel remains unbiased
+            let channel = rand::random_range(0..3);
+            let distance = -(1.0 - rand::random::<f32>()).ln() / sigma_t[channel];
+
+            if let Some(exit) = entry
+                .object
+                .intersect(&ray, 1e-5, distance, entry.object_index)
+            {
+                let transmittance = (-sigma_t * exit.t).exp();
+                let pdf = transmittance.element_sum() / 3.0;
+                throughput *= transmittance / pdf;
+
+                return Some((leaving_through_back_face(exit, ray.direction), throughput));
+            }
+
+            let transmittance = (-sigma_t * distance).exp();
+            let pdf = (sigma_t * transmittance).element_sum() / 3.0;
+            throughput *= single_scattering_albedo * sigma_t * transmittance / pdf;
+
+            if throughput.max_element() < 1e-5 {
+                return None;
+            }
+
+            ray = Ray::new(
+                ray.origin + ray.direction * distance,
+                random_sphere_direction(),
+            );
+        }
+
+        None
+    }
+}
+
+/// Scatters light at the surface of an object whose diffuse lobe is partly replaced by a random
+/// walk, leaving out the `subsurface` fraction of the lobe that the walk stands in for.
+///
+/// The specular, sheen and clearcoat lobes of the surface are kept as they are.
+#[derive(Clone, Copy)]
+pub(crate) struct SubsurfaceEntryBrdf<'a> {
+    pub brdf: &'a dyn Brdf,
+    pub subsurface: f32,
+}
+
+impl Brdf for SubsurfaceEntryBrdf<'_> {
+    fn is_delta_surface(&self, material: &Material) -> bool {
+        self.brdf.is_delta_surface(material)
+    }
+
+    fn is_transmissive(&self, material: &Material) -> bool {
+        self.brdf.is_transmissive(material)
+    }
+
+    fn eval(
+        &self,
+        view: Vec3A,
+        normal: Vec3A,
+        tangent: Vec3A,
+        light: Vec3A,
+        material: &Material,
+    ) -> BrdfEval {
+        let BrdfEval { f_r, pdf } = self.brdf.eval(view, normal, tangent, light, material);
+        let diffuse = self.brdf.eval_diffuse(view, normal, light, material);
+
+        BrdfEval {
+            f_r: (f_r - self.subsurface * diffuse).max(Vec3A::ZERO),
+            pdf,
+        }
+    }
+
+    fn sample(
+        &self,
+        view: Vec3A,
+        normal: Vec3A,
+        tangent: Vec3A,
+        material: &Material,
+    ) -> BrdfSample {
+        let sample = self.brdf.sample(view, normal, tangent, material);
+
+        if self.is_delta_surface(material) || sample.pdf < 1e-5 {
+            return sample;
+        }
+
+        // directions are still sampled from the whole surface, including the missing part of
+        // the diffuse lobe, so the attenuation is computed again without it
+        let BrdfEval { f_r, .. } = self.eval(view, normal, tangent, sample.direction, material);
+        let n_dot_l = normal.dot(sample.direction).abs();
+
+        BrdfSample {
+            attenuation: f_r * n_dot_l / sample.pdf,
+            ..sample
+        }
+    }
+
+    fn diffuse_layer(&self, material: &Material) -> Option<DiffuseLayer> {
+        self.brdf.diffuse_layer(material)
+    }
+
+    fn eval_diffuse(&self, view: Vec3A, normal: Vec3A, light: Vec3A, material: &Material) -> Vec3A {
+        (1.0 - self.subsurface) * self.brdf.eval_diffuse(view, normal, light, material)
+    }
+}
+
+/// Scatters the light leaving an object at the end of a random walk.
+///
+/// The walk already accounts for the color of the object, so this is a white Lambertian lobe
+/// on the outside of the object, dimmed by the light the specular layer of the surface reflects
+/// back inside.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SubsurfaceExitBrdf {
+    pub layer: DiffuseLayer,
+}
+
+impl Brdf for SubsurfaceExitBrdf {
+    fn is_delta_surface(&self, _material: &Material) -> bool {
+        false
+    }
+
+    fn is_transmissive(&self, _material: &Material) -> bool {
+        true
+    }
+
+    fn eval(
+        &self,
+        _view: Vec3A,
+        normal: Vec3A,
+        _tangent: Vec3A,
+        light: Vec3A,
+        _material: &Material,
+    ) -> BrdfEval {
+        let n_dot_l = normal.dot(light);
+
+        if n_dot_l <= 0.0 {
+            return BrdfEval::ZERO;
+        }
+
+        BrdfEval {
+            f_r: self.layer.transmittance(n_dot_l) * FRAC_1_PI,
+            pdf: n_dot_l * FRAC_1_PI,
+        }
+    }
+
+    fn sample(
+        &self,
+        _view: Vec3A,
+        normal: Vec3A,
+        _tangent: Vec3A,
+        _material: &Material,
+    ) -> BrdfSample {
+        let light = random_cosine_direction(normal);
+        let n_dot_l = normal.dot(light).max(0.0);
+
+        BrdfSample {
+            direction: light,
+            attenuation: self.layer.transmittance(n_dot_l),
+            pdf: n_dot_l * FRAC_1_PI,
+        }
+    }
+}
+
+/// The walk reaches the surface from inside the object, so the exit must be a back face hit, with
+/// its normal pointing back inside. Objects that are not closed or that get the side wrong would
+/// otherwise send the exiting light back into the object, so their records are turned around.
+fn leaving_through_back_face<'a>(exit: HitRecord<'a>, direction: Vec3A) -> HitRecord<'a> {
+    let normal = if exit.normal.dot(direction) > 0.0 {
+        -exit.normal
+    } else {
+        exit.normal
+    };
+
+    HitRecord {
+        normal,
+        front_face: false,
+        ..exit
+    }
+}
+
+/// Inverts the multiple scattering albedo of a semi-infinite slab into the albedo of a single
+/// scattering event, using the fit from Chiang et al.
+fn single_scattering_albedo(albedo: f32) -> f32 {
+    let a = albedo.clamp(0.0, 1.0);
+    let s = 4.09712 + 4.20863 * a - (9.59217 + 41.6808 * a + 17.7126 * a * a).sqrt();
+
+    1.0 - s * s
+}
+
+fn random_sphere_direction() -> Vec3A {
+    let z = 1.0 - 2.0 * rand::random::<f32>();
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * rand::random::<f32>();
+
+    Vec3A::new(r * phi.cos(), r * phi.sin(), z)
+}
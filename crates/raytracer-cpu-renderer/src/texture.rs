@@ -0,0 +1,167 @@
+use glam::{Vec2, Vec3A};
+use raytracer_core::material::Material;
+use raytracer_primitives::SurfaceFrame;
+use std::sync::Arc;
+
+/// Where a texture is looked up on a surface.
+#[derive(Debug, Clone)]
+pub struct TextureCoordinates {
+    /// surface parameterization, with `v` pointing up the image
+    pub uv: Vec2,
+    /// world space position of the point
+    pub point: Vec3A,
+    /// the color interpolated from vertex colors, for surfaces that have them
+    pub color: Option<Vec3A>,
+}
+
+/// A color that varies over a surface.
+pub trait Texture: Send + Sync {
+    fn evaluate(&self, coordinates: &TextureCoordinates) -> Vec3A;
+}
+
+/// A texture driving a scalar material parameter, read from one of its channels.
+#[derive(Clone)]
+pub struct ScalarTexture {
+    pub texture: Arc<dyn Texture>,
+    /// 0 for red, 1 for green and 2 for blue
+    pub channel: usize,
+}
+
+impl ScalarTexture {
+    pub fn new(texture: Arc<dyn Texture>, channel: usize) -> Self {
+        Self { texture, channel }
+    }
+
+    pub fn evaluate(&self, coordinates: &TextureCoordinates) -> f32 {
+        self.texture.evaluate(coordinates)[self.channel.min(2)]
+    }
+}
+
+/// Textures replacing the constant parameters of a material.
+///
+/// Parameters without a texture keep the value from the material.
+/// Light selection still weighs emissive objects by their constant emission,
+/// so an emission texture should be paired with a representative constant.
+#[derive(Clone, Default)]
+pub struct MaterialTextures {
+    pub albedo: Option<Arc<dyn Texture>>,
+    pub emission: Option<Arc<dyn Texture>>,
+    pub roughness: Option<ScalarTexture>,
+    pub metallic: Option<ScalarTexture>,
+    pub normal: Option<NormalPerturbation>,
+}
+
+impl MaterialTextures {
+    pub fn is_empty(&self) -> bool {
+        self.albedo.is_none()
+            && self.emission.is_none()
+            && self.roughness.is_none()
+            && self.metallic.is_none()
+            && self.normal.is_none()
+    }
+
+    /// Returns these textures, taking the ones they lack from `other`.
+    pub fn or(self, other: Self) -> Self {
+        Self {
+            albedo: self.albedo.or(other.albedo),
+            emission: self.emission.or(other.emission),
+            roughness: self.roughness.or(other.roughness),
+            metallic: self.metallic.or(other.metallic),
+            normal: self.normal.or(other.normal),
+        }
+    }
+
+    /// Returns the material with its textured parameters evaluated at the given coordinates.
+    pub fn apply(&self, material: &Material, coordinates: &TextureCoordinates) -> Material {
+        let mut material = material.clone();
+
+        if let Some(albedo) = &self.albedo {
+            material.albedo = albedo.evaluate(coordinates);
+        }
+
+        if let Some(emission) = &self.emission {
+            material.emission = emission.evaluate(coordinates);
+        }
+
+        if let Some(roughness) = &self.roughness {
+            material.roughness = roughness.evaluate(coordinates).clamp(0.0, 1.0);
+        }
+
+        if let Some(metallic) = &self.metallic {
+            material.metallic = metallic.evaluate(coordinates).clamp(0.0, 1.0);
+        }
+
+        material
+    }
+}
+
+/// Perturbs the shading normal of a surface to add detail without adding geometry.
+#[derive(Clone)]
+pub enum NormalPerturbation {
+    /// a tangent space normal map, with linear colors in `[0, 1]` mapped to `[-1, 1]`;
+    /// `+X` follows `u`, and `+Y` follows `v`, as in OpenGL and glTF
+    NormalMap(Arc<dyn Texture>),
+    /// a height field, offsetting the surface along its normal by the texture times `scale`
+    Bump { height: ScalarTexture, scale: f32 },
+}
+
+impl NormalPerturbation {
+    /// Returns the perturbed shading normal, facing outwards like the one of the frame.
+    pub fn perturb(&self, frame: &SurfaceFrame, point: Vec3A) -> Vec3A {
+        let normal = frame.shading_normal;
+
+        let perturbed = match self {
+            Self::NormalMap(texture) => {
+                let coordinates = TextureCoordinates {
+                    uv: frame.uv,
+                    point,
+                    color: frame.color,
+                };
+                let local = texture.evaluate(&coordinates) * 2.0 - 1.0;
+
+                // tangent and bitangent orthogonalized against the shading normal
+                let tangent = (frame.dpdu - normal * normal.dot(frame.dpdu)).normalize_or_zero();
+                let mut bitangent = normal.cross(tangent);
+
+                if bitangent.dot(frame.dpdv) < 0.0 {
+                    bitangent = -bitangent;
+                }
+
+                tangent * local.x + bitangent * local.y + normal * local.z
+            }
+            Self::Bump { height, scale } => {
+                // finite differences over a small step in UV space, as in pbrt
+                const DELTA: f32 = 5e-4;
+
+                let height_at = |uv: Vec2, point: Vec3A| {
+                    height.evaluate(&TextureCoordinates {
+                        uv,
+                        point,
+                        color: frame.color,
+                    }) * scale
+                };
+
+                let displacement = height_at(frame.uv, point);
+                let u_displacement =
+                    height_at(frame.uv + Vec2::new(DELTA, 0.0), point + frame.dpdu * DELTA);
+                let v_displacement =
+                    height_at(frame.uv + Vec2::new(0.0, DELTA), point + frame.dpdv * DELTA);
+
+                let dpdu = frame.dpdu + normal * ((u_displacement - displacement) / DELTA);
+                let dpdv = frame.dpdv + normal * ((v_displacement - displacement) / DELTA);
+
+                dpdu.cross(dpdv)
+            }
+        };
+
+        let perturbed = perturbed.normalize_or_zero();
+
+        if perturbed == Vec3A::ZERO {
+            normal
+        } else if perturbed.dot(normal) < 0.0 {
+            -perturbed
+        } else {
+            perturbed
+        }
+    }
+}
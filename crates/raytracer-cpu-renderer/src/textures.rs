@@ -0,0 +1,71 @@
+pub mod checker;
+pub mod grid;
+pub mod image;
+pub mod noise;
+
+use crate::texture::{Texture, TextureCoordinates};
+use glam::{Affine3A, Vec2, Vec3A};
+
+/// How a procedural texture places its pattern on a surface.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextureMapping {
+    /// the pattern follows the surface parameterization, in the `XY` plane of pattern space
+    Uv { scale: Vec2, offset: Vec2 },
+    /// the pattern is fixed in space, regardless of how surfaces are parameterized;
+    /// `transform` maps world space to pattern space, so passing the inverse of an object's
+    /// placement makes the pattern move with the object
+    Space { transform: Affine3A },
+}
+
+impl TextureMapping {
+    pub fn uv() -> Self {
+        Self::Uv {
+            scale: Vec2::ONE,
+            offset: Vec2::ZERO,
+        }
+    }
+
+    pub fn world() -> Self {
+        Self::Space {
+            transform: Affine3A::IDENTITY,
+        }
+    }
+
+    /// Returns the point in pattern space.
+    pub fn map(&self, coordinates: &TextureCoordinates) -> Vec3A {
+        match self {
+            Self::Uv { scale, offset } => (coordinates.uv * *scale + *offset).extend(0.0).into(),
+            Self::Space { transform } => transform.transform_point3a(coordinates.point),
+        }
+    }
+}
+
+impl Default for TextureMapping {
+    fn default() -> Self {
+        Self::uv()
+    }
+}
+
+/// The same color everywhere, mostly useful as an input of other textures.
+#[derive(Debug, Clone)]
+pub struct ConstantTexture(pub Vec3A);
+
+impl Texture for ConstantTexture {
+    fn evaluate(&self, _coordinates: &TextureCoordinates) -> Vec3A {
+        self.0
+    }
+}
+
+/// The color interpolated from the vertex colors of a mesh, such as those of a scanned PLY file.
+///
+/// Surfaces without vertex colors get `fallback`.
+#[derive(Debug, Clone)]
+pub struct VertexColorTexture {
+    pub fallback: Vec3A,
+}
+
+impl Texture for VertexColorTexture {
+    fn evaluate(&self, coordinates: &TextureCoordinates) -> Vec3A {
+        coordinates.color.unwrap_or(self.fallback)
+    }
+}
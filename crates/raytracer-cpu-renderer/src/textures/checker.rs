@@ -0,0 +1,36 @@
+use crate::{
+    texture::{Texture, TextureCoordinates},
+    textures::TextureMapping,
+};
+use glam::Vec3A;
+use std::sync::Arc;
+
+/// Alternates between two textures on a grid of unit cells in pattern space.
+///
+/// With a UV mapping, the cells are squares on the surface; with a spatial mapping, they are cubes
+/// the surface cuts through.
+#[derive(Clone)]
+pub struct CheckerTexture {
+    pub mapping: TextureMapping,
+    pub even: Arc<dyn Texture>,
+    pub odd: Arc<dyn Texture>,
+}
+
+impl CheckerTexture {
+    pub fn new(mapping: TextureMapping, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Self {
+        Self { mapping, even, odd }
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn evaluate(&self, coordinates: &TextureCoordinates) -> Vec3A {
+        let cell = self.mapping.map(coordinates).floor();
+        let parity = (cell.x + cell.y + cell.z).rem_euclid(2.0);
+
+        if parity < 0.5 {
+            self.even.evaluate(coordinates)
+        } else {
+            self.odd.evaluate(coordinates)
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use crate::{
+    texture::{Texture, TextureCoordinates},
+    textures::TextureMapping,
+};
+use glam::Vec3A;
+use std::sync::Arc;
+
+/// Draws lines at every integer coordinate of pattern space, which makes UV layouts and
+/// distortion easy to see.
+#[derive(Clone)]
+pub struct GridTexture {
+    pub mapping: TextureMapping,
+    pub line: Arc<dyn Texture>,
+    pub background: Arc<dyn Texture>,
+    /// width of the lines, as a fraction of a cell
+    pub line_width: f32,
+}
+
+impl GridTexture {
+    pub fn new(
+        mapping: TextureMapping,
+        line: Arc<dyn Texture>,
+        background: Arc<dyn Texture>,
+    ) -> Self {
+        Self {
+            mapping,
+            line,
+            background,
+            line_width: 0.05,
+        }
+    }
+
+    pub fn with_line_width(mut self, line_width: f32) -> Self {
+        self.line_width = line_width;
+        self
+    }
+}
+
+impl Texture for GridTexture {
+    fn evaluate(&self, coordinates: &TextureCoordinates) -> Vec3A {
+        let point = self.mapping.map(coordinates);
+
+        // distance to the nearest line along each axis; the `Z` axis only matters in space
+        let distance = (point - point.round()).abs();
+        let half_width = self.line_width * 0.5;
+        let is_line = match self.mapping {
+            TextureMapping::Uv { .. } => distance.x < half_width || distance.y < half_width,
+            TextureMapping::Space { .. } => {
+                // lines are where two of the three coordinates are close to an integer
+                let near = distance.cmplt(Vec3A::splat(half_width));
+                (near.bitmask().count_ones()) >= 2
+            }
+        };
+
+        if is_line {
+            self.line.evaluate(coordinates)
+        } else {
+            self.background.evaluate(coordinates)
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use crate::{
+    hdr::{HdrError, HdrImage, load_hdr},
+    texture::{Texture, TextureCoordinates},
+};
+use glam::{Vec2, Vec3A};
+use std::{
+    fmt::Display,
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+/// How texture coordinates outside of `[0, 1]` are mapped onto the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WrapMode {
+    #[default]
+    Repeat,
+    MirroredRepeat,
+    Clamp,
+}
+
+/// How the values stored in an 8-bit image are encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSpace {
+    /// for colors, such as albedo and emission
+    Srgb,
+    /// for data, such as roughness and metallic
+    Linear,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Filter {
+    Nearest,
+    #[default]
+    Bilinear,
+}
+
+/// A texture sampled from an image.
+///
+/// `v` points up the image, so `(0, 0)` is the bottom left corner, as in OBJ and pbrt.
+#[derive(Debug, Clone)]
+pub struct ImageTexture {
+    /// linear colors, stored row by row from the top
+    image: HdrImage,
+    pub wrap_mode: WrapMode,
+    pub filter: Filter,
+    /// multiplies every texel
+    pub scale: f32,
+}
+
+#[derive(Debug)]
+pub enum TextureError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Png {
+        path: PathBuf,
+        source: png::DecodingError,
+    },
+    Hdr(HdrError),
+    UnsupportedFormat {
+        path: PathBuf,
+    },
+}
+
+impl Display for TextureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io { path, source } => {
+                write!(f, "failed to read `{}`: {}", path.display(), source)
+            }
+            Self::Png { path, source } => {
+                write!(
+                    f,
+                    "`{}` is not a valid PNG image: {}",
+                    path.display(),
+                    source
+                )
+            }
+            Self::Hdr(error) => error.fmt(f),
+            Self::UnsupportedFormat { path } => {
+                write!(
+                    f,
+                    "`{}` is not a supported texture, expected a `.png` or `.hdr` image",
+                    path.display()
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for TextureError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Png { source, .. } => Some(source),
+            Self::Hdr(error) => Some(error),
+            Self::UnsupportedFormat { .. } => None,
+        }
+    }
+}
+
+impl ImageTexture {
+    pub fn new(image: HdrImage) -> Self {
+        Self {
+            image,
+            wrap_mode: WrapMode::default(),
+            filter: Filter::default(),
+            scale: 1.0,
+        }
+    }
+
+    /// Loads a `.png` or Radiance `.hdr` image.
+    ///
+    /// The color space only applies to PNG images; HDR images are always linear.
+    pub fn load(path: impl AsRef<Path>, color_space: ColorSpace) -> Result<Self, TextureError> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+
+        let image = match extension.as_deref() {
+            Some("png") => load_png(path, color_space)?,
+            Some("hdr") => load_hdr(path).map_err(TextureError::Hdr)?,
+            _ => {
+                return Err(TextureError::UnsupportedFormat {
+                    path: path.to_owned(),
+                });
+            }
+        };
+
+        Ok(Self::new(image))
+    }
+
+    pub fn with_wrap_mode(mut self, wrap_mode: WrapMode) -> Self {
+        self.wrap_mode = wrap_mode;
+        self
+    }
+
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn image(&self) -> &HdrImage {
+        &self.image
+    }
+
+    /// Returns the texel at the given integer coordinates, which may lie outside the image.
+    fn texel(&self, x: i64, y: i64) -> Vec3A {
+        let x = wrap(x, self.image.width, self.wrap_mode);
+        let y = wrap(y, self.image.height, self.wrap_mode);
+
+        self.image.pixel(x, y)
+    }
+
+    fn lookup(&self, uv: Vec2) -> Vec3A {
+        let width = self.image.width as f32;
+        let height = self.image.height as f32;
+
+        // texel centers sit at half-integer coordinates
+        let x = uv.x * width - 0.5;
+        let y = (1.0 - uv.y) * height - 0.5;
+
+        match self.filter {
+            Filter::Nearest => self.texel(x.round() as i64, y.round() as i64),
+            Filter::Bilinear => {
+                let (x0, y0) = (x.floor(), y.floor());
+                let (tx, ty) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+
+                let top = self.texel(x0, y0).lerp(self.texel(x0 + 1, y0), tx);
+                let bottom = self.texel(x0, y0 + 1).lerp(self.texel(x0 + 1, y0 + 1), tx);
+
+                top.lerp(bottom, ty)
+            }
+        }
+    }
+}
+
+impl Texture for ImageTexture {
+    fn evaluate(&self, coordinates: &TextureCoordinates) -> Vec3A {
+        if !coordinates.uv.is_finite() {
+            return Vec3A::ZERO;
+        }
+
+        self.lookup(coordinates.uv) * self.scale
+    }
+}
+
+fn wrap(coordinate: i64, size: usize, wrap_mode: WrapMode) -> usize {
+    let size = size as i64;
+
+    let wrapped = match wrap_mode {
+        WrapMode::Repeat => coordinate.rem_euclid(size),
+        WrapMode::MirroredRepeat => {
+            let period = coordinate.rem_euclid(2 * size);
+            if period < size {
+                period
+            } else {
+                2 * size - 1 - period
+            }
+        }
+        WrapMode::Clamp => coordinate.clamp(0, size - 1),
+    };
+
+    wrapped as usize
+}
+
+fn load_png(path: &Path, color_space: ColorSpace) -> Result<HdrImage, TextureError> {
+    let png_error = |source| TextureError::Png {
+        path: path.to_owned(),
+        source,
+    };
+
+    let file = File::open(path).map_err(|source| TextureError::Io {
+        path: path.to_owned(),
+        source,
+    })?;
+    let mut decoder = png::Decoder::new(BufReader::new(file));
+
+    // expand palettes and low bit depths, and reduce 16-bit channels to 8 bits
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+
+    let mut reader = decoder.read_info().map_err(png_error)?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(png_error)?;
+
+    let channels = info.color_type.samples();
+    let decode = |value: u8| {
+        let value = value as f32 / 255.0;
+        match color_space {
+            ColorSpace::Srgb => srgb_to_linear(value),
+            ColorSpace::Linear => value,
+        }
+    };
+
+    // alpha is ignored
+    let pixels = buffer[..info.buffer_size()]
+        .chunks_exact(channels)
+        .map(|texel| match texel {
+            [gray] | [gray, _] => Vec3A::splat(decode(*gray)),
+            [r, g, b] | [r, g, b, _] => Vec3A::new(decode(*r), decode(*g), decode(*b)),
+            _ => Vec3A::ZERO,
+        })
+        .collect();
+
+    Ok(HdrImage {
+        width: info.width as usize,
+        height: info.height as usize,
+        pixels,
+    })
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
@@ -0,0 +1,252 @@
+use crate::{
+    texture::{Texture, TextureCoordinates},
+    textures::TextureMapping,
+};
+use glam::Vec3A;
+use std::sync::Arc;
+
+/// How octaves of Perlin noise are summed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NoiseKind {
+    /// fractional Brownian motion, a soft and cloudy pattern
+    #[default]
+    Fbm,
+    /// the sum of the absolute values of the octaves, with sharp creases where the noise crosses
+    /// zero
+    Turbulence,
+}
+
+/// Parameters shared by the noise based textures.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Octaves {
+    pub count: u32,
+    /// how much the frequency grows from one octave to the next
+    pub lacunarity: f32,
+    /// how much the amplitude shrinks from one octave to the next
+    pub gain: f32,
+}
+
+impl Default for Octaves {
+    fn default() -> Self {
+        Self {
+            count: 6,
+            lacunarity: 2.0,
+            gain: 0.5,
+        }
+    }
+}
+
+/// Blends between two textures with Perlin noise.
+#[derive(Clone)]
+pub struct NoiseTexture {
+    pub mapping: TextureMapping,
+    pub kind: NoiseKind,
+    pub octaves: Octaves,
+    /// the texture where the noise is lowest
+    pub low: Arc<dyn Texture>,
+    /// the texture where the noise is highest
+    pub high: Arc<dyn Texture>,
+}
+
+impl NoiseTexture {
+    pub fn new(mapping: TextureMapping, low: Arc<dyn Texture>, high: Arc<dyn Texture>) -> Self {
+        Self {
+            mapping,
+            kind: NoiseKind::default(),
+            octaves: Octaves::default(),
+            low,
+            high,
+        }
+    }
+
+    pub fn with_kind(mut self, kind: NoiseKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn with_octaves(mut self, octaves: Octaves) -> Self {
+        self.octaves = octaves;
+        self
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn evaluate(&self, coordinates: &TextureCoordinates) -> Vec3A {
+        let point = self.mapping.map(coordinates);
+
+        let blend = match self.kind {
+            NoiseKind::Fbm => fbm(point, &self.octaves) * 0.5 + 0.5,
+            NoiseKind::Turbulence => turbulence(point, &self.octaves),
+        };
+        let blend = blend.clamp(0.0, 1.0);
+
+        self.low
+            .evaluate(coordinates)
+            .lerp(self.high.evaluate(coordinates), blend)
+    }
+}
+
+/// Veins of one texture running through another, from a sine wave along `X` in pattern space
+/// distorted by turbulence, after Perlin.
+#[derive(Clone)]
+pub struct MarbleTexture {
+    pub mapping: TextureMapping,
+    pub octaves: Octaves,
+    /// number of veins per unit along `X`
+    pub frequency: f32,
+    /// how far the turbulence pushes the veins around
+    pub distortion: f32,
+    pub base: Arc<dyn Texture>,
+    pub vein: Arc<dyn Texture>,
+}
+
+impl MarbleTexture {
+    pub fn new(mapping: TextureMapping, base: Arc<dyn Texture>, vein: Arc<dyn Texture>) -> Self {
+        Self {
+            mapping,
+            octaves: Octaves::default(),
+            frequency: 1.0,
+            distortion: 1.5,
+            base,
+            vein,
+        }
+    }
+
+    pub fn with_octaves(mut self, octaves: Octaves) -> Self {
+        self.octaves = octaves;
+        self
+    }
+
+    pub fn with_frequency(mut self, frequency: f32) -> Self {
+        self.frequency = frequency;
+        self
+    }
+
+    pub fn with_distortion(mut self, distortion: f32) -> Self {
+        self.distortion = distortion;
+        self
+    }
+}
+
+impl Texture for MarbleTexture {
+    fn evaluate(&self, coordinates: &TextureCoordinates) -> Vec3A {
+        let point = self.mapping.map(coordinates);
+
+        let phase = point.x * self.frequency + self.distortion * turbulence(point, &self.octaves);
+        let wave = (phase * std::f32::consts::TAU).sin() * 0.5 + 0.5;
+
+        // sharpen the veins so that most of the surface is the base
+        let blend = (1.0 - wave).powi(4);
+
+        self.base
+            .evaluate(coordinates)
+            .lerp(self.vein.evaluate(coordinates), blend)
+    }
+}
+
+/// Sums octaves of Perlin noise, roughly in `[-1, 1]`.
+pub fn fbm(point: Vec3A, octaves: &Octaves) -> f32 {
+    sum_octaves(point, octaves, perlin)
+}
+
+/// Sums the absolute values of octaves of Perlin noise, roughly in `[0, 1]`.
+pub fn turbulence(point: Vec3A, octaves: &Octaves) -> f32 {
+    sum_octaves(point, octaves, |point| perlin(point).abs())
+}
+
+fn sum_octaves(point: Vec3A, octaves: &Octaves, noise: impl Fn(Vec3A) -> f32) -> f32 {
+    let mut sum = 0.0;
+    let mut amplitude = 1.0;
+    let mut total_amplitude = 0.0;
+    let mut frequency = 1.0;
+
+    for _ in 0..octaves.count {
+        sum += noise(point * frequency) * amplitude;
+        total_amplitude += amplitude;
+        amplitude *= octaves.gain;
+        frequency *= octaves.lacunarity;
+    }
+
+    if total_amplitude > 0.0 {
+        sum / total_amplitude
+    } else {
+        0.0
+    }
+}
+
+/// Perlin's improved noise, in `[-1, 1]` and zero at every integer point.
+///
+/// The permutation is fixed, so patterns are the same from one render to the next.
+pub fn perlin(point: Vec3A) -> f32 {
+    let cell = point.floor();
+    let local = point - cell;
+
+    // wrapping keeps the lattice periodic, every 256 units
+    let x = (cell.x as i64).rem_euclid(256) as usize;
+    let y = (cell.y as i64).rem_euclid(256) as usize;
+    let z = (cell.z as i64).rem_euclid(256) as usize;
+
+    let hash = |x: usize, y: usize, z: usize| {
+        PERMUTATION[(PERMUTATION[(PERMUTATION[x & 255] as usize + y) & 255] as usize + z) & 255]
+    };
+
+    let fade = |t: f32| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+    let (u, v, w) = (fade(local.x), fade(local.y), fade(local.z));
+
+    let corner = |dx: usize, dy: usize, dz: usize| {
+        gradient(
+            hash(x + dx, y + dy, z + dz),
+            local - Vec3A::new(dx as f32, dy as f32, dz as f32),
+        )
+    };
+
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+
+    lerp(
+        lerp(
+            lerp(corner(0, 0, 0), corner(1, 0, 0), u),
+            lerp(corner(0, 1, 0), corner(1, 1, 0), u),
+            v,
+        ),
+        lerp(
+            lerp(corner(0, 0, 1), corner(1, 0, 1), u),
+            lerp(corner(0, 1, 1), corner(1, 1, 1), u),
+            v,
+        ),
+        w,
+    )
+}
+
+/// Dots the offset with one of the twelve edge directions of a cube, chosen by the hash.
+fn gradient(hash: u8, offset: Vec3A) -> f32 {
+    let hash = hash & 15;
+    let u = if hash < 8 { offset.x } else { offset.y };
+    let v = match hash {
+        0..4 => offset.y,
+        12 | 14 => offset.x,
+        _ => offset.z,
+    };
+
+    (if hash & 1 == 0 { u } else { -u }) + (if hash & 2 == 0 { v } else { -v })
+}
+
+/// The permutation from Perlin's reference implementation.
+#[rustfmt::skip]
+const PERMUTATION: [u8; 256] = [
+    151, 160, 137, 91, 90, 15, 131, 13, 201, 95, 96, 53, 194, 233, 7, 225,
+    140, 36, 103, 30, 69, 142, 8, 99, 37, 240, 21, 10, 23, 190, 6, 148,
+    247, 120, 234, 75, 0, 26, 197, 62, 94, 252, 219, 203, 117, 35, 11, 32,
+    57, 177, 33, 88, 237, 149, 56, 87, 174, 20, 125, 136, 171, 168, 68, 175,
+    74, 165, 71, 134, 139, 48, 27, 166, 77, 146, 158, 231, 83, 111, 229, 122,
+    60, 211, 133, 230, 220, 105, 92, 41, 55, 46, 245, 40, 244, 102, 143, 54,
+    65, 25, 63, 161, 1, 216, 80, 73, 209, 76, 132, 187, 208, 89, 18, 169,
+    200, 196, 135, 130, 116, 188, 159, 86, 164, 100, 109, 198, 173, 186, 3, 64,
+    52, 217, 226, 250, 124, 123, 5, 202, 38, 147, 118, 126, 255, 82, 85, 212,
+    207, 206, 59, 227, 47, 16, 58, 17, 182, 189, 28, 42, 223, 183, 170, 213,
+    119, 248, 152, 2, 44, 154, 163, 70, 221, 153, 101, 155, 167, 43, 172, 9,
+    129, 22, 39, 253, 19, 98, 108, 110, 79, 113, 224, 232, 178, 185, 112, 104,
+    218, 246, 97, 228, 251, 34, 242, 193, 238, 210, 144, 12, 191, 179, 162, 241,
+    81, 51, 145, 235, 249, 14, 239, 107, 49, 192, 214, 31, 181, 199, 106, 157,
+    184, 84, 204, 176, 115, 121, 50, 45, 127, 4, 150, 254, 138, 236, 205, 93,
+    222, 114, 67, 29, 24, 72, 243, 141, 128, 195, 78, 66, 215, 61, 156, 180,
+];
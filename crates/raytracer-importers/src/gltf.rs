@@ -0,0 +1,329 @@
+use crate::materials::{DEFAULT_MATERIAL, specular_from_ior};
+use glam::{Affine3A, Mat4, Vec2, Vec3, Vec3A};
+use gltf::{
+    Gltf,
+    camera::Projection,
+    mesh::{Mode, util::ReadIndices},
+};
+use raytracer_core::{camera::Camera, material::Material, object::Object, scene::Scene};
+use raytracer_cpu_renderer::shading::SceneShading;
+use raytracer_primitives::{Instance, Mesh};
+use std::{collections::HashMap, fmt::Display, path::Path, sync::Arc};
+
+#[derive(Debug)]
+pub enum GltfError {
+    Gltf(gltf::Error),
+    /// the document has no scene to import
+    NoScene,
+    InvalidPrimitive {
+        mesh: String,
+        message: String,
+    },
+}
+
+impl Display for GltfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Gltf(err) => write!(f, "failed to load glTF: {}", err),
+            Self::NoScene => write!(f, "the glTF document does not contain any scene"),
+            Self::InvalidPrimitive { mesh, message } => {
+                write!(f, "invalid primitive in mesh `{}`: {}", mesh, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GltfError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Gltf(err) => Some(err),
+            Self::NoScene | Self::InvalidPrimitive { .. } => None,
+        }
+    }
+}
+
+impl From<gltf::Error> for GltfError {
+    fn from(err: gltf::Error) -> Self {
+        Self::Gltf(err)
+    }
+}
+
+/// Loads a `.gltf` or `.glb` file and adds every triangle primitive of its default scene to the
+/// scene, with the node hierarchy flattened into world space and placed by `transform`. Their
+/// surfaces are registered with the shading, along with the first set of texture coordinates of
+/// each primitive. `material` replaces the materials of the file when given.
+///
+/// Each glTF mesh is converted only once; every node referencing it becomes an [`Instance`].
+///
+/// Only buffers embedded in the file or stored next to it are loaded; remote URIs are rejected.
+/// Images are not loaded, as textures are not supported.
+///
+/// Returns the perspective cameras found in the scene, in node order, placed by `transform` too.
+/// Orthographic cameras are skipped, since [`Camera`] only supports perspective projection.
+pub fn add_gltf_to_scene(
+    scene: &mut Scene,
+    shading: &mut SceneShading,
+    path: impl AsRef<Path>,
+    transform: Affine3A,
+    material: Option<&Material>,
+) -> Result<Vec<Camera>, GltfError> {
+    let path = path.as_ref();
+    let Gltf { document, blob } = Gltf::open(path)?;
+    let buffers = gltf::import_buffers(&document, path.parent(), blob)?;
+
+    let mut importer = Importer {
+        scene,
+        shading,
+        buffers: &buffers,
+        material,
+        meshes: HashMap::new(),
+        cameras: Vec::new(),
+    };
+
+    for node in default_scene(&document)?.nodes() {
+        importer.import_node(&node, Mat4::from(transform))?;
+    }
+
+    Ok(importer.cameras)
+}
+
+/// Returns the perspective cameras of a `.gltf` or `.glb` file, like [`add_gltf_to_scene`] does,
+/// without loading its geometry.
+pub fn load_gltf_cameras(path: impl AsRef<Path>) -> Result<Vec<Camera>, GltfError> {
+    let Gltf { document, .. } = Gltf::open(path)?;
+    let mut cameras = Vec::new();
+
+    for node in default_scene(&document)?.nodes() {
+        collect_cameras(&node, Mat4::IDENTITY, &mut cameras);
+    }
+
+    Ok(cameras)
+}
+
+fn default_scene(document: &gltf::Document) -> Result<gltf::Scene<'_>, GltfError> {
+    document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .ok_or(GltfError::NoScene)
+}
+
+fn collect_cameras(node: &gltf::Node, parent_transform: Mat4, cameras: &mut Vec<Camera>) {
+    let transform = parent_transform * Mat4::from_cols_array_2d(&node.transform().matrix());
+
+    if let Some(camera) = node
+        .camera()
+        .and_then(|camera| convert_camera(&camera, transform))
+    {
+        cameras.push(camera);
+    }
+
+    for child in node.children() {
+        collect_cameras(&child, transform, cameras);
+    }
+}
+
+struct Importer<'a> {
+    scene: &'a mut Scene,
+    shading: &'a mut SceneShading,
+    buffers: &'a [gltf::buffer::Data],
+    /// replaces the materials of the file when set
+    material: Option<&'a Material>,
+    /// converted primitives, keyed by the index of the glTF mesh they belong to
+    meshes: HashMap<usize, Vec<Arc<Mesh>>>,
+    cameras: Vec<Camera>,
+}
+
+impl Importer<'_> {
+    fn import_node(&mut self, node: &gltf::Node, parent_transform: Mat4) -> Result<(), GltfError> {
+        let transform = parent_transform * Mat4::from_cols_array_2d(&node.transform().matrix());
+
+        if let Some(camera) = node
+            .camera()
+            .and_then(|camera| convert_camera(&camera, transform))
+        {
+            self.cameras.push(camera);
+        }
+
+        // nodes with a singular transform, such as a zero scale, are invisible
+        if let Some(mesh) = node.mesh().filter(|_| transform.determinant() != 0.0) {
+            let object_to_world = Affine3A::from_mat4(transform);
+
+            for geometry in self.convert_mesh(&mesh)?.to_vec() {
+                if geometry.material().is_emissive && !Instance::scales_uniformly(object_to_world) {
+                    // lights are sampled by area, which only instances scaled uniformly preserve,
+                    // so the transform is applied to a copy of the mesh
+                    self.shading
+                        .add_object(self.scene, geometry.transformed(object_to_world));
+                } else {
+                    self.shading
+                        .add_object(self.scene, Instance::new(geometry, object_to_world));
+                }
+            }
+        }
+
+        for child in node.children() {
+            self.import_node(&child, transform)?;
+        }
+
+        Ok(())
+    }
+
+    /// Converts the triangle primitives of a mesh, reusing the result of earlier conversions.
+    fn convert_mesh(&mut self, mesh: &gltf::Mesh) -> Result<&[Arc<Mesh>], GltfError> {
+        if !self.meshes.contains_key(&mesh.index()) {
+            let mut geometries = Vec::new();
+
+            for primitive in mesh.primitives() {
+                if let Some(geometry) = self.convert_primitive(mesh, &primitive)? {
+                    geometries.push(Arc::new(geometry));
+                }
+            }
+
+            self.meshes.insert(mesh.index(), geometries);
+        }
+
+        Ok(&self.meshes[&mesh.index()])
+    }
+
+    /// Converts a primitive into a mesh in the local space of the glTF mesh.
+    /// Returns `None` for primitives that are not made of triangles, such as points and lines.
+    fn convert_primitive(
+        &self,
+        mesh: &gltf::Mesh,
+        primitive: &gltf::Primitive,
+    ) -> Result<Option<Mesh>, GltfError> {
+        let invalid_primitive = |message: &str| GltfError::InvalidPrimitive {
+            mesh: mesh
+                .name()
+                .map(str::to_owned)
+                .unwrap_or_else(|| format!("#{}", mesh.index())),
+            message: message.to_owned(),
+        };
+
+        let mode = primitive.mode();
+
+        if !matches!(
+            mode,
+            Mode::Triangles | Mode::TriangleStrip | Mode::TriangleFan
+        ) {
+            return Ok(None);
+        }
+
+        let reader = primitive.reader(|buffer| Some(&self.buffers[buffer.index()].0[..]));
+
+        let positions = reader
+            .read_positions()
+            .ok_or_else(|| invalid_primitive("missing POSITION attribute"))?
+            .map(Vec3A::from)
+            .collect::<Vec<_>>();
+        let normals = reader.read_normals().map(|normals| {
+            normals
+                .map(|normal| Vec3A::from(normal).normalize_or_zero())
+                .collect::<Vec<_>>()
+        });
+
+        if normals
+            .as_ref()
+            .is_some_and(|normals| normals.len() != positions.len())
+        {
+            return Err(invalid_primitive(
+                "NORMAL and POSITION attributes have different lengths",
+            ));
+        }
+
+        // glTF puts the origin of UV space at the top left of images, while `v` points up here
+        let uvs = reader.read_tex_coords(0).map(|uvs| {
+            uvs.into_f32()
+                .map(|[u, v]| Vec2::new(u, 1.0 - v))
+                .collect::<Vec<_>>()
+        });
+
+        if uvs.as_ref().is_some_and(|uvs| uvs.len() != positions.len()) {
+            return Err(invalid_primitive(
+                "TEXCOORD_0 and POSITION attributes have different lengths",
+            ));
+        }
+
+        let indices = match reader.read_indices() {
+            Some(ReadIndices::U8(indices)) => indices.map(u32::from).collect(),
+            Some(ReadIndices::U16(indices)) => indices.map(u32::from).collect(),
+            Some(ReadIndices::U32(indices)) => indices.collect(),
+            None => (0..positions.len() as u32).collect::<Vec<_>>(),
+        };
+
+        if indices
+            .iter()
+            .any(|&index| positions.len() <= index as usize)
+        {
+            return Err(invalid_primitive("vertex index out of range"));
+        }
+
+        let triangles = triangulate(mode, &indices).collect();
+
+        let material = match self.material {
+            Some(material) => material.clone(),
+            None => convert_material(&primitive.material()),
+        };
+        let mesh = Mesh::new(positions, normals, triangles, material);
+
+        Ok(Some(match uvs {
+            Some(uvs) => mesh.with_uvs(uvs),
+            None => mesh,
+        }))
+    }
+}
+
+fn triangulate(mode: Mode, indices: &[u32]) -> impl Iterator<Item = [u32; 3]> + '_ {
+    let triangle_count = match mode {
+        Mode::Triangles => indices.len() / 3,
+        _ => indices.len().saturating_sub(2),
+    };
+
+    (0..triangle_count).map(move |i| match mode {
+        Mode::TriangleStrip if i % 2 == 1 => [indices[i + 1], indices[i], indices[i + 2]],
+        Mode::TriangleStrip => [indices[i], indices[i + 1], indices[i + 2]],
+        Mode::TriangleFan => [indices[0], indices[i + 1], indices[i + 2]],
+        _ => [indices[i * 3], indices[i * 3 + 1], indices[i * 3 + 2]],
+    })
+}
+
+fn convert_camera(camera: &gltf::Camera, transform: Mat4) -> Option<Camera> {
+    let Projection::Perspective(perspective) = camera.projection() else {
+        return None;
+    };
+
+    // glTF cameras look down their local -Z axis, with +Y up
+    let position = Vec3A::from(transform.transform_point3(Vec3::ZERO));
+    let direction = Vec3A::from(transform.transform_vector3(Vec3::NEG_Z)).normalize();
+    let up = Vec3A::from(transform.transform_vector3(Vec3::Y)).normalize();
+
+    Some(Camera::look_at(
+        position,
+        position + direction,
+        up,
+        perspective.yfov().to_degrees(),
+    ))
+}
+
+/// Maps the glTF metallic-roughness model onto [`Material`].
+///
+/// The alpha channel of the base color and all textures are ignored.
+fn convert_material(material: &gltf::Material) -> Material {
+    let pbr = material.pbr_metallic_roughness();
+    let [r, g, b, _] = pbr.base_color_factor();
+
+    let emission =
+        Vec3A::from(material.emissive_factor()) * material.emissive_strength().unwrap_or(1.0);
+    let is_emissive = emission.max_element() > 0.0;
+
+    Material {
+        is_emissive,
+        emission,
+        albedo: Vec3A::new(r, g, b),
+        metallic: pbr.metallic_factor(),
+        roughness: pbr.roughness_factor(),
+        // glTF assumes an index of refraction of 1.5 unless told otherwise
+        specular: specular_from_ior(material.ior().unwrap_or(1.5)),
+        ..DEFAULT_MATERIAL
+    }
+}
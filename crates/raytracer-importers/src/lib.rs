@@ -0,0 +1,8 @@
+mod materials;
+
+pub mod gltf;
+pub mod obj;
+pub mod ply;
+pub mod stl;
+
+pub use materials::DEFAULT_MATERIAL;
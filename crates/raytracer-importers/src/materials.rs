@@ -0,0 +1,32 @@
+use glam::Vec3A;
+use raytracer_core::material::Material;
+
+/// The material assigned to imported geometry that does not specify one.
+pub const DEFAULT_MATERIAL: Material = Material {
+    is_emissive: false,
+    emission: Vec3A::ZERO,
+    albedo: Vec3A::splat(0.8),
+    subsurface: 0.0,
+    metallic: 0.0,
+    specular: 0.5,
+    specular_tint: Vec3A::ZERO,
+    roughness: 0.5,
+    anisotropic: 0.0,
+    sheen: 0.0,
+    sheen_tint: Vec3A::ZERO,
+    clearcoat: 0.0,
+    clearcoat_gloss: 0.0,
+};
+
+/// Converts an index of refraction into the Disney `specular` parameter,
+/// which maps `[0, 1]` onto a normal-incidence reflectance of `[0, 0.08]`.
+pub(crate) fn specular_from_ior(ior: f32) -> f32 {
+    let f0 = ((ior - 1.0) / (ior + 1.0)).powi(2);
+    (f0 / 0.08).clamp(0.0, 1.0)
+}
+
+/// Converts a Phong specular exponent into a perceptual roughness,
+/// using `alpha = sqrt(2 / (n + 2))` and `alpha = roughness^2`.
+pub(crate) fn roughness_from_phong_exponent(exponent: f32) -> f32 {
+    (2.0 / (exponent.max(0.0) + 2.0)).powf(0.25)
+}
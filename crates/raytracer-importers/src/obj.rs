@@ -0,0 +1,472 @@
+mod mtl;
+
+pub use mtl::*;
+
+use crate::materials::DEFAULT_MATERIAL;
+use glam::{Vec2, Vec3A};
+use raytracer_core::{material::Material, scene::Scene};
+use raytracer_cpu_renderer::shading::SceneShading;
+use raytracer_primitives::Mesh;
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+impl ObjError {
+    fn parse(path: &Path, line: usize, message: impl Into<String>) -> Self {
+        Self::Parse {
+            path: path.to_owned(),
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for ObjError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io { path, source } => {
+                write!(f, "failed to read `{}`: {}", path.display(), source)
+            }
+            Self::Parse {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", path.display(), line, message),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Parse { .. } => None,
+        }
+    }
+}
+
+/// Loads a Wavefront OBJ file, along with the MTL libraries it references.
+///
+/// Faces are grouped by material, so the result contains one mesh per material in use.
+/// Polygons with more than three vertices are triangulated as fans.
+pub fn load_obj(path: impl AsRef<Path>) -> Result<Vec<Mesh>, ObjError> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path).map_err(|source| ObjError::Io {
+        path: path.to_owned(),
+        source,
+    })?;
+
+    parse_obj(&source, path)
+}
+
+/// Loads a Wavefront OBJ file and adds all of its meshes to the scene,
+/// registering their surfaces with the shading.
+pub fn add_obj_to_scene(
+    scene: &mut Scene,
+    shading: &mut SceneShading,
+    path: impl AsRef<Path>,
+) -> Result<(), ObjError> {
+    for mesh in load_obj(path)? {
+        shading.add_object(scene, mesh);
+    }
+
+    Ok(())
+}
+
+/// Parses the contents of an OBJ file.
+///
+/// `path` is used to resolve `mtllib` statements and to report errors.
+pub fn parse_obj(source: &str, path: &Path) -> Result<Vec<Mesh>, ObjError> {
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut normals = Vec::new();
+    let mut materials = HashMap::new();
+    let mut groups: Vec<MeshBuilder> = vec![MeshBuilder::new(DEFAULT_MATERIAL)];
+    let mut group_indices = HashMap::<String, usize>::new();
+    let mut current_group = 0;
+
+    for (line_index, line) in source.lines().enumerate() {
+        let line_number = line_index + 1;
+        let line = strip_comment(line);
+        let mut tokens = line.split_whitespace();
+
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+
+        match keyword {
+            "v" => {
+                let position = parse_vec3(&mut tokens)
+                    .map_err(|message| ObjError::parse(path, line_number, message))?;
+                positions.push(position);
+            }
+            "vt" => {
+                let uv = parse_uv(&mut tokens)
+                    .map_err(|message| ObjError::parse(path, line_number, message))?;
+                uvs.push(uv);
+            }
+            "vn" => {
+                let normal = parse_vec3(&mut tokens)
+                    .map_err(|message| ObjError::parse(path, line_number, message))?;
+                normals.push(normal.normalize_or_zero());
+            }
+            "f" => {
+                let vertices = tokens
+                    .map(|token| {
+                        parse_face_vertex(token, positions.len(), uvs.len(), normals.len())
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|message| ObjError::parse(path, line_number, message))?;
+
+                if vertices.len() < 3 {
+                    return Err(ObjError::parse(
+                        path,
+                        line_number,
+                        format!(
+                            "face has {} vertices; at least 3 are required",
+                            vertices.len()
+                        ),
+                    ));
+                }
+
+                let group = &mut groups[current_group];
+
+                for i in 1..vertices.len() - 1 {
+                    group.push_triangle(
+                        [vertices[0], vertices[i], vertices[i + 1]],
+                        &positions,
+                        &uvs,
+                        &normals,
+                    );
+                }
+            }
+            "mtllib" => {
+                // file names may contain spaces, so take the rest of the line as is
+                let file_name = line[keyword.len()..].trim();
+
+                if file_name.is_empty() {
+                    return Err(ObjError::parse(path, line_number, "missing MTL file name"));
+                }
+
+                materials.extend(load_mtl(base_dir.join(file_name))?);
+            }
+            "usemtl" => {
+                let name = line[keyword.len()..].trim();
+                let material = materials.get(name).ok_or_else(|| {
+                    ObjError::parse(path, line_number, format!("undefined material `{name}`"))
+                })?;
+
+                current_group = *group_indices.entry(name.to_owned()).or_insert_with(|| {
+                    groups.push(MeshBuilder::new(material.to_material()));
+                    groups.len() - 1
+                });
+            }
+            // object/group names, smoothing groups and free-form geometry
+            // do not affect the triangle geometry
+            _ => {}
+        }
+    }
+
+    Ok(groups
+        .into_iter()
+        .filter(|group| !group.triangles.is_empty())
+        .map(MeshBuilder::build)
+        .collect())
+}
+
+fn strip_comment(line: &str) -> &str {
+    match line.find('#') {
+        Some(index) => &line[..index],
+        None => line,
+    }
+}
+
+fn parse_vec3<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Result<Vec3A, String> {
+    let mut components = [0.0; 3];
+
+    for component in &mut components {
+        let token = tokens
+            .next()
+            .ok_or_else(|| "expected 3 components".to_owned())?;
+        *component = token
+            .parse()
+            .map_err(|_| format!("invalid number `{token}`"))?;
+    }
+
+    Ok(Vec3A::from_array(components))
+}
+
+/// Parses the `u` and optional `v` of a texture coordinate, ignoring any `w`.
+fn parse_uv<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Result<Vec2, String> {
+    let mut parse = |default: Option<f32>| match (tokens.next(), default) {
+        (Some(token), _) => token
+            .parse()
+            .map_err(|_| format!("invalid number `{token}`")),
+        (None, Some(default)) => Ok(default),
+        (None, None) => Err("expected at least 1 component".to_owned()),
+    };
+
+    Ok(Vec2::new(parse(None)?, parse(Some(0.0))?))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct FaceVertex {
+    position: usize,
+    uv: Option<usize>,
+    normal: Option<usize>,
+}
+
+/// Parses a face vertex in one of the forms `v`, `v/vt`, `v//vn` or `v/vt/vn`.
+fn parse_face_vertex(
+    token: &str,
+    position_count: usize,
+    uv_count: usize,
+    normal_count: usize,
+) -> Result<FaceVertex, String> {
+    let mut parts = token.split('/');
+
+    let position = match parts.next() {
+        Some(index) if !index.is_empty() => resolve_index(index, position_count, "vertex")?,
+        _ => return Err(format!("invalid face vertex `{token}`")),
+    };
+    let uv = match parts.next() {
+        Some(index) if !index.is_empty() => {
+            Some(resolve_index(index, uv_count, "texture coordinate")?)
+        }
+        _ => None,
+    };
+    let normal = match parts.next() {
+        Some(index) if !index.is_empty() => Some(resolve_index(index, normal_count, "normal")?),
+        _ => None,
+    };
+
+    if parts.next().is_some() {
+        return Err(format!("invalid face vertex `{token}`"));
+    }
+
+    Ok(FaceVertex {
+        position,
+        uv,
+        normal,
+    })
+}
+
+/// Converts a 1-based (or negative, relative) OBJ index into a 0-based index.
+fn resolve_index(index: &str, count: usize, kind: &str) -> Result<usize, String> {
+    let value = index
+        .parse::<isize>()
+        .map_err(|_| format!("invalid {kind} index `{index}`"))?;
+
+    let resolved = match value {
+        1.. => value - 1,
+        ..0 => count as isize + value,
+        0 => return Err(format!("{kind} index must not be zero")),
+    };
+
+    if resolved < 0 || count as isize <= resolved {
+        return Err(format!(
+            "{kind} index {value} is out of range; only {count} are defined so far"
+        ));
+    }
+
+    Ok(resolved as usize)
+}
+
+struct MeshBuilder {
+    material: Material,
+    positions: Vec<Vec3A>,
+    uvs: Vec<Vec2>,
+    normals: Vec<Vec3A>,
+    triangles: Vec<[u32; 3]>,
+    vertex_indices: HashMap<FaceVertex, u32>,
+    /// per-vertex UVs are only kept if every vertex of the mesh has one
+    has_all_uvs: bool,
+    /// per-vertex normals are only kept if every vertex of the mesh has one
+    has_all_normals: bool,
+}
+
+impl MeshBuilder {
+    fn new(material: Material) -> Self {
+        Self {
+            material,
+            positions: Vec::new(),
+            uvs: Vec::new(),
+            normals: Vec::new(),
+            triangles: Vec::new(),
+            vertex_indices: HashMap::new(),
+            has_all_uvs: true,
+            has_all_normals: true,
+        }
+    }
+
+    fn push_triangle(
+        &mut self,
+        vertices: [FaceVertex; 3],
+        positions: &[Vec3A],
+        uvs: &[Vec2],
+        normals: &[Vec3A],
+    ) {
+        let triangle = vertices.map(|vertex| {
+            *self.vertex_indices.entry(vertex).or_insert_with(|| {
+                self.positions.push(positions[vertex.position]);
+
+                match vertex.uv {
+                    Some(uv) => self.uvs.push(uvs[uv]),
+                    None => {
+                        self.uvs.push(Vec2::ZERO);
+                        self.has_all_uvs = false;
+                    }
+                }
+
+                match vertex.normal {
+                    Some(normal) => self.normals.push(normals[normal]),
+                    None => {
+                        self.normals.push(Vec3A::ZERO);
+                        self.has_all_normals = false;
+                    }
+                }
+
+                (self.positions.len() - 1) as u32
+            })
+        });
+
+        self.triangles.push(triangle);
+    }
+
+    fn build(self) -> Mesh {
+        let normals = self.has_all_normals.then_some(self.normals);
+        let mesh = Mesh::new(self.positions, normals, self.triangles, self.material);
+
+        if self.has_all_uvs {
+            mesh.with_uvs(self.uvs)
+        } else {
+            mesh
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Vec<Mesh> {
+        parse_obj(source, Path::new("test.obj")).unwrap()
+    }
+
+    #[test]
+    fn negative_indices_count_back_from_the_last_element() {
+        let meshes = parse(
+            "\
+v 0 0 0
+v 1 0 0
+v 1 1 0
+vt 0 0
+vt 1 1
+vn 0 0 1
+f 1/1/1 2/1/1 3/2/1
+v 0 0 1
+v 1 0 1
+v 1 1 1
+vt 0.5 0.5
+vn 0 0 -2
+f -3/-2/-1 -2/-2/-1 -1/-1/-1
+",
+        );
+
+        let [mesh] = &meshes[..] else {
+            panic!("expected one mesh, got {}", meshes.len());
+        };
+        assert_eq!(
+            mesh.positions(),
+            [
+                Vec3A::ZERO,
+                Vec3A::X,
+                Vec3A::new(1.0, 1.0, 0.0),
+                Vec3A::Z,
+                Vec3A::new(1.0, 0.0, 1.0),
+                Vec3A::ONE,
+            ]
+        );
+        assert_eq!(mesh.triangles(), [[0, 1, 2], [3, 4, 5]]);
+        assert_eq!(
+            mesh.uvs().unwrap()[3..],
+            [Vec2::ONE, Vec2::ONE, Vec2::splat(0.5)]
+        );
+        // normals are normalized when they are read
+        assert_eq!(mesh.normals().unwrap()[3..], [Vec3A::NEG_Z; 3]);
+    }
+
+    #[test]
+    fn negative_indices_only_see_what_was_defined_before_them() {
+        let error = parse_obj("v 0 0 0\nv 1 0 0\nf -1 -2 -3\n", Path::new("test.obj"));
+
+        match error {
+            Err(ObjError::Parse { line, message, .. }) => {
+                assert_eq!(line, 3);
+                assert_eq!(
+                    message,
+                    "vertex index -3 is out of range; only 2 are defined so far"
+                );
+            }
+            other => panic!("expected a parse error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn polygons_are_triangulated_as_fans() {
+        let meshes = parse(
+            "\
+v 0 0 0
+v 1 0 0
+v 1.5 1 0
+v 0.5 1.5 0
+v -0.5 1 0
+f 1 2 3 4 5
+",
+        );
+
+        assert_eq!(meshes.len(), 1);
+        assert_eq!(meshes[0].triangles(), [[0, 1, 2], [0, 2, 3], [0, 3, 4]]);
+        // the vertices are shared by the triangles rather than repeated
+        assert_eq!(meshes[0].positions().len(), 5);
+    }
+
+    #[test]
+    fn undefined_materials_are_errors() {
+        let error = parse_obj(
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\n\nusemtl missing\nf 1 2 3\n",
+            Path::new("test.obj"),
+        );
+
+        match error {
+            Err(ObjError::Parse {
+                path,
+                line,
+                message,
+            }) => {
+                assert_eq!(path, Path::new("test.obj"));
+                assert_eq!(line, 5);
+                assert_eq!(message, "undefined material `missing`");
+            }
+            other => panic!("expected a parse error, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use super::ObjError;
+use crate::materials::{DEFAULT_MATERIAL, roughness_from_phong_exponent, specular_from_ior};
+use glam::Vec3A;
+use raytracer_core::material::Material;
+use std::{collections::HashMap, fs, path::Path};
+
+/// A material as described by an MTL file, before it is mapped onto [`Material`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MtlMaterial {
+    /// `Kd`
+    pub diffuse: Vec3A,
+    /// `Ks`
+    pub specular: Vec3A,
+    /// `Ns`
+    pub specular_exponent: f32,
+    /// `Ke`
+    pub emission: Vec3A,
+    /// `d`, or `1 - Tr`
+    pub dissolve: f32,
+    /// `Ni`
+    pub ior: Option<f32>,
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        Self {
+            diffuse: Vec3A::splat(0.8),
+            specular: Vec3A::ZERO,
+            specular_exponent: 0.0,
+            emission: Vec3A::ZERO,
+            dissolve: 1.0,
+            ior: None,
+        }
+    }
+}
+
+impl MtlMaterial {
+    /// Maps the MTL parameters onto the Disney material model:
+    ///
+    /// - `Kd` becomes the albedo
+    /// - `Ni` determines the specular reflectance if present; otherwise the largest component of
+    ///   `Ks` is used as is
+    /// - `Ns` is converted into roughness
+    /// - a non-zero `Ke` makes the material emissive
+    ///
+    /// The material model has no notion of transparency, so `d` is kept in [`Self::dissolve`] but
+    /// does not affect the result.
+    pub fn to_material(&self) -> Material {
+        let specular = match self.ior {
+            Some(ior) => specular_from_ior(ior),
+            None => self.specular.max_element().clamp(0.0, 1.0),
+        };
+        let is_emissive = self.emission.max_element() > 0.0;
+
+        Material {
+            is_emissive,
+            emission: self.emission,
+            albedo: self.diffuse,
+            specular,
+            roughness: roughness_from_phong_exponent(self.specular_exponent),
+            ..DEFAULT_MATERIAL
+        }
+    }
+}
+
+/// Loads all materials defined in an MTL file, keyed by name.
+pub fn load_mtl(path: impl AsRef<Path>) -> Result<HashMap<String, MtlMaterial>, ObjError> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path).map_err(|source| ObjError::Io {
+        path: path.to_owned(),
+        source,
+    })?;
+
+    parse_mtl(&source, path)
+}
+
+/// Parses the contents of an MTL file. `path` is only used to report errors.
+pub fn parse_mtl(source: &str, path: &Path) -> Result<HashMap<String, MtlMaterial>, ObjError> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlMaterial)> = None;
+
+    for (line_index, line) in source.lines().enumerate() {
+        let line_number = line_index + 1;
+        let line = super::strip_comment(line);
+        let mut tokens = line.split_whitespace();
+
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+
+        if keyword == "newmtl" {
+            let name = line[keyword.len()..].trim();
+
+            if name.is_empty() {
+                return Err(ObjError::parse(path, line_number, "missing material name"));
+            }
+
+            if let Some((name, material)) = current.take() {
+                materials.insert(name, material);
+            }
+
+            current = Some((name.to_owned(), MtlMaterial::default()));
+            continue;
+        }
+
+        let Some((_, material)) = &mut current else {
+            return Err(ObjError::parse(
+                path,
+                line_number,
+                format!("`{keyword}` appears before any `newmtl`"),
+            ));
+        };
+        let parse_error = |message: String| ObjError::parse(path, line_number, message);
+
+        match keyword {
+            "Kd" => material.diffuse = parse_color(&mut tokens).map_err(parse_error)?,
+            "Ks" => material.specular = parse_color(&mut tokens).map_err(parse_error)?,
+            "Ke" => material.emission = parse_color(&mut tokens).map_err(parse_error)?,
+            "Ns" => material.specular_exponent = parse_scalar(&mut tokens).map_err(parse_error)?,
+            "Ni" => material.ior = Some(parse_scalar(&mut tokens).map_err(parse_error)?),
+            "d" => material.dissolve = parse_scalar(&mut tokens).map_err(parse_error)?,
+            "Tr" => material.dissolve = 1.0 - parse_scalar(&mut tokens).map_err(parse_error)?,
+            // other statements (ambient color, illumination models, texture maps, ...) are not supported
+            _ => {}
+        }
+    }
+
+    if let Some((name, material)) = current {
+        materials.insert(name, material);
+    }
+
+    Ok(materials)
+}
+
+fn parse_scalar<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Result<f32, String> {
+    let token = tokens
+        .next()
+        .ok_or_else(|| "expected a number".to_owned())?;
+    token
+        .parse()
+        .map_err(|_| format!("invalid number `{token}`"))
+}
+
+/// Parses an RGB color. A single component is treated as a gray value, as allowed by the format.
+fn parse_color<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Result<Vec3A, String> {
+    let r = parse_scalar(tokens)?;
+
+    match tokens.next() {
+        Some(token) => {
+            let g = token
+                .parse()
+                .map_err(|_| format!("invalid number `{token}`"))?;
+            let b = parse_scalar(tokens)?;
+            Ok(Vec3A::new(r, g, b))
+        }
+        None => Ok(Vec3A::splat(r)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "\
+# two materials
+newmtl glass
+Kd 0.1 0.2 0.3
+Ks 1
+Ns 98
+Ni 1.5
+
+newmtl lamp
+Kd 0.5
+Ks 0.2 0.6 0.4
+Ke 4 3 2
+";
+
+    #[test]
+    fn materials_are_mapped_onto_the_disney_model() {
+        let materials = parse_mtl(SOURCE, Path::new("test.mtl")).unwrap();
+
+        let glass = materials["glass"].to_material();
+        assert_eq!(glass.albedo, Vec3A::new(0.1, 0.2, 0.3));
+        // an index of refraction of 1.5 reflects 4% at normal incidence, whatever `Ks` says
+        assert!((glass.specular - 0.5).abs() < 1e-6);
+        // `alpha = sqrt(2 / (98 + 2))`
+        assert!((glass.roughness - 0.02f32.powf(0.25)).abs() < 1e-6);
+        assert!(!glass.is_emissive);
+
+        let lamp = materials["lamp"].to_material();
+        assert_eq!(lamp.albedo, Vec3A::splat(0.5));
+        assert_eq!(lamp.specular, 0.6);
+        // without `Ns`, the surface is as rough as it gets
+        assert_eq!(lamp.roughness, 1.0);
+        assert!(lamp.is_emissive);
+        assert_eq!(lamp.emission, Vec3A::new(4.0, 3.0, 2.0));
+    }
+}
@@ -0,0 +1,667 @@
+use glam::{Vec2, Vec3A};
+use raytracer_core::material::Material;
+use raytracer_primitives::Mesh;
+use std::{
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug)]
+pub enum PlyError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        message: String,
+    },
+}
+
+impl PlyError {
+    fn parse(path: &Path, message: impl Into<String>) -> Self {
+        Self::Parse {
+            path: path.to_owned(),
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for PlyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io { path, source } => {
+                write!(f, "failed to read `{}`: {}", path.display(), source)
+            }
+            Self::Parse { path, message } => write!(f, "{}: {}", path.display(), message),
+        }
+    }
+}
+
+impl std::error::Error for PlyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Parse { .. } => None,
+        }
+    }
+}
+
+/// Loads a PLY file as a single mesh with the given material.
+///
+/// Both the ASCII and the binary encodings are supported. Vertices need `x`, `y` and `z`, and may
+/// also have normals (`nx`, `ny`, `nz`), texture coordinates (`u` and `v`, or `s` and `t`) and
+/// colors (`red`, `green` and `blue`). Integer colors are read as sRGB, and floating point ones as
+/// linear. Polygons with more than three vertices are triangulated as fans, and elements other
+/// than vertices and faces are skipped.
+pub fn load_ply(path: impl AsRef<Path>, material: Material) -> Result<Mesh, PlyError> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|source| PlyError::Io {
+        path: path.to_owned(),
+        source,
+    })?;
+
+    parse_ply(&bytes, material).map_err(|message| PlyError::parse(path, message))
+}
+
+fn parse_ply(bytes: &[u8], material: Material) -> Result<Mesh, String> {
+    let (header, body) = parse_header(bytes)?;
+
+    let mut reader: Box<dyn ValueReader> = match header.format {
+        Format::Ascii => {
+            let body = std::str::from_utf8(body)
+                .map_err(|_| "the body of an ASCII file is not valid text".to_owned())?;
+            Box::new(AsciiReader {
+                tokens: body.split_ascii_whitespace(),
+                length: body.len(),
+            })
+        }
+        Format::BinaryLittleEndian => Box::new(BinaryReader {
+            bytes: body,
+            big_endian: false,
+        }),
+        Format::BinaryBigEndian => Box::new(BinaryReader {
+            bytes: body,
+            big_endian: true,
+        }),
+    };
+
+    let mut vertices = None;
+    let mut triangles = Vec::new();
+
+    for element in &header.elements {
+        match element.name.as_str() {
+            "vertex" => vertices = Some(read_vertices(element, reader.as_mut())?),
+            "face" => triangles = read_faces(element, reader.as_mut())?,
+            _ => skip_element(element, reader.as_mut())?,
+        }
+    }
+
+    let Some(vertices) = vertices else {
+        return Err("the file has no `vertex` element".to_owned());
+    };
+
+    let vertex_count = vertices.positions.len();
+
+    if let Some(&index) = triangles
+        .iter()
+        .flatten()
+        .find(|&&index| index as usize >= vertex_count)
+    {
+        return Err(format!(
+            "a face references vertex {index}, but there are only {vertex_count}"
+        ));
+    }
+
+    let mut mesh = Mesh::new(vertices.positions, vertices.normals, triangles, material);
+
+    if let Some(uvs) = vertices.uvs {
+        mesh = mesh.with_uvs(uvs);
+    }
+
+    if let Some(colors) = vertices.colors {
+        mesh = mesh.with_colors(colors);
+    }
+
+    Ok(mesh)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ScalarType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Result<Self, String> {
+        Ok(match name {
+            "char" | "int8" => Self::Int8,
+            "uchar" | "uint8" => Self::UInt8,
+            "short" | "int16" => Self::Int16,
+            "ushort" | "uint16" => Self::UInt16,
+            "int" | "int32" => Self::Int32,
+            "uint" | "uint32" => Self::UInt32,
+            "float" | "float32" => Self::Float32,
+            "double" | "float64" => Self::Float64,
+            _ => return Err(format!("unknown property type `{name}`")),
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::Int8 | Self::UInt8 => 1,
+            Self::Int16 | Self::UInt16 => 2,
+            Self::Int32 | Self::UInt32 | Self::Float32 => 4,
+            Self::Float64 => 8,
+        }
+    }
+
+    /// Returns the largest value of an integer type, which colors of that type are divided by.
+    fn max_value(self) -> Option<f64> {
+        match self {
+            Self::Int8 => Some(i8::MAX as f64),
+            Self::UInt8 => Some(u8::MAX as f64),
+            Self::Int16 => Some(i16::MAX as f64),
+            Self::UInt16 => Some(u16::MAX as f64),
+            Self::Int32 => Some(i32::MAX as f64),
+            Self::UInt32 => Some(u32::MAX as f64),
+            Self::Float32 | Self::Float64 => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum PropertyType {
+    Scalar(ScalarType),
+    List { count: ScalarType, item: ScalarType },
+}
+
+#[derive(Debug, Clone)]
+struct Property {
+    name: String,
+    ty: PropertyType,
+}
+
+#[derive(Debug, Clone)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    fn scalar(&self, name: &str) -> Option<(usize, ScalarType)> {
+        self.properties
+            .iter()
+            .enumerate()
+            .find_map(|(index, property)| match property.ty {
+                PropertyType::Scalar(ty) if property.name == name => Some((index, ty)),
+                _ => None,
+            })
+    }
+}
+
+#[derive(Debug)]
+struct Header {
+    format: Format,
+    elements: Vec<Element>,
+}
+
+/// Splits the file into its parsed header and the bytes of its body.
+fn parse_header(bytes: &[u8]) -> Result<(Header, &[u8]), String> {
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut offset = 0;
+    let mut line_number = 0;
+
+    loop {
+        let Some(length) = bytes[offset..].iter().position(|&byte| byte == b'\n') else {
+            return Err("the header does not end with `end_header`".to_owned());
+        };
+        let line = std::str::from_utf8(&bytes[offset..offset + length])
+            .map_err(|_| "the header is not valid text".to_owned())?
+            .trim();
+        offset += length + 1;
+        line_number += 1;
+
+        let error = |message: String| format!("line {line_number}: {message}");
+        let mut tokens = line.split_ascii_whitespace();
+
+        if line_number == 1 {
+            if line != "ply" {
+                return Err("not a PLY file".to_owned());
+            }
+            continue;
+        }
+
+        match tokens.next() {
+            None | Some("comment" | "obj_info") => {}
+            Some("format") => {
+                format = Some(match (tokens.next(), tokens.next()) {
+                    (Some("ascii"), Some("1.0")) => Format::Ascii,
+                    (Some("binary_little_endian"), Some("1.0")) => Format::BinaryLittleEndian,
+                    (Some("binary_big_endian"), Some("1.0")) => Format::BinaryBigEndian,
+                    _ => return Err(error(format!("unsupported format `{line}`"))),
+                });
+            }
+            Some("element") => {
+                let (Some(name), Some(count)) = (tokens.next(), tokens.next()) else {
+                    return Err(error("expected an element name and count".to_owned()));
+                };
+                let count = count
+                    .parse()
+                    .map_err(|_| error(format!("invalid element count `{count}`")))?;
+
+                elements.push(Element {
+                    name: name.to_owned(),
+                    count,
+                    properties: Vec::new(),
+                });
+            }
+            Some("property") => {
+                let Some(element) = elements.last_mut() else {
+                    return Err(error("property outside of an element".to_owned()));
+                };
+                let tokens: Vec<_> = tokens.collect();
+                let property = match tokens[..] {
+                    ["list", count, item, name] => Property {
+                        name: name.to_owned(),
+                        ty: PropertyType::List {
+                            count: ScalarType::parse(count).map_err(error)?,
+                            item: ScalarType::parse(item).map_err(error)?,
+                        },
+                    },
+                    [ty, name] => Property {
+                        name: name.to_owned(),
+                        ty: PropertyType::Scalar(ScalarType::parse(ty).map_err(error)?),
+                    },
+                    _ => return Err(error("expected a property type and name".to_owned())),
+                };
+
+                element.properties.push(property);
+            }
+            Some("end_header") => break,
+            Some(keyword) => return Err(error(format!("unknown keyword `{keyword}`"))),
+        }
+    }
+
+    let Some(format) = format else {
+        return Err("the header does not declare a format".to_owned());
+    };
+
+    Ok((Header { format, elements }, &bytes[offset..]))
+}
+
+/// Reads the values of the body one at a time, whatever their encoding.
+trait ValueReader {
+    fn read(&mut self, ty: ScalarType) -> Result<f64, String>;
+
+    /// Returns an upper bound of the number of bytes left to read.
+    fn remaining_bytes(&self) -> usize;
+
+    /// Returns how many rows of an element to allocate room for.
+    ///
+    /// Every value takes up at least a byte, so this is the row count of the header, but no more
+    /// than the rest of the file could possibly hold.
+    fn capacity(&self, element: &Element) -> usize {
+        let row_size = element.properties.len().max(1);
+        element.count.min(self.remaining_bytes() / row_size)
+    }
+}
+
+struct AsciiReader<'a> {
+    tokens: std::str::SplitAsciiWhitespace<'a>,
+    /// the length of the whole body, as the tokens do not tell how much of it is left
+    length: usize,
+}
+
+impl ValueReader for AsciiReader<'_> {
+    fn read(&mut self, _ty: ScalarType) -> Result<f64, String> {
+        let token = self
+            .tokens
+            .next()
+            .ok_or_else(|| "unexpected end of file".to_owned())?;
+
+        token
+            .parse()
+            .map_err(|_| format!("invalid number `{token}`"))
+    }
+
+    fn remaining_bytes(&self) -> usize {
+        self.length
+    }
+}
+
+struct BinaryReader<'a> {
+    bytes: &'a [u8],
+    big_endian: bool,
+}
+
+impl ValueReader for BinaryReader<'_> {
+    fn read(&mut self, ty: ScalarType) -> Result<f64, String> {
+        let size = ty.size();
+
+        if self.bytes.len() < size {
+            return Err("unexpected end of file".to_owned());
+        }
+
+        let (value, rest) = self.bytes.split_at(size);
+        self.bytes = rest;
+
+        let mut buffer = [0; 8];
+        buffer[..size].copy_from_slice(value);
+
+        if self.big_endian {
+            buffer[..size].reverse();
+        }
+
+        Ok(match ty {
+            ScalarType::Int8 => i8::from_le_bytes([buffer[0]]) as f64,
+            ScalarType::UInt8 => buffer[0] as f64,
+            ScalarType::Int16 => i16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+            ScalarType::UInt16 => u16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+            ScalarType::Int32 => i32::from_le_bytes(buffer[..4].try_into().unwrap()) as f64,
+            ScalarType::UInt32 => u32::from_le_bytes(buffer[..4].try_into().unwrap()) as f64,
+            ScalarType::Float32 => f32::from_le_bytes(buffer[..4].try_into().unwrap()) as f64,
+            ScalarType::Float64 => f64::from_le_bytes(buffer),
+        })
+    }
+
+    fn remaining_bytes(&self) -> usize {
+        self.bytes.len()
+    }
+}
+
+/// Reads one row of an element, with the values of list properties appended to `lists`.
+fn read_row(
+    element: &Element,
+    reader: &mut dyn ValueReader,
+    row: &mut [f64],
+    lists: &mut [Vec<f64>],
+) -> Result<(), String> {
+    for (index, property) in element.properties.iter().enumerate() {
+        match property.ty {
+            PropertyType::Scalar(ty) => row[index] = reader.read(ty)?,
+            PropertyType::List { count, item } => {
+                let count = reader.read(count)?;
+
+                if count < 0.0 {
+                    return Err(format!("negative length of `{}`", property.name));
+                }
+
+                lists[index].clear();
+
+                for _ in 0..count as usize {
+                    lists[index].push(reader.read(item)?);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn skip_element(element: &Element, reader: &mut dyn ValueReader) -> Result<(), String> {
+    if element.properties.is_empty() {
+        // there is nothing to read, however many rows there are
+        return Ok(());
+    }
+
+    let mut row = vec![0.0; element.properties.len()];
+    let mut lists = vec![Vec::new(); element.properties.len()];
+
+    for _ in 0..element.count {
+        read_row(element, reader, &mut row, &mut lists)?;
+    }
+
+    Ok(())
+}
+
+struct Vertices {
+    positions: Vec<Vec3A>,
+    normals: Option<Vec<Vec3A>>,
+    uvs: Option<Vec<Vec2>>,
+    colors: Option<Vec<Vec3A>>,
+}
+
+fn read_vertices(element: &Element, reader: &mut dyn ValueReader) -> Result<Vertices, String> {
+    let scalar = |name: &str| element.scalar(name).map(|(index, _)| index);
+    let all = |indices: &[Option<usize>]| indices.iter().copied().collect::<Option<Vec<_>>>();
+
+    let Some(position) = all(&[scalar("x"), scalar("y"), scalar("z")]) else {
+        return Err("vertices need `x`, `y` and `z` properties".to_owned());
+    };
+    let normal = all(&[scalar("nx"), scalar("ny"), scalar("nz")]);
+    let uv = [
+        ("u", "v"),
+        ("s", "t"),
+        ("texture_u", "texture_v"),
+        ("texture_s", "texture_t"),
+    ]
+    .into_iter()
+    .find_map(|(u, v)| all(&[scalar(u), scalar(v)]));
+    let color = all(&[scalar("red"), scalar("green"), scalar("blue")]).map(|indices| {
+        let (_, ty) = element.scalar("red").unwrap();
+        (indices, ty.max_value())
+    });
+
+    let capacity = reader.capacity(element);
+    let mut vertices = Vertices {
+        positions: Vec::with_capacity(capacity),
+        normals: normal.as_ref().map(|_| Vec::with_capacity(capacity)),
+        uvs: uv.as_ref().map(|_| Vec::with_capacity(capacity)),
+        colors: color.as_ref().map(|_| Vec::with_capacity(capacity)),
+    };
+
+    let mut row = vec![0.0; element.properties.len()];
+    let mut lists = vec![Vec::new(); element.properties.len()];
+    let vec3 = |row: &[f64], indices: &[usize]| {
+        Vec3A::new(
+            row[indices[0]] as f32,
+            row[indices[1]] as f32,
+            row[indices[2]] as f32,
+        )
+    };
+
+    for _ in 0..element.count {
+        read_row(element, reader, &mut row, &mut lists)?;
+
+        vertices.positions.push(vec3(&row, &position));
+
+        if let (Some(normals), Some(indices)) = (&mut vertices.normals, &normal) {
+            normals.push(vec3(&row, indices).normalize_or_zero());
+        }
+
+        if let (Some(uvs), Some(indices)) = (&mut vertices.uvs, &uv) {
+            uvs.push(Vec2::new(row[indices[0]] as f32, row[indices[1]] as f32));
+        }
+
+        if let (Some(colors), Some((indices, max_value))) = (&mut vertices.colors, &color) {
+            let color = vec3(&row, indices);
+            colors.push(match max_value {
+                Some(max_value) => (color / *max_value as f32).map(srgb_to_linear),
+                None => color,
+            });
+        }
+    }
+
+    Ok(vertices)
+}
+
+fn read_faces(element: &Element, reader: &mut dyn ValueReader) -> Result<Vec<[u32; 3]>, String> {
+    let Some(indices) = element.properties.iter().position(|property| {
+        matches!(property.ty, PropertyType::List { .. })
+            && matches!(property.name.as_str(), "vertex_indices" | "vertex_index")
+    }) else {
+        return Err("faces need a `vertex_indices` list property".to_owned());
+    };
+
+    let mut triangles = Vec::with_capacity(reader.capacity(element));
+    let mut row = vec![0.0; element.properties.len()];
+    let mut lists = vec![Vec::new(); element.properties.len()];
+
+    for face in 0..element.count {
+        read_row(element, reader, &mut row, &mut lists)?;
+
+        let vertices = &lists[indices];
+
+        if vertices.len() < 3 {
+            return Err(format!(
+                "face {face} has {} vertices; at least 3 are required",
+                vertices.len()
+            ));
+        }
+
+        if vertices.iter().any(|&index| index < 0.0) {
+            return Err(format!("face {face} references a negative vertex index"));
+        }
+
+        for i in 1..vertices.len() - 1 {
+            triangles.push([vertices[0], vertices[i], vertices[i + 1]].map(|index| index as u32));
+        }
+    }
+
+    Ok(triangles)
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DEFAULT_MATERIAL;
+
+    const HEADER: &str = "\
+element vertex 4
+property float x
+property float y
+property float z
+property float nx
+property float ny
+property float nz
+property float s
+property float t
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+";
+
+    /// A unit square in the XY plane, facing `+Z`, with a red, a green, a blue and a gray corner.
+    const POSITIONS: [[f32; 3]; 4] = [
+        [0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [0.0, 1.0, 0.0],
+    ];
+    const COLORS: [[u8; 3]; 4] = [[255, 0, 0], [0, 255, 0], [0, 0, 255], [128, 128, 128]];
+
+    fn file(format: &str, header: &str, body: &[u8]) -> Vec<u8> {
+        let mut file = format!("ply\nformat {format} 1.0\ncomment a square\n{header}").into_bytes();
+        file.extend_from_slice(body);
+        file
+    }
+
+    fn binary_body(big_endian: bool) -> Vec<u8> {
+        let bytes = |value: [u8; 4]| {
+            if big_endian {
+                [value[3], value[2], value[1], value[0]]
+            } else {
+                value
+            }
+        };
+
+        let mut body = Vec::new();
+
+        for (&[x, y, z], color) in POSITIONS.iter().zip(COLORS) {
+            for value in [x, y, z, 0.0, 0.0, 2.0, x, y] {
+                body.extend(bytes(value.to_le_bytes()));
+            }
+            body.extend(color);
+        }
+
+        body.push(4);
+        for index in [0, 1, 2, 3] {
+            body.extend(bytes(i32::to_le_bytes(index)));
+        }
+        body
+    }
+
+    fn assert_is_square(mesh: &Mesh) {
+        let positions: Vec<_> = POSITIONS.into_iter().map(Vec3A::from_array).collect();
+        assert_eq!(mesh.positions(), positions);
+        assert_eq!(mesh.triangles(), [[0, 1, 2], [0, 2, 3]]);
+
+        // normals are normalized when they are read
+        assert_eq!(mesh.normals(), Some(&[Vec3A::Z; 4][..]));
+
+        let uvs: Vec<_> = POSITIONS
+            .into_iter()
+            .map(|[x, y, _]| Vec2::new(x, y))
+            .collect();
+        assert_eq!(mesh.uvs(), Some(&uvs[..]));
+
+        let colors = mesh.colors().unwrap();
+        assert_eq!(colors[..3], [Vec3A::X, Vec3A::Y, Vec3A::Z]);
+        // mid gray in sRGB is about a fifth of full intensity in linear space
+        assert!(colors[3].abs_diff_eq(Vec3A::splat(0.2158605), 1e-5));
+    }
+
+    #[test]
+    fn ascii_files_are_read() {
+        let body = "\
+0 0 0 0 0 2 0 0 255 0 0
+1 0 0 0 0 2 1 0 0 255 0
+1 1 0 0 0 2 1 1 0 0 255
+0 1 0 0 0 2 0 1 128 128 128
+4 0 1 2 3
+";
+        let mesh = parse_ply(&file("ascii", HEADER, body.as_bytes()), DEFAULT_MATERIAL).unwrap();
+
+        assert_is_square(&mesh);
+    }
+
+    #[test]
+    fn binary_files_are_read_in_either_byte_order() {
+        for (format, big_endian) in [("binary_little_endian", false), ("binary_big_endian", true)] {
+            let file = file(format, HEADER, &binary_body(big_endian));
+            let mesh = parse_ply(&file, DEFAULT_MATERIAL)
+                .unwrap_or_else(|message| panic!("{format}: {message}"));
+
+            assert_is_square(&mesh);
+        }
+    }
+
+    #[test]
+    fn oversized_element_counts_fail_without_allocating_for_them() {
+        let header = HEADER.replacen("vertex 4", "vertex 4000000000", 1);
+        let file = file("binary_little_endian", &header, &binary_body(false));
+
+        // room is only made for as many vertices as the body could hold, and reading stops at its
+        // end, long before the count of the header
+        let error = parse_ply(&file, DEFAULT_MATERIAL).unwrap_err();
+        assert_eq!(error, "unexpected end of file");
+    }
+}
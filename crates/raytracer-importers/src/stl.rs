@@ -0,0 +1,284 @@
+use glam::Vec3A;
+use raytracer_core::material::Material;
+use raytracer_primitives::Mesh;
+use std::{
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug)]
+pub enum StlError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        message: String,
+    },
+}
+
+impl StlError {
+    fn parse(path: &Path, message: impl Into<String>) -> Self {
+        Self::Parse {
+            path: path.to_owned(),
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for StlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io { path, source } => {
+                write!(f, "failed to read `{}`: {}", path.display(), source)
+            }
+            Self::Parse { path, message } => write!(f, "{}: {}", path.display(), message),
+        }
+    }
+}
+
+impl std::error::Error for StlError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Parse { .. } => None,
+        }
+    }
+}
+
+/// The size of the header of a binary STL file, followed by the number of triangles.
+const HEADER_SIZE: usize = 80;
+/// The size of a triangle of a binary STL file: a normal, three vertices and an attribute.
+const TRIANGLE_SIZE: usize = 50;
+
+/// Loads an STL file as a single mesh with the given material.
+///
+/// Binary files are expected, but ASCII ones, which are text starting with `solid` and do not
+/// have the size a binary file with the same triangle count would have, are read as well.
+/// STL triangles do not share vertices and have no vertex normals, so the mesh is flat shaded.
+/// Triangles whose facet normal disagrees with their winding are flipped to match the normal.
+pub fn load_stl(path: impl AsRef<Path>, material: Material) -> Result<Mesh, StlError> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|source| StlError::Io {
+        path: path.to_owned(),
+        source,
+    })?;
+
+    parse_stl(&bytes, material).map_err(|message| StlError::parse(path, message))
+}
+
+fn parse_stl(bytes: &[u8], material: Material) -> Result<Mesh, String> {
+    let facets = match std::str::from_utf8(bytes) {
+        Ok(source) if is_ascii(bytes) => parse_ascii(source),
+        _ => parse_binary(bytes),
+    }?;
+
+    let mut positions = Vec::with_capacity(facets.len() * 3);
+    let mut triangles = Vec::with_capacity(facets.len());
+
+    for (normal, [p0, p1, p2]) in facets {
+        let first = positions.len() as u32;
+
+        if (p1 - p0).cross(p2 - p0).dot(normal) < 0.0 {
+            positions.extend([p0, p2, p1]);
+        } else {
+            positions.extend([p0, p1, p2]);
+        }
+
+        triangles.push([first, first + 1, first + 2]);
+    }
+
+    Ok(Mesh::new(positions, None, triangles, material))
+}
+
+/// A facet normal, which may be zero, and the vertices of a triangle.
+type Facet = (Vec3A, [Vec3A; 3]);
+
+fn is_ascii(bytes: &[u8]) -> bool {
+    if !bytes.starts_with(b"solid") {
+        return false;
+    }
+
+    // some binary exporters start their header with `solid` too
+    match bytes.get(HEADER_SIZE..HEADER_SIZE + 4) {
+        Some(count) => {
+            let count = u32::from_le_bytes(count.try_into().unwrap()) as usize;
+            bytes.len() != HEADER_SIZE + 4 + count * TRIANGLE_SIZE
+        }
+        None => true,
+    }
+}
+
+fn parse_binary(bytes: &[u8]) -> Result<Vec<Facet>, String> {
+    let Some(count) = bytes.get(HEADER_SIZE..HEADER_SIZE + 4) else {
+        return Err("the file is too short for a binary STL header".to_owned());
+    };
+    let count = u32::from_le_bytes(count.try_into().unwrap()) as usize;
+    let data = &bytes[HEADER_SIZE + 4..];
+
+    if data.len() < count * TRIANGLE_SIZE {
+        return Err(format!(
+            "the header declares {count} triangles, but the file only holds {}",
+            data.len() / TRIANGLE_SIZE
+        ));
+    }
+
+    let vec3 = |bytes: &[u8]| {
+        let component =
+            |index: usize| f32::from_le_bytes(bytes[index * 4..index * 4 + 4].try_into().unwrap());
+        Vec3A::new(component(0), component(1), component(2))
+    };
+
+    Ok(data
+        .chunks_exact(TRIANGLE_SIZE)
+        .take(count)
+        .map(|triangle| {
+            (
+                vec3(&triangle[0..12]),
+                [
+                    vec3(&triangle[12..24]),
+                    vec3(&triangle[24..36]),
+                    vec3(&triangle[36..48]),
+                ],
+            )
+        })
+        .collect())
+}
+
+fn parse_ascii(source: &str) -> Result<Vec<Facet>, String> {
+    let mut facets = Vec::new();
+    let mut normal = Vec3A::ZERO;
+    let mut vertices = Vec::with_capacity(3);
+
+    for (line_index, line) in source.lines().enumerate() {
+        let error = |message: String| format!("line {}: {}", line_index + 1, message);
+        let mut tokens = line.split_ascii_whitespace();
+
+        match tokens.next() {
+            Some("facet") => {
+                if tokens.next() != Some("normal") {
+                    return Err(error("expected `facet normal`".to_owned()));
+                }
+
+                normal = parse_vec3(&mut tokens).map_err(error)?;
+                vertices.clear();
+            }
+            Some("vertex") => vertices.push(parse_vec3(&mut tokens).map_err(error)?),
+            Some("endfacet") => {
+                let Ok(triangle) = <[Vec3A; 3]>::try_from(vertices.as_slice()) else {
+                    return Err(error(format!(
+                        "facet has {} vertices; exactly 3 are required",
+                        vertices.len()
+                    )));
+                };
+
+                facets.push((normal, triangle));
+            }
+            // `solid`, `outer loop`, `endloop` and `endsolid` only delimit the data
+            _ => {}
+        }
+    }
+
+    Ok(facets)
+}
+
+fn parse_vec3<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Result<Vec3A, String> {
+    let mut components = [0.0; 3];
+
+    for component in &mut components {
+        let token = tokens
+            .next()
+            .ok_or_else(|| "expected 3 components".to_owned())?;
+        *component = token
+            .parse()
+            .map_err(|_| format!("invalid number `{token}`"))?;
+    }
+
+    Ok(Vec3A::from_array(components))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DEFAULT_MATERIAL;
+
+    /// A binary file holding one triangle whose facet normal disagrees with its winding.
+    ///
+    /// Every coordinate is 0, 0.5 or 2, whose bytes are all ASCII, so the whole file is valid
+    /// UTF-8 and only its size tells it apart from a text file when the header starts with `solid`.
+    fn binary_file(header: &[u8]) -> Vec<u8> {
+        let mut file = vec![b' '; HEADER_SIZE];
+        file[..header.len()].copy_from_slice(header);
+        file.extend(1u32.to_le_bytes());
+
+        let values = [
+            [0.0, 0.0, 0.5],
+            [0.0, 0.0, 0.0],
+            [0.0, 0.5, 0.0],
+            [2.0, 0.0, 0.0],
+        ];
+        for value in values.iter().flatten() {
+            file.extend(f32::to_le_bytes(*value));
+        }
+        file.extend([0, 0]);
+        file
+    }
+
+    fn assert_is_flipped_triangle(mesh: &Mesh) {
+        assert_eq!(
+            mesh.positions(),
+            [
+                Vec3A::ZERO,
+                Vec3A::new(2.0, 0.0, 0.0),
+                Vec3A::new(0.0, 0.5, 0.0)
+            ]
+        );
+        assert_eq!(mesh.triangles(), [[0, 1, 2]]);
+        assert!(mesh.triangle_normal(0).abs_diff_eq(Vec3A::Z, 1e-6));
+    }
+
+    #[test]
+    fn binary_files_are_read() {
+        let file = binary_file(b"exported by some tool");
+        assert!(std::str::from_utf8(&file).is_ok());
+
+        assert_is_flipped_triangle(&parse_stl(&file, DEFAULT_MATERIAL).unwrap());
+    }
+
+    #[test]
+    fn binary_files_with_a_solid_header_are_not_read_as_text() {
+        let file = binary_file(b"solid exported by some tool");
+        assert!(std::str::from_utf8(&file).is_ok());
+
+        assert_is_flipped_triangle(&parse_stl(&file, DEFAULT_MATERIAL).unwrap());
+    }
+
+    #[test]
+    fn ascii_files_are_read() {
+        let file = "\
+solid triangle
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 0 0.5 0
+      vertex 2 0 0
+    endloop
+  endfacet
+endsolid triangle
+";
+
+        assert_is_flipped_triangle(&parse_stl(file.as_bytes(), DEFAULT_MATERIAL).unwrap());
+    }
+
+    #[test]
+    fn ascii_facets_need_three_vertices() {
+        let file = "solid\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nendloop\nendfacet\n";
+
+        assert_eq!(
+            parse_stl(file.as_bytes(), DEFAULT_MATERIAL).unwrap_err(),
+            "line 7: facet has 2 vertices; exactly 3 are required"
+        );
+    }
+}
@@ -1,4 +1,5 @@
-use glam::{Mat3A, Quat, Vec3A};
+use crate::{SurfaceFrame, SurfaceParameterization};
+use glam::{Mat3A, Quat, Vec2, Vec3A};
 use raytracer_core::{
     aabb::Aabb,
     hit_record::HitRecord,
@@ -121,7 +122,9 @@ impl Object for Box {
 
         let mut t_near = f32::NEG_INFINITY;
         let mut t_far = f32::INFINITY;
-        let mut hit_normal_local = Vec3A::ZERO;
+        // outward normals of the faces where the ray enters and leaves the box
+        let mut near_normal_local = Vec3A::ZERO;
+        let mut far_normal_local = Vec3A::ZERO;
 
         for i in 0..3 {
             let inv_d = 1.0 / local_ray.direction[i];
@@ -138,20 +141,24 @@ impl Object for Box {
 
             if t_near < t1 {
                 t_near = t1;
-                hit_normal_local = normal;
+                near_normal_local = normal;
             }
 
-            t_far = t_far.min(t2);
+            if t2 < t_far {
+                t_far = t2;
+                far_normal_local = -normal;
+            }
 
             if t_far <= t_near {
                 return None;
             }
         }
 
-        let t_hit = if t_min <= t_near && t_near <= t_max {
-            t_near
+        // rays starting inside the box leave it through the far face
+        let (t_hit, hit_normal_local) = if t_min <= t_near && t_near <= t_max {
+            (t_near, near_normal_local)
         } else if t_min <= t_far && t_far <= t_max {
-            t_far
+            (t_far, far_normal_local)
         } else {
             return None;
         };
@@ -170,3 +177,87 @@ impl Object for Box {
         ))
     }
 }
+
+impl SurfaceParameterization for Box {
+    /// Every face is mapped to the whole `[0, 1]²` UV square, upright when seen from outside the box;
+    /// the top and bottom faces have `v` pointing towards `-Z` and `+Z` respectively.
+    fn surface_frame(&self, point: Vec3A, _normal: Vec3A) -> SurfaceFrame {
+        let half_size = self.size * 0.5;
+        let local = self.rotation.inverse().mul_vec3a(point - self.center);
+
+        // the face is the one the point is relatively closest to
+        let relative = (local / half_size.max(Vec3A::splat(1e-8))).abs();
+        let axis = relative.max_position();
+        let sign = local[axis].signum();
+
+        let (u_axis, v_axis) = match (axis, sign > 0.0) {
+            (0, true) => (Vec3A::NEG_Z, Vec3A::Y),
+            (0, false) => (Vec3A::Z, Vec3A::Y),
+            (1, true) => (Vec3A::X, Vec3A::NEG_Z),
+            (1, false) => (Vec3A::X, Vec3A::Z),
+            (_, true) => (Vec3A::X, Vec3A::Y),
+            (_, false) => (Vec3A::NEG_X, Vec3A::Y),
+        };
+        let mut local_normal = Vec3A::ZERO;
+        local_normal[axis] = sign;
+
+        let u_size = u_axis.abs().dot(self.size);
+        let v_size = v_axis.abs().dot(self.size);
+        let normal = self.rotation.mul_vec3a(local_normal);
+
+        SurfaceFrame {
+            uv: Vec2::new(
+                local.dot(u_axis) / u_size.max(1e-8) + 0.5,
+                local.dot(v_axis) / v_size.max(1e-8) + 0.5,
+            ),
+            dpdu: self.rotation.mul_vec3a(u_axis) * u_size,
+            dpdv: self.rotation.mul_vec3a(v_axis) * v_size,
+            geometric_normal: normal,
+            shading_normal: normal,
+            color: None,
+        }
+        .with_valid_tangents()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box() -> Box {
+        Box {
+            center: Vec3A::ZERO,
+            size: Vec3A::ONE,
+            rotation: Quat::IDENTITY,
+            material: crate::test_material(),
+        }
+    }
+
+    #[test]
+    fn rays_from_outside_hit_the_front_face() {
+        let cube = unit_box();
+        let ray = Ray::new(Vec3A::new(-2.0, 0.05, 0.02), Vec3A::X);
+        let hit = cube.intersect(&ray, 1e-4, f32::INFINITY, 0).unwrap();
+
+        assert!((hit.point.x + 0.5).abs() < 1e-5);
+        assert_eq!(hit.normal, Vec3A::NEG_X);
+        assert!(hit.front_face);
+    }
+
+    #[test]
+    fn rays_from_inside_hit_the_back_of_the_far_face() {
+        let cube = Box {
+            rotation: Quat::from_rotation_y(0.3),
+            ..unit_box()
+        };
+        let origin = cube.rotation.mul_vec3a(Vec3A::new(0.1, 0.05, 0.02));
+        let direction = cube.rotation.mul_vec3a(Vec3A::X);
+        let hit = cube
+            .intersect(&Ray::new(origin, direction), 1e-4, f32::INFINITY, 0)
+            .unwrap();
+
+        assert!((hit.t - 0.4).abs() < 1e-5);
+        assert!(hit.normal.abs_diff_eq(-direction, 1e-5));
+        assert!(!hit.front_face);
+    }
+}
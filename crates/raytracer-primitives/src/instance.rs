@@ -0,0 +1,250 @@
+use crate::{SurfaceFrame, SurfaceParameterization};
+use glam::{Affine3A, Mat3A, Vec3, Vec3A};
+use raytracer_core::{
+    aabb::Aabb,
+    hit_record::HitRecord,
+    material::Material,
+    object::{Object, PointOnObject},
+    ray::Ray,
+};
+use std::sync::Arc;
+
+/// A placement of shared geometry in the scene.
+///
+/// Any number of instances can reference the same geometry, so memory scales with the number of
+/// unique objects rather than the number of placements. Together with the scene-level BVH built
+/// over instances and the BVH a [`Mesh`](crate::Mesh) builds over its triangles, this forms a
+/// two-level acceleration structure.
+#[derive(Clone)]
+pub struct Instance {
+    geometry: Arc<dyn SurfaceParameterization>,
+    object_to_world: Affine3A,
+    world_to_object: Affine3A,
+    /// transforms normals from object space into world space
+    normal_matrix: Mat3A,
+    /// ratio between world space and object space areas
+    area_scale: f32,
+}
+
+impl Instance {
+    /// Emissive geometry is sampled uniformly by area, which a non-uniform scale would distort, so
+    /// it has to be placed with a transform for which [`Instance::scales_uniformly`] holds.
+    /// Other geometry can be scaled freely, but then only has an approximate [`Object::area`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `object_to_world` is not invertible, or scales emissive geometry non-uniformly.
+    pub fn new(geometry: Arc<dyn SurfaceParameterization>, object_to_world: Affine3A) -> Self {
+        let determinant = object_to_world.matrix3.determinant();
+        assert!(determinant != 0.0, "instance transform must be invertible");
+        assert!(
+            !geometry.material().is_emissive || Self::scales_uniformly(object_to_world),
+            "emissive instances must be scaled uniformly"
+        );
+
+        let world_to_object = object_to_world.inverse();
+
+        Self {
+            geometry,
+            object_to_world,
+            world_to_object,
+            normal_matrix: world_to_object.matrix3.transpose(),
+            area_scale: determinant.abs().powf(2.0 / 3.0),
+        }
+    }
+
+    /// Returns whether a transform scales every area by the same factor, which is the case for
+    /// combinations of rotations, reflections, translations and uniform scales.
+    pub fn scales_uniformly(transform: Affine3A) -> bool {
+        let matrix = transform.matrix3;
+        let scale_squared = matrix.determinant().abs().powf(2.0 / 3.0);
+
+        // the columns of such a transform are orthogonal and all have the same length
+        (matrix.transpose() * matrix).abs_diff_eq(
+            Mat3A::from_diagonal(Vec3::splat(scale_squared)),
+            scale_squared * 1e-4,
+        )
+    }
+
+    pub fn geometry(&self) -> &Arc<dyn SurfaceParameterization> {
+        &self.geometry
+    }
+
+    pub fn transform(&self) -> Affine3A {
+        self.object_to_world
+    }
+}
+
+impl Object for Instance {
+    fn material(&self) -> &Material {
+        self.geometry.material()
+    }
+
+    fn area(&self) -> f32 {
+        self.geometry.area() * self.area_scale
+    }
+
+    fn sample_point(&self) -> PointOnObject {
+        let local = self.geometry.sample_point();
+
+        PointOnObject {
+            point: self.object_to_world.transform_point3a(local.point),
+            normal: self.normal_matrix.mul_vec3a(local.normal).normalize(),
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let Aabb { min, max } = self.geometry.bounding_box();
+        let center = (min + max) * 0.5;
+        let half_extent = (max - min) * 0.5;
+
+        let matrix = self.object_to_world.matrix3;
+        let abs_matrix = Mat3A::from_cols(
+            matrix.x_axis.abs(),
+            matrix.y_axis.abs(),
+            matrix.z_axis.abs(),
+        );
+        let world_center = self.object_to_world.transform_point3a(center);
+        let world_half_extent = abs_matrix.mul_vec3a(half_extent);
+
+        Aabb {
+            min: world_center - world_half_extent,
+            max: world_center + world_half_extent,
+        }
+    }
+
+    fn intersect(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        object_index: usize,
+    ) -> Option<HitRecord<'_>> {
+        // the direction is deliberately left unnormalized, so that distances along the local ray
+        // are the same as distances along the world ray
+        let local_ray = Ray {
+            origin: self.world_to_object.transform_point3a(ray.origin),
+            direction: self.world_to_object.transform_vector3a(ray.direction),
+        };
+        let local_hit = self
+            .geometry
+            .intersect(&local_ray, t_min, t_max, object_index)?;
+
+        // normals transformed by the inverse transpose keep their orientation relative to the
+        // transformed ray, so the side that was hit stays the same
+        Some(HitRecord {
+            point: self.object_to_world.transform_point3a(local_hit.point),
+            normal: self.normal_matrix.mul_vec3a(local_hit.normal).normalize(),
+            t: local_hit.t,
+            front_face: local_hit.front_face,
+            object: self,
+            object_index,
+        })
+    }
+}
+
+impl SurfaceParameterization for Instance {
+    fn surface_frame(&self, point: Vec3A, normal: Vec3A) -> SurfaceFrame {
+        // normals are brought back into object space by the transpose of the normal matrix
+        let local_frame = self.geometry.surface_frame(
+            self.world_to_object.transform_point3a(point),
+            self.object_to_world
+                .matrix3
+                .transpose()
+                .mul_vec3a(normal)
+                .normalize_or_zero(),
+        );
+        let to_world_normal = |normal| self.normal_matrix.mul_vec3a(normal).normalize_or_zero();
+
+        SurfaceFrame {
+            uv: local_frame.uv,
+            dpdu: self.object_to_world.transform_vector3a(local_frame.dpdu),
+            dpdv: self.object_to_world.transform_vector3a(local_frame.dpdv),
+            geometric_normal: to_world_normal(local_frame.geometric_normal),
+            shading_normal: to_world_normal(local_frame.shading_normal),
+            color: local_frame.color,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Mesh;
+    use glam::Quat;
+
+    /// A flat shaded octahedron; vertex normals would be interpolated before the transform by the
+    /// instance and after it by the baked mesh, which differs slightly under a non-uniform scale.
+    fn octahedron() -> Mesh {
+        let positions = vec![
+            Vec3A::X,
+            Vec3A::NEG_X,
+            Vec3A::Y,
+            Vec3A::NEG_Y,
+            Vec3A::Z,
+            Vec3A::NEG_Z,
+        ];
+        let triangles = vec![
+            [0, 2, 4],
+            [2, 1, 4],
+            [1, 3, 4],
+            [3, 0, 4],
+            [2, 0, 5],
+            [1, 2, 5],
+            [3, 1, 5],
+            [0, 3, 5],
+        ];
+
+        Mesh::new(positions, None, triangles, crate::test_material())
+    }
+
+    fn assert_instance_matches_baked_mesh(transform: Affine3A) {
+        let mesh = Arc::new(octahedron());
+        let instance = Instance::new(mesh.clone(), transform);
+        let baked = mesh.transformed(transform);
+
+        for _ in 0..500 {
+            let origin = Vec3A::new(rand::random(), rand::random(), rand::random()) * 8.0 - 4.0;
+            let target = transform.transform_point3a(
+                Vec3A::new(rand::random(), rand::random(), rand::random()) - 0.5,
+            );
+            let ray = Ray::new(origin, (target - origin).normalize());
+
+            let expected = baked.intersect(&ray, 1e-4, f32::INFINITY, 0);
+            let hit = instance.intersect(&ray, 1e-4, f32::INFINITY, 0);
+
+            match (hit, expected) {
+                (Some(hit), Some(expected)) => {
+                    assert!((hit.t - expected.t).abs() < 1e-4, "{ray:?}");
+                    assert!(hit.point.abs_diff_eq(expected.point, 1e-4), "{ray:?}");
+                    assert!(hit.normal.abs_diff_eq(expected.normal, 1e-4), "{ray:?}");
+                    assert_eq!(hit.front_face, expected.front_face, "{ray:?}");
+                }
+                (None, None) => {}
+                (hit, expected) => panic!(
+                    "{ray:?} hit the instance at {:?} but the baked mesh at {:?}",
+                    hit.map(|hit| hit.t),
+                    expected.map(|hit| hit.t)
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn instances_scaled_non_uniformly_match_their_baked_mesh() {
+        assert_instance_matches_baked_mesh(Affine3A::from_scale_rotation_translation(
+            Vec3::new(2.0, 0.5, 1.0),
+            Quat::from_euler(glam::EulerRot::YXZ, 0.4, -0.7, 0.2),
+            Vec3::new(0.3, -0.2, 0.5),
+        ));
+    }
+
+    #[test]
+    fn mirrored_instances_match_their_baked_mesh() {
+        assert_instance_matches_baked_mesh(Affine3A::from_scale_rotation_translation(
+            Vec3::new(-1.5, 0.7, 1.0),
+            Quat::from_rotation_z(0.9),
+            Vec3::ZERO,
+        ));
+    }
+}
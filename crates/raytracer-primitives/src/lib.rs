@@ -1,7 +1,39 @@
 mod r#box;
+mod instance;
+mod mesh;
 mod plain;
+mod solid_angle;
 mod sphere;
+mod surface;
+mod triangle;
 
 pub use r#box::*;
+pub use instance::*;
+pub use mesh::*;
 pub use plain::*;
+pub use solid_angle::*;
 pub use sphere::*;
+pub use surface::*;
+pub use triangle::*;
+
+/// A gray material for tests, which only look at geometry.
+#[cfg(test)]
+pub(crate) fn test_material() -> raytracer_core::material::Material {
+    use glam::Vec3A;
+
+    raytracer_core::material::Material {
+        is_emissive: false,
+        emission: Vec3A::ZERO,
+        albedo: Vec3A::splat(0.8),
+        subsurface: 0.0,
+        metallic: 0.0,
+        specular: 0.5,
+        specular_tint: Vec3A::ZERO,
+        roughness: 0.5,
+        anisotropic: 0.0,
+        sheen: 0.0,
+        sheen_tint: Vec3A::ZERO,
+        clearcoat: 0.0,
+        clearcoat_gloss: 0.0,
+    }
+}
@@ -0,0 +1,516 @@
+use crate::{
+    SurfaceFrame, SurfaceParameterization,
+    triangle::{
+        barycentric_coordinates, intersect_triangle, sample_uniform_barycentric, shading_normal,
+        triangle_area, triangle_bounding_box, triangle_frame,
+    },
+};
+use glam::{Affine3A, Vec2, Vec3A};
+use raytracer_bvh::Bvh;
+use raytracer_core::{
+    aabb::Aabb,
+    hit_record::HitRecord,
+    material::Material,
+    object::{Object, PointOnObject},
+    ray::Ray,
+};
+use std::{cell::RefCell, collections::HashMap};
+
+thread_local! {
+    /// The point and triangle of the closest hit each mesh last reported on this thread, keyed by
+    /// the address of the mesh, so that shading the hit does not have to search for its triangle.
+    static LAST_HITS: RefCell<HashMap<usize, (Vec3A, usize)>> = RefCell::new(HashMap::new());
+}
+
+/// An indexed triangle mesh sharing a single material.
+///
+/// Triangles are wound counter-clockwise; the geometric normal of a face points towards the side
+/// from which its vertices appear in counter-clockwise order.
+#[derive(Debug, Clone)]
+pub struct Mesh {
+    positions: Vec<Vec3A>,
+    normals: Option<Vec<Vec3A>>,
+    uvs: Option<Vec<Vec2>>,
+    /// linear RGB, one per position
+    colors: Option<Vec<Vec3A>>,
+    triangles: Vec<[u32; 3]>,
+    material: Material,
+    area: f32,
+    /// cumulative triangle areas, used to sample a triangle proportional to its area
+    area_cdf: Vec<f32>,
+    bounding_box: Aabb,
+    bvh: Bvh,
+}
+
+impl Mesh {
+    /// Creates a new mesh.
+    ///
+    /// `normals`, if given, must have the same length as `positions`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a triangle references a vertex that does not exist, or if the number of normals
+    /// does not match the number of positions.
+    pub fn new(
+        positions: Vec<Vec3A>,
+        normals: Option<Vec<Vec3A>>,
+        triangles: Vec<[u32; 3]>,
+        material: Material,
+    ) -> Self {
+        if let Some(normals) = &normals {
+            assert_eq!(
+                normals.len(),
+                positions.len(),
+                "the number of normals must match the number of positions"
+            );
+        }
+
+        assert!(
+            triangles
+                .iter()
+                .flatten()
+                .all(|&index| (index as usize) < positions.len()),
+            "triangle references a vertex out of range"
+        );
+
+        let mut area = 0.0;
+        let mut area_cdf = Vec::with_capacity(triangles.len());
+
+        for &[i0, i1, i2] in &triangles {
+            area += triangle_area(
+                positions[i0 as usize],
+                positions[i1 as usize],
+                positions[i2 as usize],
+            );
+            area_cdf.push(area);
+        }
+
+        let triangle_bounding_boxes: Vec<_> = triangles
+            .iter()
+            .map(|&[i0, i1, i2]| {
+                triangle_bounding_box(
+                    positions[i0 as usize],
+                    positions[i1 as usize],
+                    positions[i2 as usize],
+                )
+            })
+            .collect();
+        let bvh = Bvh::build(&triangle_bounding_boxes);
+        let bounding_box = triangle_bounding_boxes
+            .into_iter()
+            .reduce(|a, b| Aabb {
+                min: a.min.min(b.min),
+                max: a.max.max(b.max),
+            })
+            .unwrap_or(Aabb {
+                min: Vec3A::ZERO,
+                max: Vec3A::ZERO,
+            });
+
+        Self {
+            positions,
+            normals,
+            uvs: None,
+            colors: None,
+            triangles,
+            material,
+            area,
+            area_cdf,
+            bounding_box,
+            bvh,
+        }
+    }
+
+    /// Sets per-vertex texture coordinates.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of UVs does not match the number of positions.
+    pub fn with_uvs(mut self, uvs: Vec<Vec2>) -> Self {
+        assert_eq!(
+            uvs.len(),
+            self.positions.len(),
+            "the number of UVs must match the number of positions"
+        );
+
+        self.uvs = Some(uvs);
+        self
+    }
+
+    /// Sets per-vertex colors, in linear RGB.
+    ///
+    /// Colors are interpolated into [`SurfaceFrame::color`], where textures can pick them up.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of colors does not match the number of positions.
+    pub fn with_colors(mut self, colors: Vec<Vec3A>) -> Self {
+        assert_eq!(
+            colors.len(),
+            self.positions.len(),
+            "the number of colors must match the number of positions"
+        );
+
+        self.colors = Some(colors);
+        self
+    }
+
+    /// Returns a copy of the mesh with its vertices moved by a transform.
+    ///
+    /// Mirroring transforms reverse the winding of the triangles, so that they keep facing outwards.
+    pub fn transformed(&self, transform: Affine3A) -> Self {
+        let normal_matrix = transform.matrix3.inverse().transpose();
+        let triangles = if transform.matrix3.determinant() < 0.0 {
+            self.triangles
+                .iter()
+                .map(|&[i0, i1, i2]| [i0, i2, i1])
+                .collect()
+        } else {
+            self.triangles.clone()
+        };
+
+        let mesh = Self::new(
+            self.positions
+                .iter()
+                .map(|&position| transform.transform_point3a(position))
+                .collect(),
+            self.normals.as_ref().map(|normals| {
+                normals
+                    .iter()
+                    .map(|&normal| normal_matrix.mul_vec3a(normal).normalize_or_zero())
+                    .collect()
+            }),
+            triangles,
+            self.material.clone(),
+        );
+
+        Self {
+            uvs: self.uvs.clone(),
+            colors: self.colors.clone(),
+            ..mesh
+        }
+    }
+
+    pub fn positions(&self) -> &[Vec3A] {
+        &self.positions
+    }
+
+    pub fn normals(&self) -> Option<&[Vec3A]> {
+        self.normals.as_deref()
+    }
+
+    pub fn uvs(&self) -> Option<&[Vec2]> {
+        self.uvs.as_deref()
+    }
+
+    pub fn colors(&self) -> Option<&[Vec3A]> {
+        self.colors.as_deref()
+    }
+
+    pub fn triangles(&self) -> &[[u32; 3]] {
+        &self.triangles
+    }
+
+    pub fn triangle_vertices(&self, triangle_index: usize) -> [Vec3A; 3] {
+        let [i0, i1, i2] = self.triangles[triangle_index];
+        [
+            self.positions[i0 as usize],
+            self.positions[i1 as usize],
+            self.positions[i2 as usize],
+        ]
+    }
+
+    pub fn triangle_normal(&self, triangle_index: usize) -> Vec3A {
+        let [p0, p1, p2] = self.triangle_vertices(triangle_index);
+        (p1 - p0).cross(p2 - p0).normalize_or_zero()
+    }
+
+    fn intersect_triangle_at(
+        &self,
+        triangle_index: usize,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        object_index: usize,
+    ) -> Option<HitRecord<'_>> {
+        let [p0, p1, p2] = self.triangle_vertices(triangle_index);
+        let hit = intersect_triangle(ray, p0, p1, p2, t_min, t_max)?;
+
+        let geometric_normal = self.triangle_normal(triangle_index);
+        let normal = match &self.normals {
+            Some(normals) => {
+                let [i0, i1, i2] = self.triangles[triangle_index];
+                let vertex_normals = [
+                    normals[i0 as usize],
+                    normals[i1 as usize],
+                    normals[i2 as usize],
+                ];
+                shading_normal(&vertex_normals, hit.barycentric, geometric_normal)
+            }
+            None => geometric_normal,
+        };
+
+        Some(hit.record(geometric_normal, normal, ray, self, object_index))
+    }
+}
+
+impl Object for Mesh {
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn area(&self) -> f32 {
+        self.area
+    }
+
+    fn sample_point(&self) -> PointOnObject {
+        if self.area < 1e-5 {
+            return PointOnObject {
+                point: self.bounding_box.min.lerp(self.bounding_box.max, 0.5),
+                normal: Vec3A::Y,
+            };
+        }
+
+        // pick a triangle with probability proportional to its area
+        let target = rand::random::<f32>() * self.area;
+        let triangle_index = self
+            .area_cdf
+            .partition_point(|&cdf| cdf <= target)
+            .min(self.triangles.len() - 1);
+
+        let [p0, p1, p2] = self.triangle_vertices(triangle_index);
+        let barycentric = sample_uniform_barycentric();
+
+        PointOnObject {
+            point: p0 * barycentric.x + p1 * barycentric.y + p2 * barycentric.z,
+            normal: self.triangle_normal(triangle_index),
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bounding_box.clone()
+    }
+
+    fn intersect(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        object_index: usize,
+    ) -> Option<HitRecord<'_>> {
+        let mut closest_hit = None;
+
+        self.bvh
+            .traverse(ray, t_min, t_max, |triangle_index, t_max| {
+                let hit =
+                    self.intersect_triangle_at(triangle_index, ray, t_min, t_max, object_index)?;
+                let t = hit.t;
+                closest_hit = Some((hit, triangle_index));
+                Some(t)
+            });
+
+        let (hit, triangle_index) = closest_hit?;
+        LAST_HITS.with_borrow_mut(|hits| hits.insert(self.key(), (hit.point, triangle_index)));
+
+        Some(hit)
+    }
+}
+
+impl Mesh {
+    fn key(&self) -> usize {
+        self as *const Self as usize
+    }
+
+    /// Returns the triangle of the last hit this mesh reported on this thread, if that hit was at
+    /// `point`, along with the barycentric weights of the point.
+    fn last_hit_triangle(&self, point: Vec3A, epsilon: f32) -> Option<(usize, Vec3A)> {
+        let (hit_point, triangle_index) =
+            LAST_HITS.with_borrow(|hits| hits.get(&self.key()).copied())?;
+
+        // instances shade a point that went through their transform and back, so it is only
+        // close to the one that was hit; a mesh dropped since then may also have left its hit to
+        // another mesh at the same address
+        if hit_point.distance_squared(point) > epsilon * epsilon
+            || triangle_index >= self.triangles.len()
+        {
+            return None;
+        }
+
+        let [p0, p1, p2] = self.triangle_vertices(triangle_index);
+        let barycentric = barycentric_coordinates(point, p0, p1, p2);
+
+        (barycentric.min_element() > -1e-3).then_some((triangle_index, barycentric))
+    }
+
+    /// Finds the triangle under a point with a short ray along the normal, so this is about as
+    /// expensive as a shadow ray.
+    fn find_triangle(&self, point: Vec3A, normal: Vec3A, epsilon: f32) -> Option<(usize, Vec3A)> {
+        let ray = Ray::new(point + normal * epsilon, -normal);
+
+        // keep the triangle closest to the point, rather than to the origin of the ray
+        let mut closest: Option<(usize, Vec3A, f32)> = None;
+        self.bvh
+            .traverse(&ray, 0.0, 2.0 * epsilon, |triangle_index, t_max| {
+                let [p0, p1, p2] = self.triangle_vertices(triangle_index);
+                let hit = intersect_triangle(&ray, p0, p1, p2, 0.0, t_max)?;
+                let distance = (hit.t - epsilon).abs();
+
+                if closest.is_none_or(|(_, _, closest_distance)| distance < closest_distance) {
+                    closest = Some((triangle_index, hit.barycentric, distance));
+                }
+
+                Some(t_max)
+            });
+
+        closest.map(|(triangle_index, barycentric, _)| (triangle_index, barycentric))
+    }
+}
+
+impl SurfaceParameterization for Mesh {
+    /// Points reported by [`Mesh::intersect`] are shaded on the triangle that was hit; other
+    /// points, such as those sampled on lights, look for their triangle with a short ray.
+    fn surface_frame(&self, point: Vec3A, normal: Vec3A) -> SurfaceFrame {
+        let normal = normal.normalize_or(Vec3A::Y);
+        let extent = (self.bounding_box.max - self.bounding_box.min).length();
+        let epsilon = 1e-4 * extent.max(1e-3);
+
+        let Some((triangle_index, barycentric)) = self
+            .last_hit_triangle(point, epsilon)
+            .or_else(|| self.find_triangle(point, normal, epsilon))
+        else {
+            return SurfaceFrame::from_normal(normal);
+        };
+
+        let [i0, i1, i2] = self.triangles[triangle_index].map(|index| index as usize);
+        let normals = self
+            .normals
+            .as_ref()
+            .map(|normals| [normals[i0], normals[i1], normals[i2]]);
+        let uvs = self.uvs.as_ref().map(|uvs| [uvs[i0], uvs[i1], uvs[i2]]);
+        let colors = self
+            .colors
+            .as_ref()
+            .map(|colors| [colors[i0], colors[i1], colors[i2]]);
+
+        triangle_frame(
+            self.triangle_vertices(triangle_index),
+            normals,
+            uvs,
+            colors,
+            barycentric,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Instance;
+    use std::sync::Arc;
+
+    /// A downward direction, tilted away from the vertical by up to 60 degrees.
+    fn random_downward_direction() -> Vec3A {
+        let tilt =
+            Vec3A::new(rand::random(), rand::random(), 0.0) * 2.0 - Vec3A::new(1.0, 1.0, 0.0);
+        (tilt * 1.2 - Vec3A::Z).normalize()
+    }
+
+    /// Checks that rays aimed at `targets` from many directions always hit the mesh.
+    fn assert_no_ray_slips_through(mesh: &Mesh, targets: impl Fn(f32) -> Vec3A) {
+        for i in 0..1000 {
+            let target = targets(i as f32 / 1000.0);
+            let direction = random_downward_direction();
+            let ray = Ray::new(target - direction * 3.0, direction);
+
+            assert!(
+                mesh.intersect(&ray, 1e-4, f32::INFINITY, 0).is_some(),
+                "{ray:?} slipped through the mesh"
+            );
+        }
+    }
+
+    #[test]
+    fn rays_through_a_shared_edge_hit_the_mesh() {
+        let mesh = Mesh::new(
+            vec![
+                Vec3A::ZERO,
+                Vec3A::new(1.0, 0.1, 0.0),
+                Vec3A::new(0.9, 1.0, 0.2),
+                Vec3A::new(0.0, 1.0, 0.0),
+            ],
+            None,
+            vec![[0, 1, 2], [0, 2, 3]],
+            crate::test_material(),
+        );
+
+        assert_no_ray_slips_through(&mesh, |s| Vec3A::new(0.9, 1.0, 0.2) * (0.05 + 0.9 * s));
+    }
+
+    #[test]
+    fn rays_through_a_shared_vertex_hit_the_mesh() {
+        let center = Vec3A::new(0.37, 0.41, 0.13);
+        let mesh = Mesh::new(
+            vec![
+                center,
+                Vec3A::new(-1.0, -1.0, 0.0),
+                Vec3A::new(1.0, -1.0, 0.0),
+                Vec3A::new(1.0, 1.0, 0.0),
+                Vec3A::new(-1.0, 1.0, 0.0),
+            ],
+            None,
+            vec![[0, 1, 2], [0, 2, 3], [0, 3, 4], [0, 4, 1]],
+            crate::test_material(),
+        );
+
+        assert_no_ray_slips_through(&mesh, |_| center);
+    }
+
+    #[test]
+    fn hits_are_shaded_on_the_triangle_that_was_hit() {
+        // a tent folded along the y axis, with a sharp ridge between its two sides
+        let mesh = Arc::new(Mesh::new(
+            vec![
+                Vec3A::new(0.0, -1.0, 0.5),
+                Vec3A::new(0.0, 1.0, 0.5),
+                Vec3A::new(-1.0, -1.0, 0.0),
+                Vec3A::new(-1.0, 1.0, 0.0),
+                Vec3A::new(1.0, -1.0, 0.0),
+                Vec3A::new(1.0, 1.0, 0.0),
+            ],
+            None,
+            vec![[0, 3, 2], [0, 1, 3], [0, 4, 5], [0, 5, 1]],
+            crate::test_material(),
+        ));
+        let instance = Instance::new(
+            mesh.clone(),
+            Affine3A::from_scale_rotation_translation(
+                glam::Vec3::new(1.0, 2.0, 0.5),
+                glam::Quat::from_rotation_z(0.5),
+                glam::Vec3::new(0.2, 0.0, -0.3),
+            ),
+        );
+        let objects: [&dyn SurfaceParameterization; 2] = [mesh.as_ref(), &instance];
+
+        for object in objects {
+            for _ in 0..200 {
+                let direction = random_downward_direction();
+                let target = Vec3A::new(rand::random(), rand::random(), 0.0) * 1.6 - 0.8;
+                let ray = Ray::new(target - direction * 3.0, direction);
+                let Some(hit) = object.intersect(&ray, 1e-4, f32::INFINITY, 0) else {
+                    continue;
+                };
+
+                let frame = object.surface_frame(hit.point, hit.normal);
+                let outward_normal = if hit.front_face {
+                    hit.normal
+                } else {
+                    -hit.normal
+                };
+                assert!(
+                    frame.geometric_normal.abs_diff_eq(outward_normal, 1e-4),
+                    "{ray:?}"
+                );
+            }
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::{DirectionToObject, SolidAngleSampling, SurfaceFrame, SurfaceParameterization};
 use glam::{Mat3A, Vec2, Vec3A};
 use raytracer_core::{
     aabb::Aabb,
@@ -6,6 +7,7 @@ use raytracer_core::{
     object::{Object, PointOnObject},
     ray::Ray,
 };
+use std::f32::consts::PI;
 
 #[derive(Debug, Clone)]
 pub struct Plain {
@@ -29,6 +31,64 @@ impl Plain {
 
         Mat3A::from_cols(new_x, new_y, new_z)
     }
+
+    /// Projects the plain onto the unit sphere around the reference point.
+    ///
+    /// Plains only emit light from the side their normal faces,
+    /// so there is nothing to sample from behind them.
+    fn spherical_rectangle(&self, reference: Vec3A) -> Option<SphericalRectangle> {
+        if (reference - self.center).dot(self.normal) <= 1e-6 {
+            return None;
+        }
+
+        let rotation = self.rotation();
+        let (x, y, z) = (rotation.x_axis, rotation.y_axis, rotation.z_axis);
+        let corner = self.center - x * (self.size.x * 0.5) - y * (self.size.y * 0.5);
+
+        // the rectangle in the local frame of the plain, centered on the reference point;
+        // being in front of the plain, `z0` is negative
+        let offset = corner - reference;
+        let (x0, y0, z0) = (offset.dot(x), offset.dot(y), offset.dot(z));
+        let (x1, y1) = (x0 + self.size.x, y0 + self.size.y);
+
+        let v00 = Vec3A::new(x0, y0, z0);
+        let v01 = Vec3A::new(x0, y1, z0);
+        let v10 = Vec3A::new(x1, y0, z0);
+        let v11 = Vec3A::new(x1, y1, z0);
+
+        // normals of the planes through the reference point and each edge
+        let n0 = v00.cross(v10).normalize();
+        let n1 = v10.cross(v11).normalize();
+        let n2 = v11.cross(v01).normalize();
+        let n3 = v01.cross(v00).normalize();
+
+        // interior angles of the spherical rectangle
+        let g0 = (-n0.dot(n1)).clamp(-1.0, 1.0).acos();
+        let g1 = (-n1.dot(n2)).clamp(-1.0, 1.0).acos();
+        let g2 = (-n2.dot(n3)).clamp(-1.0, 1.0).acos();
+        let g3 = (-n3.dot(n0)).clamp(-1.0, 1.0).acos();
+
+        let k = 2.0 * PI - g2 - g3;
+        let solid_angle = g0 + g1 - k;
+
+        if solid_angle.is_nan() || solid_angle <= 1e-7 {
+            return None;
+        }
+
+        Some(SphericalRectangle {
+            reference,
+            frame: rotation,
+            x0,
+            x1,
+            y0,
+            y1,
+            z0,
+            b0: n0.z,
+            b1: n2.z,
+            k,
+            solid_angle,
+        })
+    }
 }
 
 impl Object for Plain {
@@ -114,3 +174,128 @@ impl Object for Plain {
         ))
     }
 }
+
+impl SolidAngleSampling for Plain {
+    fn supports_solid_angle_sampling(&self, _reference: Vec3A) -> bool {
+        true
+    }
+
+    fn sample_direction(&self, reference: Vec3A) -> Option<DirectionToObject> {
+        let rectangle = self.spherical_rectangle(reference)?;
+        let point = rectangle.sample(rand::random::<f32>(), rand::random::<f32>());
+        let diff = point - reference;
+        let distance = diff.length();
+
+        if distance < 1e-5 {
+            return None;
+        }
+
+        Some(DirectionToObject {
+            point,
+            normal: self.normal,
+            direction: diff / distance,
+            distance,
+            pdf: rectangle.solid_angle.recip(),
+        })
+    }
+
+    fn direction_pdf(&self, reference: Vec3A, direction: Vec3A) -> f32 {
+        let Some(rectangle) = self.spherical_rectangle(reference) else {
+            return 0.0;
+        };
+
+        let denominator = self.normal.dot(direction);
+
+        if denominator > -1e-5 {
+            return 0.0;
+        }
+
+        let t = (self.center - reference).dot(self.normal) / denominator;
+        let offset = reference + direction * t - self.center;
+        let half_size = self.size * 0.5;
+
+        if offset.dot(rectangle.frame.x_axis).abs() > half_size.x
+            || offset.dot(rectangle.frame.y_axis).abs() > half_size.y
+        {
+            return 0.0;
+        }
+
+        rectangle.solid_angle.recip()
+    }
+}
+
+/// A rectangle projected onto the unit sphere around a point, which can be sampled uniformly.
+///
+/// See Ureña et al., "An Area-Preserving Parametrization for Spherical Rectangles".
+struct SphericalRectangle {
+    reference: Vec3A,
+    frame: Mat3A,
+    x0: f32,
+    x1: f32,
+    y0: f32,
+    y1: f32,
+    z0: f32,
+    b0: f32,
+    b1: f32,
+    k: f32,
+    solid_angle: f32,
+}
+
+impl SphericalRectangle {
+    /// Maps two uniform random numbers to a uniformly distributed point of the rectangle,
+    /// with respect to solid angle.
+    fn sample(&self, u: f32, v: f32) -> Vec3A {
+        let Self {
+            x0,
+            x1,
+            y0,
+            y1,
+            z0,
+            b0,
+            b1,
+            k,
+            ..
+        } = *self;
+
+        // choose the x coordinate so that the sub-rectangle to its left covers `u` of the area
+        let au = u * self.solid_angle + k;
+        let fu = (au.cos() * b0 - b1) / au.sin();
+        let cu = (fu.signum() / (fu * fu + b0 * b0).sqrt()).clamp(-1.0, 1.0);
+        let xu = (-(cu * z0) / (1.0 - cu * cu).max(1e-12).sqrt()).clamp(x0, x1);
+
+        // then the y coordinate, uniformly in the projected height of that column
+        let d = (xu * xu + z0 * z0).sqrt();
+        let h0 = y0 / (d * d + y0 * y0).sqrt();
+        let h1 = y1 / (d * d + y1 * y1).sqrt();
+        let hv = h0 + v * (h1 - h0);
+        let hv2 = hv * hv;
+        let yv = if hv2 < 1.0 - 1e-6 {
+            (hv * d / (1.0 - hv2).sqrt()).clamp(y0, y1)
+        } else {
+            y1
+        };
+
+        self.reference + self.frame.mul_vec3a(Vec3A::new(xu, yv, z0))
+    }
+}
+
+impl SurfaceParameterization for Plain {
+    /// UVs span the plain from `(0, 0)` to `(1, 1)`, along the axes of [`Plain::rotation`].
+    fn surface_frame(&self, point: Vec3A, _normal: Vec3A) -> SurfaceFrame {
+        let rotation = self.rotation();
+        let local = point - self.center;
+
+        SurfaceFrame {
+            uv: Vec2::new(
+                local.dot(rotation.x_axis) / self.size.x + 0.5,
+                local.dot(rotation.y_axis) / self.size.y + 0.5,
+            ),
+            dpdu: rotation.x_axis * self.size.x,
+            dpdv: rotation.y_axis * self.size.y,
+            geometric_normal: self.normal,
+            shading_normal: self.normal,
+            color: None,
+        }
+        .with_valid_tangents()
+    }
+}
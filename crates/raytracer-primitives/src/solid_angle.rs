@@ -0,0 +1,51 @@
+use glam::Vec3A;
+use raytracer_core::object::Object;
+
+/// A direction from a reference point towards a point on an object.
+#[derive(Debug, Clone)]
+pub struct DirectionToObject {
+    pub point: Vec3A,
+    pub normal: Vec3A,
+    /// unit vector from the reference point towards `point`
+    pub direction: Vec3A,
+    pub distance: f32,
+    /// probability density of the direction, with respect to solid angle
+    pub pdf: f32,
+}
+
+/// Objects that can sample the directions they cover as seen from a point,
+/// rather than only points on their surface.
+///
+/// Sampling the visible solid angle avoids the samples that area sampling wastes on the far side
+/// of an object, and on the parts that are seen at a grazing angle, which makes it much less noisy
+/// for lights close to the point being lit.
+pub trait SolidAngleSampling: Object {
+    /// Returns whether the directions can be sampled from the reference point.
+    ///
+    /// Otherwise, callers should fall back to [`Object::sample_point`].
+    fn supports_solid_angle_sampling(&self, reference: Vec3A) -> bool;
+    /// Samples a direction towards a point of the object that emits light towards the reference
+    /// point, if there is any.
+    fn sample_direction(&self, reference: Vec3A) -> Option<DirectionToObject>;
+    /// Returns the probability density of [`SolidAngleSampling::sample_direction`] choosing
+    /// `direction` from the reference point.
+    fn direction_pdf(&self, reference: Vec3A, direction: Vec3A) -> f32;
+}
+
+/// Builds two unit vectors perpendicular to `normal` and to each other.
+///
+/// See Duff et al., "Building an Orthonormal Basis, Revisited".
+pub(crate) fn orthonormal_basis(normal: Vec3A) -> (Vec3A, Vec3A) {
+    let sign = 1f32.copysign(normal.z);
+    let a = -1.0 / (sign + normal.z);
+    let b = normal.x * normal.y * a;
+
+    (
+        Vec3A::new(
+            1.0 + sign * normal.x * normal.x * a,
+            sign * b,
+            -sign * normal.x,
+        ),
+        Vec3A::new(b, sign + normal.y * normal.y * a, -normal.y),
+    )
+}
@@ -1,4 +1,8 @@
-use glam::Vec3A;
+use crate::{
+    DirectionToObject, SolidAngleSampling, SurfaceFrame, SurfaceParameterization,
+    solid_angle::orthonormal_basis,
+};
+use glam::{Vec2, Vec3A};
 use raytracer_core::{
     aabb::Aabb,
     hit_record::HitRecord,
@@ -99,3 +103,106 @@ impl Object for Sphere {
         Some(hit_record)
     }
 }
+
+impl Sphere {
+    /// Returns the axis of the cone of directions covered by the sphere as seen from the
+    /// reference point, along with the distance to its center and `1 - cos` of its half angle.
+    fn visible_cone(&self, reference: Vec3A) -> (Vec3A, f32, f32) {
+        let to_center = self.center - reference;
+        let distance = to_center.length();
+        let sin2_theta_max = (self.radius * self.radius / (distance * distance)).min(1.0);
+        let cos_theta_max = (1.0 - sin2_theta_max).sqrt();
+
+        // written this way to stay accurate for small, distant spheres
+        let one_minus_cos_theta_max = sin2_theta_max / (1.0 + cos_theta_max);
+
+        (to_center / distance, distance, one_minus_cos_theta_max)
+    }
+}
+
+impl SolidAngleSampling for Sphere {
+    fn supports_solid_angle_sampling(&self, reference: Vec3A) -> bool {
+        // from the inside, every direction hits the sphere, so there is nothing to gain
+        (reference - self.center).length_squared() > self.radius * self.radius * (1.0 + 1e-4)
+    }
+
+    fn sample_direction(&self, reference: Vec3A) -> Option<DirectionToObject> {
+        let (axis, distance, one_minus_cos_theta_max) = self.visible_cone(reference);
+
+        if one_minus_cos_theta_max <= 0.0 {
+            return None;
+        }
+
+        // uniformly sample the cone of directions covered by the sphere
+        let one_minus_cos_theta = rand::random::<f32>() * one_minus_cos_theta_max;
+        let cos_theta = 1.0 - one_minus_cos_theta;
+        let sin_theta = (one_minus_cos_theta * (2.0 - one_minus_cos_theta))
+            .max(0.0)
+            .sqrt();
+        let phi = 2.0 * PI * rand::random::<f32>();
+
+        let (tangent, bitangent) = orthonormal_basis(axis);
+        let direction = (tangent * sin_theta * phi.cos()
+            + bitangent * sin_theta * phi.sin()
+            + axis * cos_theta)
+            .normalize();
+
+        // the nearest intersection of the direction with the sphere
+        let t = distance * cos_theta
+            - (self.radius * self.radius - distance * distance * sin_theta * sin_theta)
+                .max(0.0)
+                .sqrt();
+        let point = reference + direction * t.max(0.0);
+        let normal = (point - self.center).normalize();
+
+        Some(DirectionToObject {
+            point,
+            normal,
+            direction,
+            distance: t.max(0.0),
+            pdf: (2.0 * PI * one_minus_cos_theta_max).recip(),
+        })
+    }
+
+    fn direction_pdf(&self, reference: Vec3A, direction: Vec3A) -> f32 {
+        let (axis, _, one_minus_cos_theta_max) = self.visible_cone(reference);
+
+        if one_minus_cos_theta_max <= 0.0 || 1.0 - direction.dot(axis) > one_minus_cos_theta_max {
+            return 0.0;
+        }
+
+        (2.0 * PI * one_minus_cos_theta_max).recip()
+    }
+}
+
+impl SurfaceParameterization for Sphere {
+    /// `u` goes around the `Y` axis starting from `+X` towards `-Z`, so that it increases to the
+    /// right when seen from outside, and `v` goes from the bottom pole to the top.
+    fn surface_frame(&self, point: Vec3A, _normal: Vec3A) -> SurfaceFrame {
+        let local = point - self.center;
+        let normal = local.normalize_or(Vec3A::Y);
+        let local = normal * self.radius;
+
+        let phi = (-local.z).atan2(local.x).rem_euclid(2.0 * PI);
+        let theta = normal.y.clamp(-1.0, 1.0).acos();
+
+        // the position as a function of (φ, θ) is (r sinθ cosφ, r cosθ, -r sinθ sinφ),
+        // with φ = 2πu and θ = π(1 - v)
+        let dpdu = Vec3A::new(local.z, 0.0, -local.x) * (2.0 * PI);
+        let dpdv = Vec3A::new(
+            local.y * phi.cos(),
+            -self.radius * theta.sin(),
+            -local.y * phi.sin(),
+        ) * -PI;
+
+        SurfaceFrame {
+            uv: Vec2::new(phi / (2.0 * PI), 1.0 - theta / PI),
+            dpdu,
+            dpdv,
+            geometric_normal: normal,
+            shading_normal: normal,
+            color: None,
+        }
+        .with_valid_tangents()
+    }
+}
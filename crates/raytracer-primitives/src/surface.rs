@@ -0,0 +1,98 @@
+use crate::solid_angle::orthonormal_basis;
+use glam::{Vec2, Vec3A};
+use raytracer_core::{
+    aabb::Aabb,
+    hit_record::HitRecord,
+    material::Material,
+    object::{Object, PointOnObject},
+    ray::Ray,
+};
+use std::sync::Arc;
+
+/// The parameterization of a surface around a point.
+#[derive(Debug, Clone)]
+pub struct SurfaceFrame {
+    pub uv: Vec2,
+    /// derivative of the position with respect to `u`
+    pub dpdu: Vec3A,
+    /// derivative of the position with respect to `v`
+    pub dpdv: Vec3A,
+    /// the true normal of the surface, facing outwards
+    pub geometric_normal: Vec3A,
+    /// the normal used for shading, such as one interpolated from vertex normals;
+    /// always on the same side as the geometric normal
+    pub shading_normal: Vec3A,
+    /// the color interpolated from vertex colors, for meshes that have them
+    pub color: Option<Vec3A>,
+}
+
+impl SurfaceFrame {
+    /// A frame for surfaces without a parameterization, with every point at the origin of UV space.
+    pub fn from_normal(normal: Vec3A) -> Self {
+        let (dpdu, dpdv) = orthonormal_basis(normal);
+
+        Self {
+            uv: Vec2::ZERO,
+            dpdu,
+            dpdv,
+            geometric_normal: normal,
+            shading_normal: normal,
+            color: None,
+        }
+    }
+
+    /// Fills in tangents for points where the parameterization degenerates, such as at the poles
+    /// of a sphere, so that `dpdu` and `dpdv` always span the tangent plane.
+    pub(crate) fn with_valid_tangents(mut self) -> Self {
+        if self.dpdu.cross(self.dpdv).length_squared() < 1e-12 {
+            let (dpdu, dpdv) = orthonormal_basis(self.geometric_normal);
+            self.dpdu = dpdu;
+            self.dpdv = dpdv;
+        }
+
+        self
+    }
+}
+
+/// Objects that can describe their surface around a point that was reported by
+/// [`Object::intersect`] or [`Object::sample_point`].
+///
+/// Hit records only carry a position and a normal, so textures and shading frames are computed
+/// from those afterwards, and only for the points that are actually shaded.
+pub trait SurfaceParameterization: Object {
+    /// `normal` may be the normal of either side of the surface.
+    fn surface_frame(&self, point: Vec3A, normal: Vec3A) -> SurfaceFrame;
+}
+
+/// An object shared with other owners, such as a scene and whatever shades it.
+///
+/// The object is not copied, which matters for large meshes.
+pub struct SharedObject<T: ?Sized>(pub Arc<T>);
+
+impl<T: Object + ?Sized> Object for SharedObject<T> {
+    fn material(&self) -> &Material {
+        self.0.material()
+    }
+
+    fn area(&self) -> f32 {
+        self.0.area()
+    }
+
+    fn sample_point(&self) -> PointOnObject {
+        self.0.sample_point()
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.0.bounding_box()
+    }
+
+    fn intersect(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        object_index: usize,
+    ) -> Option<HitRecord<'_>> {
+        self.0.intersect(ray, t_min, t_max, object_index)
+    }
+}
@@ -0,0 +1,356 @@
+use crate::{SurfaceFrame, SurfaceParameterization};
+use glam::{Vec2, Vec3A};
+use raytracer_core::{
+    aabb::Aabb,
+    hit_record::HitRecord,
+    material::Material,
+    object::{Object, PointOnObject},
+    ray::Ray,
+};
+
+#[derive(Debug, Clone)]
+pub struct Triangle {
+    pub vertices: [Vec3A; 3],
+    /// per-vertex normals used for smooth shading; the geometric normal is used if `None`
+    pub normals: Option<[Vec3A; 3]>,
+    /// per-vertex texture coordinates; `(0, 0)`, `(1, 0)` and `(1, 1)` are used if `None`
+    pub uvs: Option<[Vec2; 3]>,
+    pub material: Material,
+}
+
+impl Triangle {
+    pub fn geometric_normal(&self) -> Vec3A {
+        let [p0, p1, p2] = self.vertices;
+        (p1 - p0).cross(p2 - p0).normalize_or_zero()
+    }
+}
+
+impl Object for Triangle {
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn area(&self) -> f32 {
+        let [p0, p1, p2] = self.vertices;
+        triangle_area(p0, p1, p2)
+    }
+
+    fn sample_point(&self) -> PointOnObject {
+        let [p0, p1, p2] = self.vertices;
+        let barycentric = sample_uniform_barycentric();
+
+        PointOnObject {
+            point: p0 * barycentric.x + p1 * barycentric.y + p2 * barycentric.z,
+            normal: self.geometric_normal(),
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let [p0, p1, p2] = self.vertices;
+        triangle_bounding_box(p0, p1, p2)
+    }
+
+    fn intersect(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        object_index: usize,
+    ) -> Option<HitRecord<'_>> {
+        let [p0, p1, p2] = self.vertices;
+        let hit = intersect_triangle(ray, p0, p1, p2, t_min, t_max)?;
+
+        let geometric_normal = self.geometric_normal();
+        let normal = match &self.normals {
+            Some(normals) => shading_normal(normals, hit.barycentric, geometric_normal),
+            None => geometric_normal,
+        };
+
+        Some(hit.record(geometric_normal, normal, ray, self, object_index))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TriangleHit {
+    pub t: f32,
+    pub point: Vec3A,
+    /// barycentric weights of the three vertices, in vertex order
+    pub barycentric: Vec3A,
+}
+
+impl TriangleHit {
+    /// Builds the hit record, deciding which side was hit from the geometric normal.
+    ///
+    /// An interpolated shading normal can face the ray near silhouettes while the triangle itself
+    /// faces away, so the shading normal is only flipped to the side the geometric normal faces.
+    pub(crate) fn record<'a>(
+        &self,
+        geometric_normal: Vec3A,
+        shading_normal: Vec3A,
+        ray: &Ray,
+        object: &'a dyn Object,
+        object_index: usize,
+    ) -> HitRecord<'a> {
+        let front_face = ray.direction.dot(geometric_normal) < 0.0;
+
+        HitRecord {
+            point: self.point,
+            normal: if front_face {
+                shading_normal
+            } else {
+                -shading_normal
+            },
+            t: self.t,
+            front_face,
+            object,
+            object_index,
+        }
+    }
+}
+
+/// Watertight ray-triangle intersection, as described in
+/// [Woop et al. 2013](https://jcgt.org/published/0002/01/05/).
+///
+/// The ray is transformed into a space where it points along +Z, which makes the edge tests
+/// consistent between neighbouring triangles, so rays never slip through shared edges.
+pub(crate) fn intersect_triangle(
+    ray: &Ray,
+    p0: Vec3A,
+    p1: Vec3A,
+    p2: Vec3A,
+    t_min: f32,
+    t_max: f32,
+) -> Option<TriangleHit> {
+    let direction = ray.direction;
+    let abs_direction = direction.abs();
+
+    // permute the axes so that the largest component of the direction becomes z
+    let kz = if abs_direction.x > abs_direction.y {
+        if abs_direction.x > abs_direction.z {
+            0
+        } else {
+            2
+        }
+    } else if abs_direction.y > abs_direction.z {
+        1
+    } else {
+        2
+    };
+    let kx = (kz + 1) % 3;
+    let ky = (kx + 1) % 3;
+
+    if direction[kz] == 0.0 {
+        return None;
+    }
+
+    let permute = |v: Vec3A| Vec3A::new(v[kx], v[ky], v[kz]);
+    let d = permute(direction);
+    let mut p0t = permute(p0 - ray.origin);
+    let mut p1t = permute(p1 - ray.origin);
+    let mut p2t = permute(p2 - ray.origin);
+
+    // shear the vertices so that the ray direction becomes (0, 0, 1)
+    let shear_x = -d.x / d.z;
+    let shear_y = -d.y / d.z;
+    let shear_z = d.z.recip();
+
+    p0t.x += shear_x * p0t.z;
+    p0t.y += shear_y * p0t.z;
+    p1t.x += shear_x * p1t.z;
+    p1t.y += shear_y * p1t.z;
+    p2t.x += shear_x * p2t.z;
+    p2t.y += shear_y * p2t.z;
+
+    let mut e0 = p1t.x * p2t.y - p1t.y * p2t.x;
+    let mut e1 = p2t.x * p0t.y - p2t.y * p0t.x;
+    let mut e2 = p0t.x * p1t.y - p0t.y * p1t.x;
+
+    // fall back to double precision when an edge function is exactly zero,
+    // so that rays hitting an edge are never reported as a miss for both triangles
+    if e0 == 0.0 || e1 == 0.0 || e2 == 0.0 {
+        e0 = (p1t.x as f64 * p2t.y as f64 - p1t.y as f64 * p2t.x as f64) as f32;
+        e1 = (p2t.x as f64 * p0t.y as f64 - p2t.y as f64 * p0t.x as f64) as f32;
+        e2 = (p0t.x as f64 * p1t.y as f64 - p0t.y as f64 * p1t.x as f64) as f32;
+    }
+
+    if (e0 < 0.0 || e1 < 0.0 || e2 < 0.0) && (e0 > 0.0 || e1 > 0.0 || e2 > 0.0) {
+        return None;
+    }
+
+    let det = e0 + e1 + e2;
+
+    if det == 0.0 {
+        return None;
+    }
+
+    p0t.z *= shear_z;
+    p1t.z *= shear_z;
+    p2t.z *= shear_z;
+
+    let det_inv = det.recip();
+    let t = (e0 * p0t.z + e1 * p1t.z + e2 * p2t.z) * det_inv;
+
+    if !(t_min..=t_max).contains(&t) {
+        return None;
+    }
+
+    let barycentric = Vec3A::new(e0, e1, e2) * det_inv;
+
+    Some(TriangleHit {
+        t,
+        point: p0 * barycentric.x + p1 * barycentric.y + p2 * barycentric.z,
+        barycentric,
+    })
+}
+
+impl SurfaceParameterization for Triangle {
+    fn surface_frame(&self, point: Vec3A, _normal: Vec3A) -> SurfaceFrame {
+        let [p0, p1, p2] = self.vertices;
+        let barycentric = barycentric_coordinates(point, p0, p1, p2);
+
+        triangle_frame(self.vertices, self.normals, self.uvs, None, barycentric)
+    }
+}
+
+/// Builds the surface frame of a triangle at the point with the given barycentric weights.
+pub(crate) fn triangle_frame(
+    vertices: [Vec3A; 3],
+    normals: Option<[Vec3A; 3]>,
+    uvs: Option<[Vec2; 3]>,
+    colors: Option<[Vec3A; 3]>,
+    barycentric: Vec3A,
+) -> SurfaceFrame {
+    let [p0, p1, p2] = vertices;
+    let geometric_normal = (p1 - p0).cross(p2 - p0).normalize_or_zero();
+
+    // the default parameterization is the same as pbrt's
+    let uvs = uvs.unwrap_or([Vec2::ZERO, Vec2::X, Vec2::ONE]);
+
+    // solve for the derivatives that map the UV edges onto the position edges
+    let duv02 = uvs[0] - uvs[2];
+    let duv12 = uvs[1] - uvs[2];
+    let dp02 = p0 - p2;
+    let dp12 = p1 - p2;
+    let determinant = duv02.x * duv12.y - duv02.y * duv12.x;
+
+    let (dpdu, dpdv) = if determinant.abs() < 1e-12 {
+        // the UVs are degenerate; any tangents will do
+        (Vec3A::ZERO, Vec3A::ZERO)
+    } else {
+        (
+            (dp02 * duv12.y - dp12 * duv02.y) / determinant,
+            (dp12 * duv02.x - dp02 * duv12.x) / determinant,
+        )
+    };
+
+    let shading_normal = match &normals {
+        Some(normals) => shading_normal(normals, barycentric, geometric_normal),
+        None => geometric_normal,
+    };
+
+    SurfaceFrame {
+        uv: uvs[0] * barycentric.x + uvs[1] * barycentric.y + uvs[2] * barycentric.z,
+        dpdu,
+        dpdv,
+        geometric_normal,
+        shading_normal,
+        color: colors.map(|colors| {
+            colors[0] * barycentric.x + colors[1] * barycentric.y + colors[2] * barycentric.z
+        }),
+    }
+    .with_valid_tangents()
+}
+
+/// Returns the barycentric weights of the projection of a point onto the plane of a triangle.
+pub(crate) fn barycentric_coordinates(point: Vec3A, p0: Vec3A, p1: Vec3A, p2: Vec3A) -> Vec3A {
+    let e1 = p1 - p0;
+    let e2 = p2 - p0;
+    let d = point - p0;
+
+    let d11 = e1.dot(e1);
+    let d12 = e1.dot(e2);
+    let d22 = e2.dot(e2);
+    let denominator = d11 * d22 - d12 * d12;
+
+    if denominator.abs() < 1e-20 {
+        return Vec3A::X;
+    }
+
+    let b1 = (d22 * d.dot(e1) - d12 * d.dot(e2)) / denominator;
+    let b2 = (d11 * d.dot(e2) - d12 * d.dot(e1)) / denominator;
+
+    Vec3A::new(1.0 - b1 - b2, b1, b2)
+}
+
+/// Interpolates the vertex normals, keeping the result on the same side as the geometric normal.
+pub(crate) fn shading_normal(
+    normals: &[Vec3A; 3],
+    barycentric: Vec3A,
+    geometric_normal: Vec3A,
+) -> Vec3A {
+    let normal =
+        (normals[0] * barycentric.x + normals[1] * barycentric.y + normals[2] * barycentric.z)
+            .normalize_or_zero();
+
+    if normal == Vec3A::ZERO {
+        geometric_normal
+    } else if normal.dot(geometric_normal) < 0.0 {
+        -normal
+    } else {
+        normal
+    }
+}
+
+pub(crate) fn triangle_area(p0: Vec3A, p1: Vec3A, p2: Vec3A) -> f32 {
+    (p1 - p0).cross(p2 - p0).length() * 0.5
+}
+
+pub(crate) fn triangle_bounding_box(p0: Vec3A, p1: Vec3A, p2: Vec3A) -> Aabb {
+    Aabb {
+        min: p0.min(p1).min(p2),
+        max: p0.max(p1).max(p2),
+    }
+}
+
+/// Returns uniformly distributed barycentric weights over a triangle.
+pub(crate) fn sample_uniform_barycentric() -> Vec3A {
+    let r1 = rand::random::<f32>();
+    let r2 = rand::random::<f32>();
+
+    let sqrt_r1 = r1.sqrt();
+    let b0 = 1.0 - sqrt_r1;
+    let b1 = r2 * sqrt_r1;
+
+    Vec3A::new(b0, b1, 1.0 - b0 - b1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vertex_normals_are_interpolated_on_the_side_that_was_hit() {
+        let normals = [
+            Vec3A::new(-1.0, 0.0, 1.0).normalize(),
+            Vec3A::new(1.0, 0.0, 1.0).normalize(),
+            Vec3A::new(0.0, 1.0, 1.0).normalize(),
+        ];
+        let triangle = Triangle {
+            vertices: [Vec3A::ZERO, Vec3A::X, Vec3A::Y],
+            normals: Some(normals),
+            uvs: None,
+            material: crate::test_material(),
+        };
+        let expected = (normals[0] * 0.5 + normals[1] * 0.25 + normals[2] * 0.25).normalize();
+
+        let above = Ray::new(Vec3A::new(0.25, 0.25, 1.0), Vec3A::NEG_Z);
+        let hit = triangle.intersect(&above, 1e-4, f32::INFINITY, 0).unwrap();
+        assert!(hit.front_face);
+        assert!(hit.normal.abs_diff_eq(expected, 1e-5));
+
+        let below = Ray::new(Vec3A::new(0.25, 0.25, -1.0), Vec3A::Z);
+        let hit = triangle.intersect(&below, 1e-4, f32::INFINITY, 0).unwrap();
+        assert!(!hit.front_face);
+        assert!(hit.normal.abs_diff_eq(-expected, 1e-5));
+    }
+}
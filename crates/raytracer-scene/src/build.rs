@@ -0,0 +1,547 @@
+use crate::{
+    description::{
+        BrdfKind, EnvironmentDescription, FilterDescription, LightDescription, MappingDescription,
+        MaterialTexturesDescription, NormalDescription, ObjectDescription,
+        ScalarTextureDescription, SceneDescription, TextureDescription, WrapDescription,
+        rotation_from_degrees,
+    },
+    file::SceneError,
+};
+use glam::{Affine3A, Vec3, Vec3A};
+use raytracer_core::{camera::Camera, material::Material, object::Object, scene::Scene};
+use raytracer_cpu_renderer::{
+    brdf::Brdf,
+    brdfs::{
+        conductor::ConductorBrdf, dielectric::DielectricBrdf, disney::DisneyBrdf,
+        lambertian::LambertianBrdf,
+    },
+    environment::EnvironmentMap,
+    hdr::HdrImage,
+    lights::{DirectionalLight, PointLight, SceneLights, SpotLight},
+    shading::{ObjectShading, SceneShading},
+    sky::{SUN_ANGULAR_RADIUS, SunSky, SunSkyConfig},
+    subsurface::RandomWalkSubsurface,
+    texture::{MaterialTextures, NormalPerturbation, ScalarTexture, Texture},
+    textures::{
+        ConstantTexture, TextureMapping, VertexColorTexture,
+        checker::CheckerTexture,
+        grid::GridTexture,
+        image::{ColorSpace, Filter, ImageTexture, WrapMode},
+        noise::{MarbleTexture, NoiseKind, NoiseTexture, Octaves},
+    },
+};
+use raytracer_importers::{
+    DEFAULT_MATERIAL, gltf::add_gltf_to_scene, obj::load_obj, ply::load_ply, stl::load_stl,
+};
+use raytracer_primitives::{Box, Instance, Mesh, Plain, Sphere};
+use std::{collections::HashMap, ffi::OsStr, path::Path, sync::Arc};
+
+/// Everything the renderer needs from a scene description.
+pub struct LoadedScene {
+    pub scene: Scene,
+    pub camera: Camera,
+    pub shading: SceneShading,
+    pub lights: SceneLights,
+}
+
+impl SceneDescription {
+    /// Creates the objects and lights of the scene.
+    ///
+    /// Relative paths are resolved against `base_dir`, and objects whose material does not choose
+    /// a BRDF use `default_brdf`. Emissive spheres and plains are lit by sampling the solid angle
+    /// they cover. Meshes with vertex colors, such as scanned PLY files, take their albedo from
+    /// those colors unless their material has an albedo texture.
+    pub fn build(
+        &self,
+        base_dir: &Path,
+        default_brdf: Arc<dyn Brdf>,
+    ) -> Result<LoadedScene, SceneError> {
+        let mut scene = Scene::new(&self.name);
+        let mut shading = SceneShading::new(default_brdf);
+        let mut lights = SceneLights::new();
+
+        let materials = self
+            .materials
+            .iter()
+            .map(|(name, material)| {
+                let brdf: Option<Arc<dyn Brdf>> = match material.brdf {
+                    None => None,
+                    Some(BrdfKind::Disney) => Some(Arc::new(DisneyBrdf)),
+                    Some(BrdfKind::Lambertian) => Some(Arc::new(LambertianBrdf)),
+                    Some(BrdfKind::Dielectric) => Some(Arc::new(DielectricBrdf::new(material.ior))),
+                    Some(BrdfKind::Conductor) => {
+                        Some(Arc::new(ConductorBrdf::new(material.eta, material.k)))
+                    }
+                };
+                let scene_material = SceneMaterial {
+                    material: material.to_material(),
+                    brdf,
+                    textures: material.textures.build(base_dir)?,
+                    subsurface: material.scattering_distance.map(RandomWalkSubsurface::new),
+                };
+                Ok((name.as_str(), scene_material))
+            })
+            .collect::<Result<HashMap<_, _>, SceneError>>()?;
+
+        let material = |name: &str| {
+            materials
+                .get(name)
+                .ok_or_else(|| SceneError::UndefinedMaterial {
+                    name: name.to_owned(),
+                })
+        };
+
+        for object in &self.objects {
+            let first_index = scene.objects().len();
+
+            match object {
+                ObjectDescription::Box {
+                    center,
+                    size,
+                    rotation,
+                    material: name,
+                } => {
+                    shading.add_object(
+                        &mut scene,
+                        Box {
+                            center: *center,
+                            size: *size,
+                            rotation: rotation_from_degrees(*rotation),
+                            material: material(name)?.material.clone(),
+                        },
+                    );
+                }
+                ObjectDescription::Plain {
+                    center,
+                    normal,
+                    size,
+                    material: name,
+                } => {
+                    let plain = Plain {
+                        center: *center,
+                        normal: normal.normalize(),
+                        size: *size,
+                        material: material(name)?.material.clone(),
+                    };
+
+                    if plain.material.is_emissive {
+                        lights.add_light_object(&mut scene, &mut shading, plain);
+                    } else {
+                        shading.add_object(&mut scene, plain);
+                    }
+                }
+                ObjectDescription::Sphere {
+                    center,
+                    radius,
+                    material: name,
+                } => {
+                    let sphere = Sphere {
+                        center: *center,
+                        radius: *radius,
+                        material: material(name)?.material.clone(),
+                    };
+
+                    if sphere.material.is_emissive {
+                        lights.add_light_object(&mut scene, &mut shading, sphere);
+                    } else {
+                        shading.add_object(&mut scene, sphere);
+                    }
+                }
+                ObjectDescription::TriangleMesh {
+                    positions,
+                    normals,
+                    uvs,
+                    triangles,
+                    material: name,
+                } => {
+                    let vertex_count = positions.len();
+                    let invalid = |message: &str| {
+                        Err(SceneError::InvalidMesh {
+                            message: message.to_owned(),
+                        })
+                    };
+
+                    if normals
+                        .as_ref()
+                        .is_some_and(|normals| normals.len() != vertex_count)
+                    {
+                        return invalid(
+                            "the number of normals does not match the number of positions",
+                        );
+                    }
+
+                    if uvs.as_ref().is_some_and(|uvs| uvs.len() != vertex_count) {
+                        return invalid("the number of UVs does not match the number of positions");
+                    }
+
+                    if triangles
+                        .iter()
+                        .flatten()
+                        .any(|&index| index as usize >= vertex_count)
+                    {
+                        return invalid("a triangle references a vertex out of range");
+                    }
+
+                    let mesh = Mesh::new(
+                        positions.clone(),
+                        normals.clone(),
+                        triangles.clone(),
+                        material(name)?.material.clone(),
+                    );
+
+                    shading.add_object(
+                        &mut scene,
+                        match uvs {
+                            Some(uvs) => mesh.with_uvs(uvs.clone()),
+                            None => mesh,
+                        },
+                    );
+                }
+                ObjectDescription::Mesh {
+                    path,
+                    material: name,
+                    translation,
+                    rotation,
+                    scale,
+                } => {
+                    let path = base_dir.join(path);
+                    let extension = path
+                        .extension()
+                        .and_then(OsStr::to_str)
+                        .map(str::to_ascii_lowercase);
+                    let material = match name {
+                        Some(name) => Some(&material(name)?.material),
+                        None => None,
+                    };
+                    let file_material = || material.cloned().unwrap_or(DEFAULT_MATERIAL);
+                    let transform = Affine3A::from_scale_rotation_translation(
+                        (*scale).into(),
+                        rotation_from_degrees(*rotation),
+                        (*translation).into(),
+                    );
+
+                    let meshes = match extension.as_deref() {
+                        Some("gltf" | "glb") => {
+                            // glTF files keep their node hierarchy, so they add their own objects
+                            add_gltf_to_scene(&mut scene, &mut shading, &path, transform, material)
+                                .map_err(SceneError::Gltf)?;
+                            Vec::new()
+                        }
+                        Some("obj") => load_obj(&path)
+                            .map_err(SceneError::Obj)?
+                            .into_iter()
+                            .map(|mesh| match material {
+                                Some(material) => with_material(&mesh, material.clone()),
+                                None => mesh,
+                            })
+                            .collect(),
+                        Some("ply") => {
+                            vec![load_ply(&path, file_material()).map_err(SceneError::Ply)?]
+                        }
+                        Some("stl") => {
+                            vec![load_stl(&path, file_material()).map_err(SceneError::Stl)?]
+                        }
+                        _ => return Err(SceneError::UnsupportedMesh { path }),
+                    };
+
+                    for mesh in meshes {
+                        let albedo = mesh.colors().is_some().then(|| {
+                            Arc::new(VertexColorTexture {
+                                fallback: mesh.material().albedo,
+                            })
+                        });
+
+                        let object_index = if transform == Affine3A::IDENTITY {
+                            shading.add_object(&mut scene, mesh)
+                        } else if mesh.material().is_emissive
+                            && !Instance::scales_uniformly(transform)
+                        {
+                            // lights are sampled by area, which only instances scaled uniformly
+                            // preserve, so the transform is applied to the mesh
+                            shading.add_object(&mut scene, mesh.transformed(transform))
+                        } else {
+                            shading.add_object(&mut scene, Instance::new(Arc::new(mesh), transform))
+                        };
+
+                        if let Some(albedo) = albedo {
+                            let brdf = shading.default_shading().brdf.clone();
+
+                            shading.set(
+                                object_index,
+                                ObjectShading::new(brdf).with_textures(MaterialTextures {
+                                    albedo: Some(albedo),
+                                    ..MaterialTextures::default()
+                                }),
+                            );
+                        }
+                    }
+                }
+            }
+
+            if let Some(name) = object.material() {
+                let scene_material = material(name)?;
+
+                for object_index in first_index..scene.objects().len() {
+                    let current = shading.get(object_index);
+                    let brdf = scene_material
+                        .brdf
+                        .clone()
+                        .unwrap_or_else(|| current.brdf.clone());
+                    let textures = scene_material.textures.clone().or(current.textures.clone());
+                    let object_shading = ObjectShading::new(brdf).with_textures(textures);
+
+                    shading.set(
+                        object_index,
+                        match scene_material.subsurface {
+                            Some(subsurface) => object_shading.with_subsurface(subsurface),
+                            None => object_shading,
+                        },
+                    );
+                }
+            }
+        }
+
+        for light in &self.lights {
+            match light {
+                LightDescription::Point {
+                    position,
+                    intensity,
+                } => lights.add_delta_light(PointLight {
+                    position: *position,
+                    intensity: *intensity,
+                }),
+                LightDescription::Spot {
+                    position,
+                    direction,
+                    intensity,
+                    falloff_start,
+                    cone_angle,
+                } => lights.add_delta_light(SpotLight::new(
+                    *position,
+                    *direction,
+                    *intensity,
+                    *falloff_start,
+                    *cone_angle,
+                )),
+                LightDescription::Directional {
+                    direction,
+                    irradiance,
+                } => lights.add_delta_light(DirectionalLight {
+                    direction: direction.normalize(),
+                    irradiance: *irradiance,
+                }),
+            }
+        }
+
+        match &self.environment {
+            None => {}
+            Some(EnvironmentDescription::Map { path, intensity }) => {
+                let environment = EnvironmentMap::load(base_dir.join(path))
+                    .map_err(SceneError::Environment)?
+                    .with_intensity(*intensity);
+                lights = lights.with_environment(Arc::new(environment));
+            }
+            Some(EnvironmentDescription::Uniform { radiance }) => {
+                let image = HdrImage {
+                    width: 1,
+                    height: 1,
+                    pixels: vec![*radiance],
+                };
+                lights = lights.with_environment(Arc::new(EnvironmentMap::new(image)));
+            }
+            Some(EnvironmentDescription::Sky {
+                sun_elevation,
+                sun_azimuth,
+                turbidity,
+                intensity,
+            }) => {
+                let sun_sky = SunSky::new(SunSkyConfig {
+                    sun_direction: SunSkyConfig::sun_direction_from_angles(
+                        *sun_elevation,
+                        *sun_azimuth,
+                    ),
+                    turbidity: *turbidity,
+                    intensity: *intensity,
+                    sun_angular_radius: SUN_ANGULAR_RADIUS,
+                });
+                lights = lights.with_environment(Arc::new(sun_sky));
+            }
+        }
+
+        Ok(LoadedScene {
+            scene,
+            camera: self.camera.to_camera(),
+            shading,
+            lights,
+        })
+    }
+}
+
+/// A material of the scene, with everything its objects need for shading.
+struct SceneMaterial {
+    material: Material,
+    /// the BRDF chosen by the material, if any
+    brdf: Option<Arc<dyn Brdf>>,
+    textures: MaterialTextures,
+    subsurface: Option<RandomWalkSubsurface>,
+}
+
+impl MaterialTexturesDescription {
+    /// Loads the images and creates the textures, with image paths relative to `base_dir`.
+    fn build(&self, base_dir: &Path) -> Result<MaterialTextures, SceneError> {
+        let color = |texture: &Option<TextureDescription>| {
+            texture
+                .as_ref()
+                .map(|texture| texture.build(base_dir, ColorSpace::Srgb))
+                .transpose()
+        };
+        let scalar = |texture: &Option<ScalarTextureDescription>| {
+            texture
+                .as_ref()
+                .map(|texture| texture.build(base_dir))
+                .transpose()
+        };
+        let normal = match &self.normal {
+            None => None,
+            Some(NormalDescription::NormalMap { texture }) => Some(NormalPerturbation::NormalMap(
+                texture.build(base_dir, ColorSpace::Linear)?,
+            )),
+            Some(NormalDescription::Bump { height, scale }) => Some(NormalPerturbation::Bump {
+                height: height.build(base_dir)?,
+                scale: *scale,
+            }),
+        };
+
+        Ok(MaterialTextures {
+            albedo: color(&self.albedo)?,
+            emission: color(&self.emission)?,
+            roughness: scalar(&self.roughness)?,
+            metallic: scalar(&self.metallic)?,
+            normal,
+        })
+    }
+}
+
+impl ScalarTextureDescription {
+    fn build(&self, base_dir: &Path) -> Result<ScalarTexture, SceneError> {
+        let texture = self.texture.build(base_dir, ColorSpace::Linear)?;
+
+        Ok(ScalarTexture::new(texture, self.channel))
+    }
+}
+
+impl TextureDescription {
+    /// Creates the texture, reading images in `color_space`.
+    fn build(
+        &self,
+        base_dir: &Path,
+        color_space: ColorSpace,
+    ) -> Result<Arc<dyn Texture>, SceneError> {
+        let constant = |color: &Vec3A| Arc::new(ConstantTexture(*color));
+
+        Ok(match self {
+            Self::Image {
+                path,
+                wrap,
+                filter,
+                scale,
+            } => {
+                let wrap_mode = match wrap {
+                    WrapDescription::Repeat => WrapMode::Repeat,
+                    WrapDescription::MirroredRepeat => WrapMode::MirroredRepeat,
+                    WrapDescription::Clamp => WrapMode::Clamp,
+                };
+                let filter = match filter {
+                    FilterDescription::Nearest => Filter::Nearest,
+                    FilterDescription::Bilinear => Filter::Bilinear,
+                };
+                let image = ImageTexture::load(base_dir.join(path), color_space)
+                    .map_err(SceneError::Texture)?
+                    .with_wrap_mode(wrap_mode)
+                    .with_filter(filter)
+                    .with_scale(*scale);
+
+                Arc::new(image)
+            }
+            Self::Checker { mapping, even, odd } => Arc::new(CheckerTexture::new(
+                mapping.to_mapping(),
+                constant(even),
+                constant(odd),
+            )),
+            Self::Grid {
+                mapping,
+                line,
+                background,
+                line_width,
+            } => Arc::new(
+                GridTexture::new(mapping.to_mapping(), constant(line), constant(background))
+                    .with_line_width(*line_width),
+            ),
+            Self::Noise {
+                mapping,
+                low,
+                high,
+                turbulence,
+                octaves,
+            } => Arc::new(
+                NoiseTexture::new(mapping.to_mapping(), constant(low), constant(high))
+                    .with_kind(if *turbulence {
+                        NoiseKind::Turbulence
+                    } else {
+                        NoiseKind::Fbm
+                    })
+                    .with_octaves(Octaves {
+                        count: *octaves,
+                        ..Octaves::default()
+                    }),
+            ),
+            Self::Marble {
+                mapping,
+                base,
+                vein,
+                frequency,
+                distortion,
+            } => Arc::new(
+                MarbleTexture::new(mapping.to_mapping(), constant(base), constant(vein))
+                    .with_frequency(*frequency)
+                    .with_distortion(*distortion),
+            ),
+        })
+    }
+}
+
+impl MappingDescription {
+    fn to_mapping(self) -> TextureMapping {
+        match self {
+            Self::Uv { scale, offset } => TextureMapping::Uv { scale, offset },
+            Self::World { scale } => TextureMapping::Space {
+                transform: Affine3A::from_scale(Vec3::splat(scale)),
+            },
+        }
+    }
+}
+
+/// Returns a copy of the mesh with another material.
+fn with_material(mesh: &Mesh, material: Material) -> Mesh {
+    let copy = Mesh::new(
+        mesh.positions().to_vec(),
+        mesh.normals().map(<[_]>::to_vec),
+        mesh.triangles().to_vec(),
+        material,
+    );
+
+    with_vertex_attributes(copy, mesh)
+}
+
+/// Copies the UVs and colors of `source` onto a mesh with the same vertices.
+fn with_vertex_attributes(mesh: Mesh, source: &Mesh) -> Mesh {
+    let mesh = match source.uvs() {
+        Some(uvs) => mesh.with_uvs(uvs.to_vec()),
+        None => mesh,
+    };
+
+    match source.colors() {
+        Some(colors) => mesh.with_colors(colors.to_vec()),
+        None => mesh,
+    }
+}
@@ -0,0 +1,499 @@
+use glam::{EulerRot, Quat, Vec2, Vec3A};
+use raytracer_core::{camera::Camera, material::Material};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::PathBuf};
+
+/// A scene as written in a scene file.
+///
+/// Objects refer to materials by name, so that one material can be shared by many objects.
+/// Relative paths are resolved against the directory of the scene file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneDescription {
+    pub name: String,
+    pub camera: CameraDescription,
+    #[serde(default)]
+    pub render: RenderSettings,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<EnvironmentDescription>,
+    #[serde(default)]
+    pub materials: BTreeMap<String, MaterialDescription>,
+    #[serde(default)]
+    pub objects: Vec<ObjectDescription>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lights: Vec<LightDescription>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraDescription {
+    pub position: Vec3A,
+    pub target: Vec3A,
+    #[serde(default = "default_up")]
+    pub up: Vec3A,
+    /// vertical field of view, in degrees
+    pub fov: f32,
+}
+
+impl CameraDescription {
+    pub fn to_camera(&self) -> Camera {
+        Camera::look_at(self.position, self.target, self.up, self.fov)
+    }
+}
+
+fn default_up() -> Vec3A {
+    Vec3A::Y
+}
+
+/// Render settings stored with a scene, which command line options take precedence over.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RenderSettings {
+    pub width: u32,
+    pub height: u32,
+    pub samples: u32,
+    pub max_bounces: u32,
+    pub exposure: f32,
+    pub gamma: f32,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            width: 512,
+            height: 512,
+            samples: 64,
+            max_bounces: 8,
+            exposure: 1.0,
+            gamma: 2.2,
+        }
+    }
+}
+
+/// The parameters of a [`Material`], along with how it scatters light.
+///
+/// A material is emissive whenever its emission is not black.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MaterialDescription {
+    /// the BRDF of the objects using this material, or the default one of the renderer if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub brdf: Option<BrdfKind>,
+    /// index of refraction, only used by the dielectric BRDF
+    pub ior: f32,
+    /// real part of the complex index of refraction `eta + i k`, only used by the conductor BRDF
+    pub eta: Vec3A,
+    /// imaginary part of the complex index of refraction, only used by the conductor BRDF
+    pub k: Vec3A,
+    pub emission: Vec3A,
+    pub albedo: Vec3A,
+    pub subsurface: f32,
+    pub metallic: f32,
+    pub specular: f32,
+    pub specular_tint: Vec3A,
+    pub roughness: f32,
+    pub anisotropic: f32,
+    pub sheen: f32,
+    pub sheen_tint: Vec3A,
+    pub clearcoat: f32,
+    pub clearcoat_gloss: f32,
+    /// textures replacing some of the constant parameters above
+    #[serde(skip_serializing_if = "MaterialTexturesDescription::is_empty")]
+    pub textures: MaterialTexturesDescription,
+    /// average distance light travels inside the object between scattering events, for each
+    /// color channel; when set, a random walk through the object replaces the `subsurface`
+    /// fraction of the diffuse lobe, which needs a closed object
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scattering_distance: Option<Vec3A>,
+}
+
+impl MaterialDescription {
+    pub fn from_material(material: &Material) -> Self {
+        Self {
+            emission: if material.is_emissive {
+                material.emission
+            } else {
+                Vec3A::ZERO
+            },
+            albedo: material.albedo,
+            subsurface: material.subsurface,
+            metallic: material.metallic,
+            specular: material.specular,
+            specular_tint: material.specular_tint,
+            roughness: material.roughness,
+            anisotropic: material.anisotropic,
+            sheen: material.sheen,
+            sheen_tint: material.sheen_tint,
+            clearcoat: material.clearcoat,
+            clearcoat_gloss: material.clearcoat_gloss,
+            ..Self::default()
+        }
+    }
+
+    pub fn to_material(&self) -> Material {
+        Material {
+            is_emissive: self.emission != Vec3A::ZERO,
+            emission: self.emission,
+            albedo: self.albedo,
+            subsurface: self.subsurface,
+            metallic: self.metallic,
+            specular: self.specular,
+            specular_tint: self.specular_tint,
+            roughness: self.roughness,
+            anisotropic: self.anisotropic,
+            sheen: self.sheen,
+            sheen_tint: self.sheen_tint,
+            clearcoat: self.clearcoat,
+            clearcoat_gloss: self.clearcoat_gloss,
+        }
+    }
+}
+
+impl Default for MaterialDescription {
+    /// A rough, light gray non-metal, like the material given to imported geometry.
+    fn default() -> Self {
+        Self {
+            brdf: None,
+            ior: 1.5,
+            // aluminium
+            eta: Vec3A::new(1.657, 0.880, 0.521),
+            k: Vec3A::new(9.224, 6.270, 4.837),
+            emission: Vec3A::ZERO,
+            albedo: Vec3A::splat(0.8),
+            subsurface: 0.0,
+            metallic: 0.0,
+            specular: 0.5,
+            specular_tint: Vec3A::ZERO,
+            roughness: 0.5,
+            anisotropic: 0.0,
+            sheen: 0.0,
+            sheen_tint: Vec3A::ZERO,
+            clearcoat: 0.0,
+            clearcoat_gloss: 0.0,
+            textures: MaterialTexturesDescription::default(),
+            scattering_distance: None,
+        }
+    }
+}
+
+/// Textures of a material, each replacing the constant parameter of the same name.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MaterialTexturesDescription {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub albedo: Option<TextureDescription>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emission: Option<TextureDescription>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub roughness: Option<ScalarTextureDescription>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metallic: Option<ScalarTextureDescription>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub normal: Option<NormalDescription>,
+}
+
+impl MaterialTexturesDescription {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// A color that varies over a surface, tagged by `type`.
+///
+/// Image paths are relative to the scene file. Images given as albedo or emission are read as
+/// sRGB colors, and the others as linear data.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum TextureDescription {
+    /// a `.png` or Radiance `.hdr` image, with `v` pointing up the image
+    Image {
+        path: PathBuf,
+        #[serde(default)]
+        wrap: WrapDescription,
+        #[serde(default)]
+        filter: FilterDescription,
+        /// multiplies every texel
+        #[serde(default = "default_intensity")]
+        scale: f32,
+    },
+    Checker {
+        #[serde(default)]
+        mapping: MappingDescription,
+        even: Vec3A,
+        odd: Vec3A,
+    },
+    Grid {
+        #[serde(default)]
+        mapping: MappingDescription,
+        line: Vec3A,
+        background: Vec3A,
+        /// width of the lines, as a fraction of a cell
+        #[serde(default = "default_line_width")]
+        line_width: f32,
+    },
+    /// a blend between `low` and `high` driven by Perlin noise
+    Noise {
+        #[serde(default)]
+        mapping: MappingDescription,
+        low: Vec3A,
+        high: Vec3A,
+        /// sums the absolute values of the octaves, for sharp creases instead of soft clouds
+        #[serde(default)]
+        turbulence: bool,
+        #[serde(default = "default_octaves")]
+        octaves: u32,
+    },
+    /// veins of `vein` running through `base`
+    Marble {
+        #[serde(default)]
+        mapping: MappingDescription,
+        base: Vec3A,
+        vein: Vec3A,
+        /// number of veins per unit of pattern space
+        #[serde(default = "default_intensity")]
+        frequency: f32,
+        /// how much the noise bends the veins
+        #[serde(default = "default_distortion")]
+        distortion: f32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WrapDescription {
+    #[default]
+    Repeat,
+    MirroredRepeat,
+    Clamp,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterDescription {
+    Nearest,
+    #[default]
+    Bilinear,
+}
+
+/// How a procedural texture places its pattern on a surface, tagged by `type`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum MappingDescription {
+    /// the pattern follows the surface parameterization
+    Uv {
+        #[serde(default = "default_uv_scale")]
+        scale: Vec2,
+        #[serde(default)]
+        offset: Vec2,
+    },
+    /// the pattern is fixed in world space, with `scale` pattern units per world unit
+    World {
+        #[serde(default = "default_intensity")]
+        scale: f32,
+    },
+}
+
+impl Default for MappingDescription {
+    fn default() -> Self {
+        Self::Uv {
+            scale: default_uv_scale(),
+            offset: Vec2::ZERO,
+        }
+    }
+}
+
+/// A texture driving a scalar parameter, read from one of its channels.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScalarTextureDescription {
+    pub texture: TextureDescription,
+    /// 0 for red, 1 for green and 2 for blue
+    #[serde(default)]
+    pub channel: usize,
+}
+
+/// A perturbation of the shading normal, tagged by `type`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum NormalDescription {
+    /// a tangent space normal map, `+Y` following `v` as in OpenGL and glTF
+    NormalMap { texture: TextureDescription },
+    /// a height field, offsetting the surface along its normal by the texture times `scale`
+    Bump {
+        height: ScalarTextureDescription,
+        scale: f32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BrdfKind {
+    Disney,
+    Lambertian,
+    Dielectric,
+    Conductor,
+}
+
+/// An object of the scene, tagged by `type`.
+///
+/// `material` names an entry of [`SceneDescription::materials`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ObjectDescription {
+    Box {
+        center: Vec3A,
+        size: Vec3A,
+        /// Euler angles in degrees, applied around `X` first, then `Y`, then `Z`
+        #[serde(default)]
+        rotation: Vec3A,
+        material: String,
+    },
+    Plain {
+        center: Vec3A,
+        /// the side the plain faces, which is the only side it emits from
+        normal: Vec3A,
+        size: Vec2,
+        material: String,
+    },
+    Sphere {
+        center: Vec3A,
+        radius: f32,
+        material: String,
+    },
+    /// a triangle mesh written out in the scene file, wound counter-clockwise
+    TriangleMesh {
+        positions: Vec<Vec3A>,
+        /// one per position
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        normals: Option<Vec<Vec3A>>,
+        /// one per position
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        uvs: Option<Vec<Vec2>>,
+        triangles: Vec<[u32; 3]>,
+        material: String,
+    },
+    /// triangle meshes loaded from a Wavefront OBJ, PLY, STL or glTF (`.gltf` or `.glb`) file;
+    /// glTF files keep their node hierarchy, placed by the transform below, and their cameras are
+    /// ignored
+    Mesh {
+        path: PathBuf,
+        /// replaces the materials of the file when set;
+        /// PLY and STL files have none, and get a default material otherwise
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        material: Option<String>,
+        #[serde(default)]
+        translation: Vec3A,
+        /// Euler angles in degrees, applied around `X` first, then `Y`, then `Z`
+        #[serde(default)]
+        rotation: Vec3A,
+        #[serde(default = "default_scale")]
+        scale: Vec3A,
+    },
+}
+
+impl ObjectDescription {
+    /// Returns the name of the material of the object, if it sets one.
+    pub fn material(&self) -> Option<&str> {
+        match self {
+            Self::Box { material, .. }
+            | Self::Plain { material, .. }
+            | Self::Sphere { material, .. }
+            | Self::TriangleMesh { material, .. } => Some(material),
+            Self::Mesh { material, .. } => material.as_deref(),
+        }
+    }
+}
+
+fn default_scale() -> Vec3A {
+    Vec3A::ONE
+}
+
+/// Converts Euler angles in degrees, as written in scene files, into a rotation.
+pub fn rotation_from_degrees(rotation: Vec3A) -> Quat {
+    Quat::from_euler(
+        EulerRot::ZYX,
+        rotation.z.to_radians(),
+        rotation.y.to_radians(),
+        rotation.x.to_radians(),
+    )
+}
+
+/// Converts a rotation into Euler angles in degrees, as written in scene files.
+pub fn degrees_from_rotation(rotation: Quat) -> Vec3A {
+    let (z, y, x) = rotation.to_euler(EulerRot::ZYX);
+    Vec3A::new(x.to_degrees(), y.to_degrees(), z.to_degrees())
+}
+
+/// A light that is not an object of the scene, tagged by `type`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum LightDescription {
+    Point {
+        position: Vec3A,
+        intensity: Vec3A,
+    },
+    Spot {
+        position: Vec3A,
+        direction: Vec3A,
+        intensity: Vec3A,
+        /// angle from the axis where the light starts to fade out, in degrees
+        falloff_start: f32,
+        /// angle from the axis beyond which there is no light, in degrees
+        cone_angle: f32,
+    },
+    Directional {
+        /// direction the light travels in
+        direction: Vec3A,
+        irradiance: Vec3A,
+    },
+}
+
+/// What lights the scene from infinitely far away, tagged by `type`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum EnvironmentDescription {
+    /// an equirectangular Radiance `.hdr` image
+    Map {
+        path: PathBuf,
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+    },
+    /// the same radiance from every direction
+    Uniform { radiance: Vec3A },
+    /// a procedural daylight sky and sun
+    Sky {
+        /// angle of the sun above the horizon, in degrees
+        sun_elevation: f32,
+        /// angle of the sun clockwise from `-Z` towards `+X`, in degrees
+        #[serde(default)]
+        sun_azimuth: f32,
+        #[serde(default = "default_turbidity")]
+        turbidity: f32,
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+    },
+}
+
+fn default_intensity() -> f32 {
+    1.0
+}
+
+fn default_turbidity() -> f32 {
+    3.0
+}
+
+fn default_line_width() -> f32 {
+    0.05
+}
+
+fn default_octaves() -> u32 {
+    6
+}
+
+fn default_distortion() -> f32 {
+    1.5
+}
+
+fn default_uv_scale() -> Vec2 {
+    Vec2::ONE
+}
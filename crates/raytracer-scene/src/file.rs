@@ -0,0 +1,345 @@
+use crate::description::{
+    CameraDescription, MaterialDescription, ObjectDescription, RenderSettings, SceneDescription,
+};
+use raytracer_cpu_renderer::{hdr::HdrError, textures::image::TextureError};
+use raytracer_importers::{gltf::GltfError, obj::ObjError, ply::PlyError, stl::StlError};
+use serde::{Deserialize, de::DeserializeOwned};
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+use toml::Spanned;
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// an object refers to a material that the scene does not define
+    UndefinedMaterial {
+        name: String,
+    },
+    UnsupportedMesh {
+        path: PathBuf,
+    },
+    InvalidMesh {
+        message: String,
+    },
+    Obj(ObjError),
+    Ply(PlyError),
+    Stl(StlError),
+    Gltf(GltfError),
+    Environment(HdrError),
+    Texture(TextureError),
+    Serialize(toml::ser::Error),
+}
+
+impl SceneError {
+    /// Locates a byte range of the source, for errors that the TOML parser did not report.
+    fn parse(path: &Path, source: &str, span: Option<Range<usize>>, message: String) -> Self {
+        let offset = span.map_or(0, |span| span.start).min(source.len());
+        let before = &source[..offset];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+        let column = before[line_start..].chars().count() + 1;
+
+        Self::Parse {
+            path: path.to_owned(),
+            line,
+            column,
+            message,
+        }
+    }
+}
+
+impl Display for SceneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io { path, source } => {
+                write!(f, "failed to read `{}`: {}", path.display(), source)
+            }
+            Self::Parse {
+                path,
+                line,
+                column,
+                message,
+            } => write!(f, "{}:{}:{}: {}", path.display(), line, column, message),
+            Self::UndefinedMaterial { name } => write!(f, "undefined material `{name}`"),
+            Self::UnsupportedMesh { path } => {
+                write!(
+                    f,
+                    "`{}` is not a supported mesh, expected a `.obj`, `.ply`, `.stl`, `.gltf` or `.glb` file",
+                    path.display()
+                )
+            }
+            Self::InvalidMesh { message } => write!(f, "invalid triangle mesh: {message}"),
+            Self::Obj(error) => error.fmt(f),
+            Self::Ply(error) => error.fmt(f),
+            Self::Stl(error) => error.fmt(f),
+            Self::Gltf(error) => error.fmt(f),
+            Self::Environment(error) => error.fmt(f),
+            Self::Texture(error) => error.fmt(f),
+            Self::Serialize(error) => write!(f, "failed to serialize the scene: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Obj(error) => Some(error),
+            Self::Ply(error) => Some(error),
+            Self::Stl(error) => Some(error),
+            Self::Gltf(error) => Some(error),
+            Self::Environment(error) => Some(error),
+            Self::Texture(error) => Some(error),
+            Self::Serialize(error) => Some(error),
+            Self::Parse { .. }
+            | Self::UndefinedMaterial { .. }
+            | Self::UnsupportedMesh { .. }
+            | Self::InvalidMesh { .. } => None,
+        }
+    }
+}
+
+impl SceneDescription {
+    /// Loads a TOML scene file.
+    ///
+    /// Invalid files, and objects referring to undefined materials, are rejected with the line
+    /// they occur on.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(|source| SceneError::Io {
+            path: path.to_owned(),
+            source,
+        })?;
+
+        Self::parse(&source, path)
+    }
+
+    /// Writes the scene to a TOML scene file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SceneError> {
+        let path = path.as_ref();
+
+        fs::write(path, self.to_toml()?).map_err(|source| SceneError::Io {
+            path: path.to_owned(),
+            source,
+        })
+    }
+
+    /// Returns the contents of a TOML scene file describing the scene.
+    pub fn to_toml(&self) -> Result<String, SceneError> {
+        let mut value = toml::Value::try_from(self).map_err(SceneError::Serialize)?;
+
+        // TOML only has 64-bit floats, so write the shortest number that reads back as the same
+        // `f32` rather than its exact value, such as `0.1` instead of `0.10000000149011612`
+        shorten_floats(&mut value);
+
+        toml::to_string(&value).map_err(SceneError::Serialize)
+    }
+
+    /// Parses the contents of a TOML scene file.
+    ///
+    /// `path` is only used to report errors.
+    pub fn parse(source: &str, path: &Path) -> Result<Self, SceneError> {
+        let error_at = |span: Option<Range<usize>>, message: String| {
+            SceneError::parse(path, source, span, message)
+        };
+
+        let file: SceneFile = toml::from_str(source)
+            .map_err(|error| error_at(error.span(), error.message().to_owned()))?;
+
+        let mut objects = Vec::with_capacity(file.objects.len());
+
+        for value in file.objects {
+            let span = match value.get_ref().get_key_value("material") {
+                Some((_, material)) => material.span(),
+                None => value.span(),
+            };
+            let object: ObjectDescription = deserialize_tagged(value, path, source)?;
+
+            if let Some(material) = object.material()
+                && !file.materials.contains_key(material)
+            {
+                return Err(error_at(
+                    Some(span),
+                    format!("undefined material `{material}`"),
+                ));
+            }
+
+            objects.push(object);
+        }
+
+        Ok(Self {
+            name: file.name,
+            camera: file.camera,
+            render: file.render,
+            environment: file
+                .environment
+                .map(|value| deserialize_tagged(value, path, source))
+                .transpose()?,
+            materials: file.materials,
+            objects,
+            lights: file
+                .lights
+                .into_iter()
+                .map(|value| deserialize_tagged(value, path, source))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+/// The layout of [`SceneDescription`], with its tagged tables left to be deserialized later.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneFile {
+    name: String,
+    camera: CameraDescription,
+    #[serde(default)]
+    render: RenderSettings,
+    environment: Option<TaggedTable>,
+    #[serde(default)]
+    materials: BTreeMap<String, MaterialDescription>,
+    #[serde(default)]
+    objects: Vec<TaggedTable>,
+    #[serde(default)]
+    lights: Vec<TaggedTable>,
+}
+
+/// A table tagged by `type`, with the location of each of its keys and values.
+type TaggedTable = Spanned<BTreeMap<Spanned<String>, Spanned<toml::Value>>>;
+
+/// Deserializes a table tagged by `type`.
+///
+/// Tagged tables are buffered before being deserialized, which loses the position of errors
+/// inside them. The key an error is about is found again by deserializing the keys one at a time,
+/// since the culprit fails the same way on its own; errors that are not about a single key, such
+/// as missing fields, are reported at the table.
+fn deserialize_tagged<T: DeserializeOwned>(
+    value: TaggedTable,
+    path: &Path,
+    source: &str,
+) -> Result<T, SceneError> {
+    let span = value.span();
+    let table = value.into_inner();
+    let deserialize = |keys: &mut dyn Iterator<Item = &Spanned<String>>| {
+        let table: toml::Table = keys
+            .map(|key| (key.get_ref().clone(), table[key].get_ref().clone()))
+            .collect();
+
+        T::deserialize(toml::Value::Table(table))
+    };
+
+    deserialize(&mut table.keys()).map_err(|error: toml::de::Error| {
+        let message = error.message();
+        let is_tag = |key: &&Spanned<String>| key.get_ref() == "type";
+        // the tag is tried first, since every other key fails with it when it is wrong
+        let mut candidates = table
+            .keys()
+            .filter(is_tag)
+            .chain(table.keys().filter(|key| !is_tag(key)))
+            .filter(|_| !message.starts_with("missing field"));
+        let culprit = candidates.find(|key| {
+            let mut keys = table.keys().filter(|other| is_tag(other) || other == key);
+
+            deserialize(&mut keys).is_err_and(|other| other.message() == message)
+        });
+        let span = culprit.map_or(span, |key| key.span());
+
+        SceneError::parse(path, source, Some(span), message.to_owned())
+    })
+}
+
+fn shorten_floats(value: &mut toml::Value) {
+    match value {
+        toml::Value::Float(float) => {
+            *float = (*float as f32).to_string().parse().unwrap_or(*float);
+        }
+        toml::Value::Array(array) => array.iter_mut().for_each(shorten_floats),
+        toml::Value::Table(table) => table
+            .iter_mut()
+            .for_each(|(_, value)| shorten_floats(value)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENE: &str = r#"name = "test"
+
+[camera]
+position = [0.0, 0.0, 3.0]
+target = [0.0, 0.0, 0.0]
+fov = 40.0
+
+[[objects]]
+type = "box"
+center = [0.0, 0.0, 0.0]
+size = [1.0, 1.0, 1.0]
+material = "white"
+
+[materials.white]
+albedo = [0.8, 0.8, 0.8]
+"#;
+
+    fn error_location(source: &str) -> (usize, usize) {
+        match SceneDescription::parse(source, Path::new("test.toml")) {
+            Err(SceneError::Parse { line, column, .. }) => (line, column),
+            other => panic!("expected a parse error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn errors_in_tagged_tables_point_at_their_key() {
+        let invalid_value = SCENE.replace("size = [1.0, 1.0, 1.0]", "size = [1.0, 1.0]");
+        assert_eq!(error_location(&invalid_value), (11, 1));
+
+        let unknown_key = SCENE.replace("size = [1.0, 1.0, 1.0]", "radius = 1.0");
+        assert_eq!(error_location(&unknown_key), (11, 1));
+
+        let unknown_type = SCENE.replace("\"box\"", "\"cube\"");
+        assert_eq!(error_location(&unknown_type), (9, 1));
+
+        let undefined_material = SCENE.replace("material = \"white\"", "material = \"black\"");
+        assert_eq!(error_location(&undefined_material), (12, 12));
+    }
+
+    #[test]
+    fn missing_keys_point_at_their_table() {
+        let missing_key = SCENE.replace("size = [1.0, 1.0, 1.0]\n", "");
+        assert_eq!(error_location(&missing_key), (8, 1));
+    }
+
+    #[test]
+    fn textured_materials_survive_a_round_trip() {
+        let textured = format!(
+            "{SCENE}{}",
+            r#"scattering_distance = [0.1, 0.05, 0.02]
+
+[materials.white.textures]
+albedo = { type = "checker", even = [1.0, 1.0, 1.0], odd = [0.0, 0.0, 0.0], mapping = { type = "world", scale = 4.0 } }
+roughness = { texture = { type = "noise", low = [0.2, 0.2, 0.2], high = [0.8, 0.8, 0.8] }, channel = 1 }
+normal = { type = "bump", height = { texture = { type = "marble", base = [0.0, 0.0, 0.0], vein = [1.0, 1.0, 1.0] } }, scale = 0.01 }
+"#
+        );
+        let scene = SceneDescription::parse(&textured, Path::new("test.toml")).unwrap();
+        let reparsed =
+            SceneDescription::parse(&scene.to_toml().unwrap(), Path::new("test.toml")).unwrap();
+
+        assert!(!scene.materials["white"].textures.is_empty());
+        assert_eq!(reparsed.materials, scene.materials);
+    }
+}
@@ -0,0 +1,66 @@
+use crate::{
+    description::{
+        CameraDescription, EnvironmentDescription, ObjectDescription, RenderSettings,
+        SceneDescription,
+    },
+    import::ImportedScene,
+};
+use glam::Vec3A;
+use raytracer_importers::gltf::{GltfError, load_gltf_cameras};
+use std::{collections::BTreeMap, path::Path};
+
+/// Imports a `.gltf` or `.glb` file as a scene made of a single `mesh` object referencing it.
+///
+/// The scene is seen from the first perspective camera of the file. glTF files rarely carry
+/// lights, so the scene is lit by a uniform white environment.
+pub fn import_gltf(path: impl AsRef<Path>) -> Result<ImportedScene, GltfError> {
+    let path = path.as_ref();
+    let mut warnings = Vec::new();
+
+    let camera = match load_gltf_cameras(path)?.first() {
+        Some(camera) => CameraDescription {
+            position: camera.position,
+            target: camera.position + camera.direction,
+            up: camera.up,
+            fov: camera.fov,
+        },
+        None => {
+            warnings.push(
+                "the file has no perspective camera, looking at the origin from `+Z`".to_owned(),
+            );
+            CameraDescription {
+                position: Vec3A::new(0.0, 0.0, 5.0),
+                target: Vec3A::ZERO,
+                up: Vec3A::Y,
+                fov: 40.0,
+            }
+        }
+    };
+
+    let name = path
+        .file_stem()
+        .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+    // scene files resolve paths against their own directory, which is the one of the glTF file
+    let file_name = path.file_name().map(Path::new).unwrap_or(path);
+
+    Ok(ImportedScene {
+        description: SceneDescription {
+            name,
+            camera,
+            render: RenderSettings::default(),
+            environment: Some(EnvironmentDescription::Uniform {
+                radiance: Vec3A::ONE,
+            }),
+            materials: BTreeMap::new(),
+            objects: vec![ObjectDescription::Mesh {
+                path: file_name.to_owned(),
+                material: None,
+                translation: Vec3A::ZERO,
+                rotation: Vec3A::ZERO,
+                scale: Vec3A::ONE,
+            }],
+            lights: Vec::new(),
+        },
+        warnings,
+    })
+}
@@ -0,0 +1,125 @@
+//! What the importers of other scene formats have in common.
+
+use crate::description::{BrdfKind, MaterialDescription, SceneDescription};
+use glam::Vec3A;
+use std::collections::BTreeMap;
+
+/// A scene imported from another format, along with what could not be imported.
+#[derive(Debug, Clone)]
+pub struct ImportedScene {
+    pub description: SceneDescription,
+    /// elements, parameters and values that were ignored or approximated, with their location
+    pub warnings: Vec<String>,
+}
+
+/// The complex index of refraction of copper, given to conductors made of an unknown metal.
+pub(crate) const COPPER: (Vec3A, Vec3A) = (
+    Vec3A::new(0.200, 0.924, 1.102),
+    Vec3A::new(3.912, 2.453, 2.142),
+);
+
+/// Returns the complex index of refraction `eta + i k` of a metal given by its chemical symbol.
+pub(crate) fn metal_ior(symbol: &str) -> Option<(Vec3A, Vec3A)> {
+    let (eta, k) = match symbol {
+        "Ag" => ([0.155, 0.117, 0.138], [4.828, 3.122, 2.147]),
+        "Al" => ([1.657, 0.880, 0.521], [9.224, 6.270, 4.837]),
+        "Au" => ([0.143, 0.375, 1.443], [3.983, 2.386, 1.603]),
+        "Cr" => ([3.107, 3.181, 2.323], [3.331, 3.329, 3.135]),
+        "Cu" => return Some(COPPER),
+        "CuZn" => ([0.444, 0.527, 1.094], [3.695, 2.765, 1.829]),
+        "Fe" => ([2.911, 2.950, 2.585], [3.089, 2.932, 2.767]),
+        "Ti" => ([2.741, 2.542, 2.267], [3.814, 3.435, 3.039]),
+        _ => return None,
+    };
+
+    Some((Vec3A::from_array(eta), Vec3A::from_array(k)))
+}
+
+/// Returns a complex index of refraction whose reflectance at normal incidence is `reflectance`,
+/// for conductors given by their color rather than by their metal.
+pub(crate) fn ior_from_reflectance(reflectance: Vec3A) -> (Vec3A, Vec3A) {
+    let reflectance = reflectance.clamp(Vec3A::ZERO, Vec3A::ONE);
+    let k =
+        2.0 * reflectance.powf(0.5) / (Vec3A::ONE - reflectance).max(Vec3A::splat(1e-4)).powf(0.5);
+
+    (Vec3A::ONE, k)
+}
+
+pub(crate) fn conductor((eta, k): (Vec3A, Vec3A), roughness: f32) -> MaterialDescription {
+    MaterialDescription {
+        brdf: Some(BrdfKind::Conductor),
+        eta,
+        k,
+        albedo: Vec3A::ONE,
+        metallic: 1.0,
+        roughness,
+        ..MaterialDescription::default()
+    }
+}
+
+pub(crate) fn diffuse(albedo: Vec3A) -> MaterialDescription {
+    MaterialDescription {
+        brdf: Some(BrdfKind::Lambertian),
+        albedo,
+        specular: 0.0,
+        roughness: 1.0,
+        ..MaterialDescription::default()
+    }
+}
+
+/// Returns the `specular` parameter giving the reflectance at normal incidence of an interface
+/// with the index of refraction `ior`, which is 0.08 times `specular`.
+pub(crate) fn specular_from_ior(ior: f32) -> f32 {
+    let reflectance = ((ior - 1.0) / (ior + 1.0)).powi(2);
+    (reflectance / 0.08).min(1.0)
+}
+
+/// Adds a material to the scene unless an identical one was already added, and returns its name.
+pub(crate) fn intern_material(
+    materials: &mut BTreeMap<String, MaterialDescription>,
+    name: String,
+    material: MaterialDescription,
+) -> String {
+    if let Some((existing, _)) = materials.iter().find(|(_, other)| **other == material) {
+        return existing.clone();
+    }
+
+    let name = (1..)
+        .map(|index| match index {
+            1 => name.clone(),
+            _ => format!("{name}_{index}"),
+        })
+        .find(|name| !materials.contains_key(name))
+        .unwrap_or(name);
+
+    materials.insert(name.clone(), material);
+    name
+}
+
+/// Returns whether most of the solid angle that a mesh covers as seen from `viewpoint` is covered
+/// by the back of its triangles.
+///
+/// This is the case for open surfaces facing away, and for closed meshes seen from inside, but
+/// not for closed meshes seen from outside, whose front and back cover the same solid angle.
+pub(crate) fn faces_away_from(
+    positions: &[Vec3A],
+    triangles: &[[u32; 3]],
+    viewpoint: Vec3A,
+) -> bool {
+    let (signed, total) = triangles
+        .iter()
+        .map(|triangle| {
+            let [p0, p1, p2] = triangle.map(|index| positions[index as usize]);
+            let area_normal = (p1 - p0).cross(p2 - p0) / 2.0;
+            let offset = (p0 + p1 + p2) / 3.0 - viewpoint;
+            let distance = offset.length().max(1e-6);
+
+            // the approximate solid angle of the triangle, positive when seen from behind
+            offset.dot(area_normal) / distance.powi(3)
+        })
+        .fold((0.0, 0.0), |(signed, total), solid_angle: f32| {
+            (signed + solid_angle, total + solid_angle.abs())
+        });
+
+    signed > total / 2.0
+}
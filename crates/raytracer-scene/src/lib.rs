@@ -0,0 +1,12 @@
+mod build;
+mod description;
+mod file;
+pub mod gltf;
+mod import;
+pub mod mitsuba;
+pub mod pbrt;
+
+pub use build::*;
+pub use description::*;
+pub use file::*;
+pub use import::ImportedScene;
@@ -0,0 +1,1447 @@
+use crate::{
+    description::{
+        BrdfKind, CameraDescription, EnvironmentDescription, LightDescription, MaterialDescription,
+        ObjectDescription, RenderSettings, SceneDescription, degrees_from_rotation,
+    },
+    import::{
+        COPPER, ImportedScene, conductor, diffuse, faces_away_from, intern_material,
+        ior_from_reflectance, metal_ior, specular_from_ior,
+    },
+};
+use glam::{Affine3A, Mat4, Vec2, Vec3, Vec3A};
+use raytracer_primitives::Plain;
+use roxmltree::{Document, Node};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug)]
+pub enum MitsubaError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+impl Display for MitsubaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io { path, source } => {
+                write!(f, "failed to read `{}`: {}", path.display(), source)
+            }
+            Self::Parse {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", path.display(), line, message),
+        }
+    }
+}
+
+impl std::error::Error for MitsubaError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Parse { .. } => None,
+        }
+    }
+}
+
+/// Imports a Mitsuba 3 XML scene file.
+///
+/// Perspective sensors with their film and sampler, rectangle, cube, sphere, OBJ and PLY shapes,
+/// area, point, spot, directional, constant and environment map emitters, and the diffuse,
+/// conductor, dielectric, plastic and principled BSDFs with constant values are imported.
+/// Other plugins and properties are skipped or replaced by a default, and reported as warnings.
+pub fn import_mitsuba(path: impl AsRef<Path>) -> Result<ImportedScene, MitsubaError> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path).map_err(|source| MitsubaError::Io {
+        path: path.to_owned(),
+        source,
+    })?;
+    let document = Document::parse(&source).map_err(|error| MitsubaError::Parse {
+        path: path.to_owned(),
+        line: error.pos().row as usize,
+        message: error.to_string(),
+    })?;
+
+    let root = document.root_element();
+    let defaults = root
+        .children()
+        .filter(|node| node.has_tag_name("default"))
+        .filter_map(|node| Some((node.attribute("name")?, node.attribute("value")?)))
+        .map(|(name, value)| (name.to_owned(), value.to_owned()))
+        .collect();
+    let importer = Importer {
+        path,
+        document: &document,
+        defaults,
+        warnings: RefCell::new(Vec::new()),
+    };
+
+    if !root.has_tag_name("scene") {
+        return Err(importer.error(root, "expected a `scene` element"));
+    }
+
+    let name = path
+        .file_stem()
+        .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+    let description = importer.scene(root, name)?;
+
+    Ok(ImportedScene {
+        description,
+        warnings: importer.warnings.into_inner(),
+    })
+}
+
+/// A BSDF mapped onto a material.
+#[derive(Debug, Clone)]
+struct Bsdf {
+    /// the name the material is stored under if no other material took it
+    name: String,
+    material: MaterialDescription,
+    /// whether both sides of the surface scatter light, rather than only its front
+    two_sided: bool,
+}
+
+impl Default for Bsdf {
+    fn default() -> Self {
+        Self {
+            name: "material".to_owned(),
+            material: diffuse(Vec3A::splat(0.5)),
+            two_sided: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum ShapeKind {
+    Sphere {
+        center: Vec3A,
+        radius: f32,
+    },
+    /// the square from `(-1, -1, 0)` to `(1, 1, 0)`, facing `+Z`
+    Rectangle,
+    /// the cube from `(-1, -1, -1)` to `(1, 1, 1)`
+    Cube,
+    Mesh {
+        path: PathBuf,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Shape {
+    kind: ShapeKind,
+    transform: Mat4,
+    bsdf: Bsdf,
+    emission: Option<Vec3A>,
+    location: String,
+}
+
+#[derive(Debug, Clone)]
+struct Sensor {
+    transform: Mat4,
+    fov: f32,
+    fov_axis: String,
+}
+
+struct Importer<'a, 'input> {
+    path: &'a Path,
+    document: &'a Document<'input>,
+    /// the values of the `$name` parameters, set by `default` elements
+    defaults: HashMap<String, String>,
+    warnings: RefCell<Vec<String>>,
+}
+
+impl<'a, 'input> Importer<'a, 'input> {
+    fn line(&self, node: Node) -> usize {
+        self.document.text_pos_at(node.range().start).row as usize
+    }
+
+    fn location(&self, node: Node) -> String {
+        format!("{}:{}", self.path.display(), self.line(node))
+    }
+
+    fn warn(&self, node: Node, message: impl Display) {
+        let warning = format!("{}: {}", self.location(node), message);
+        self.warnings.borrow_mut().push(warning);
+    }
+
+    fn error(&self, node: Node, message: impl Into<String>) -> MitsubaError {
+        MitsubaError::Parse {
+            path: self.path.to_owned(),
+            line: self.line(node),
+            message: message.into(),
+        }
+    }
+
+    /// Returns an attribute, with the `$name` parameters it contains replaced by their value.
+    fn attribute(&self, node: Node, name: &str) -> Result<Option<String>, MitsubaError> {
+        let Some(text) = node.attribute(name) else {
+            return Ok(None);
+        };
+
+        let mut value = String::with_capacity(text.len());
+        let mut rest = text;
+
+        while let Some(start) = rest.find('$') {
+            value.push_str(&rest[..start]);
+            rest = &rest[start + 1..];
+
+            let end = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            let parameter = &rest[..end];
+            let Some(default) = self.defaults.get(parameter) else {
+                return Err(self.error(node, format!("undefined parameter `${parameter}`")));
+            };
+
+            value.push_str(default);
+            rest = &rest[end..];
+        }
+
+        value.push_str(rest);
+        Ok(Some(value))
+    }
+
+    fn required(&self, node: Node, name: &str) -> Result<String, MitsubaError> {
+        self.attribute(node, name)?.ok_or_else(|| {
+            self.error(
+                node,
+                format!("missing `{name}` attribute of `{}`", node.tag_name().name()),
+            )
+        })
+    }
+
+    fn numbers(&self, node: Node, text: &str) -> Result<Vec<f32>, MitsubaError> {
+        text.split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|number| !number.is_empty())
+            .map(|number| {
+                number
+                    .parse()
+                    .map_err(|_| self.error(node, format!("invalid number `{number}`")))
+            })
+            .collect()
+    }
+
+    fn number(&self, node: Node, name: &str) -> Result<Option<f32>, MitsubaError> {
+        match self.attribute(node, name)? {
+            Some(text) => match self.numbers(node, &text)?[..] {
+                [number] => Ok(Some(number)),
+                _ => Err(self.error(node, format!("expected one number in `{name}`"))),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Reads a vector written as `value="x, y, z"` or as `x`, `y` and `z` attributes.
+    fn vector(&self, node: Node, default: f32) -> Result<Vec3, MitsubaError> {
+        if let Some(value) = self.attribute(node, "value")? {
+            return match self.numbers(node, &value)?[..] {
+                [value] => Ok(Vec3::splat(value)),
+                [x, y, z] => Ok(Vec3::new(x, y, z)),
+                _ => Err(self.error(node, "expected one or three numbers")),
+            };
+        }
+
+        Ok(Vec3::new(
+            self.number(node, "x")?.unwrap_or(default),
+            self.number(node, "y")?.unwrap_or(default),
+            self.number(node, "z")?.unwrap_or(default),
+        ))
+    }
+
+    fn triple(&self, node: Node, name: &str) -> Result<Option<Vec3>, MitsubaError> {
+        match self.attribute(node, name)? {
+            Some(text) => match self.numbers(node, &text)?[..] {
+                [x, y, z] => Ok(Some(Vec3::new(x, y, z))),
+                _ => Err(self.error(node, format!("expected three numbers in `{name}`"))),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Reads a `transform` element, whose operations are applied in order.
+    fn transform(&self, node: Node) -> Result<Mat4, MitsubaError> {
+        let mut transform = Mat4::IDENTITY;
+
+        for operation in node.children().filter(Node::is_element) {
+            let matrix = match operation.tag_name().name() {
+                "translate" => Mat4::from_translation(self.vector(operation, 0.0)?),
+                "scale" => Mat4::from_scale(self.vector(operation, 1.0)?),
+                "rotate" => {
+                    let axis = self.vector(operation, 0.0)?;
+                    let angle = self.number(operation, "angle")?.unwrap_or(0.0);
+
+                    if axis == Vec3::ZERO {
+                        return Err(self.error(operation, "rotation without an axis"));
+                    }
+
+                    Mat4::from_axis_angle(axis.normalize(), angle.to_radians())
+                }
+                "matrix" => {
+                    let value = self.required(operation, "value")?;
+
+                    let values = self.numbers(operation, &value)?;
+
+                    // written row by row
+                    match values[..] {
+                        _ if values.len() == 16 => Mat4::from_cols_slice(&values).transpose(),
+                        [a, b, c, d, e, f, g, h, i] => Mat4::from_cols_array(&[
+                            a, d, g, 0.0, b, e, h, 0.0, c, f, i, 0.0, 0.0, 0.0, 0.0, 1.0,
+                        ]),
+                        _ => return Err(self.error(operation, "expected 9 or 16 numbers")),
+                    }
+                }
+                "lookat" => {
+                    let origin = self.triple(operation, "origin")?.unwrap_or(Vec3::ZERO);
+                    let target = self.triple(operation, "target")?.unwrap_or(Vec3::Z);
+                    let up = self.triple(operation, "up")?.unwrap_or(Vec3::Y);
+
+                    // the camera looks down `+Z`, with `+X` on the left of the image
+                    let direction = (target - origin).normalize();
+                    let left = up.cross(direction).normalize();
+                    let up = direction.cross(left);
+
+                    Mat4::from_cols(
+                        left.extend(0.0),
+                        up.extend(0.0),
+                        direction.extend(0.0),
+                        origin.extend(1.0),
+                    )
+                }
+                other => {
+                    self.warn(
+                        operation,
+                        format_args!("ignoring unknown transform `{other}`"),
+                    );
+                    continue;
+                }
+            };
+
+            transform = matrix * transform;
+        }
+
+        Ok(transform)
+    }
+
+    fn scene(
+        &self,
+        root: Node<'a, 'input>,
+        name: String,
+    ) -> Result<SceneDescription, MitsubaError> {
+        let mut render = RenderSettings {
+            width: 768,
+            height: 576,
+            samples: 4,
+            ..RenderSettings::default()
+        };
+        let mut sensor = None;
+        let mut bsdfs = HashMap::new();
+        let mut shapes = Vec::new();
+        let mut lights = Vec::new();
+        let mut environment = None;
+
+        for node in root.children().filter(Node::is_element) {
+            match node.tag_name().name() {
+                "default" => {}
+                "integrator" => self.integrator(node, &mut render)?,
+                "sensor" => sensor = Some(self.sensor(node, &mut render)?),
+                "bsdf" => {
+                    let bsdf = self.bsdf(node, &bsdfs)?;
+
+                    match node.attribute("id") {
+                        Some(id) => {
+                            bsdfs.insert(id.to_owned(), bsdf);
+                        }
+                        None => self.warn(node, "ignoring a BSDF without an `id`"),
+                    }
+                }
+                "shape" => {
+                    if let Some(shape) = self.shape(node, &bsdfs)? {
+                        shapes.push(shape);
+                    }
+                }
+                "emitter" => self.emitter(node, &mut lights, &mut environment)?,
+                other => self.warn(node, format_args!("`{other}` elements are not supported")),
+            }
+        }
+
+        let sensor = sensor.unwrap_or(Sensor {
+            transform: Mat4::IDENTITY,
+            fov: 90.0,
+            fov_axis: "x".to_owned(),
+        });
+
+        let position = sensor.transform.transform_point3(Vec3::ZERO);
+        let direction = sensor.transform.transform_vector3(Vec3::Z).normalize();
+        let up = sensor.transform.transform_vector3(Vec3::Y).normalize();
+        let right = -sensor.transform.transform_vector3(Vec3::X);
+
+        // the renderer puts `direction × up` on the right of the image, which is where Mitsuba
+        // puts it too, unless the sensor is mirrored
+        let mirror = if direction.cross(up).dot(right) < 0.0 {
+            Mat4::from_scale(Vec3::new(-1.0, 1.0, 1.0))
+        } else {
+            Mat4::IDENTITY
+        };
+
+        let (width, height) = (render.width as f32, render.height as f32);
+        let tan_half_fov = (sensor.fov.to_radians() / 2.0).tan();
+        let vertical_scale = match sensor.fov_axis.as_str() {
+            "y" => 1.0,
+            "diagonal" => height / (width * width + height * height).sqrt(),
+            "smaller" => (height / width).max(1.0),
+            "larger" => (height / width).min(1.0),
+            _ => height / width,
+        };
+        let camera = CameraDescription {
+            position: mirror.transform_point3(position).into(),
+            target: mirror.transform_point3(position + direction).into(),
+            up: mirror.transform_vector3(up).into(),
+            fov: 2.0 * (tan_half_fov * vertical_scale).atan().to_degrees(),
+        };
+
+        let mut materials = BTreeMap::new();
+        let objects = shapes
+            .into_iter()
+            .map(|shape| self.object(shape, mirror, camera.position, &mut materials))
+            .collect();
+
+        let lights = lights
+            .into_iter()
+            .map(|light| mirror_light(light, mirror))
+            .collect();
+
+        Ok(SceneDescription {
+            name,
+            camera,
+            render,
+            environment,
+            materials,
+            objects,
+            lights,
+        })
+    }
+
+    fn integrator(&self, node: Node, render: &mut RenderSettings) -> Result<(), MitsubaError> {
+        let ty = self.required(node, "type")?;
+        let mut properties = Properties::new(self, node);
+
+        if !matches!(ty.as_str(), "path" | "volpath" | "volpathmis") {
+            self.warn(
+                node,
+                format_args!("the `{ty}` integrator is not supported, using path tracing"),
+            );
+        }
+
+        // Mitsuba counts the vertices of a path, so a depth of 2 is one bounce, and -1 means no
+        // limit, which is left to the renderer
+        let max_depth = properties.int("max_depth", -1)?;
+        if max_depth > 0 {
+            render.max_bounces = (max_depth - 1) as u32;
+        }
+
+        properties.finish();
+        Ok(())
+    }
+
+    fn sensor(&self, node: Node, render: &mut RenderSettings) -> Result<Sensor, MitsubaError> {
+        let ty = self.required(node, "type")?;
+        let mut properties = Properties::new(self, node);
+
+        if ty != "perspective" && ty != "thinlens" {
+            self.warn(
+                node,
+                format_args!("`{ty}` sensors are not supported, using a perspective one"),
+            );
+        }
+
+        // a focal length is relative to a full frame sensor, which is 36 mm wide
+        let default_fov = match properties.string("focal_length")? {
+            Some(focal_length) => {
+                let millimeters = focal_length.trim_end_matches("mm").trim();
+                let focal_length: f32 = millimeters.parse().map_err(|_| {
+                    self.error(node, format!("invalid focal length `{focal_length}`"))
+                })?;
+                2.0 * (18.0 / focal_length).atan().to_degrees()
+            }
+            None => 39.6,
+        };
+
+        let sensor = Sensor {
+            transform: properties.transform("to_world")?.unwrap_or(Mat4::IDENTITY),
+            fov: properties.float("fov", default_fov)?,
+            fov_axis: properties
+                .string("fov_axis")?
+                .unwrap_or_else(|| "x".to_owned()),
+        };
+        properties.finish();
+
+        for child in node.children().filter(Node::is_element) {
+            let mut properties = Properties::new(self, child);
+
+            match child.tag_name().name() {
+                "film" => {
+                    render.width = properties.int("width", 768)?.max(1) as u32;
+                    render.height = properties.int("height", 576)?.max(1) as u32;
+                }
+                "sampler" => {
+                    render.samples = properties.int("sample_count", 4)?.max(1) as u32;
+                }
+                _ => continue,
+            }
+
+            properties.finish();
+        }
+
+        Ok(sensor)
+    }
+
+    /// Reads a `bsdf` element, or a `ref` to one defined earlier.
+    fn bsdf(
+        &self,
+        node: Node<'a, 'input>,
+        bsdfs: &HashMap<String, Bsdf>,
+    ) -> Result<Bsdf, MitsubaError> {
+        if node.has_tag_name("ref") {
+            let id = self.required(node, "id")?;
+            return bsdfs
+                .get(&id)
+                .cloned()
+                .ok_or_else(|| self.error(node, format!("undefined BSDF `{id}`")));
+        }
+
+        let ty = self.required(node, "type")?;
+        let name = node.attribute("id").unwrap_or("material").to_owned();
+        let mut properties = Properties::new(self, node);
+
+        let (material, two_sided) = match ty.as_str() {
+            "twosided" | "mask" | "bumpmap" | "normalmap" => {
+                if ty != "twosided" {
+                    self.warn(
+                        node,
+                        format_args!("`{ty}` BSDFs are not supported, using the BSDF they wrap"),
+                    );
+                }
+
+                let inner = match node
+                    .children()
+                    .find(|child| child.has_tag_name("bsdf") || child.has_tag_name("ref"))
+                {
+                    Some(child) => self.bsdf(child, bsdfs)?,
+                    None => Bsdf::default(),
+                };
+
+                (inner.material, inner.two_sided || ty == "twosided")
+            }
+            "diffuse" => (
+                diffuse(properties.color("reflectance", Vec3A::splat(0.5))?),
+                false,
+            ),
+            "conductor" | "roughconductor" => {
+                let ior = self.conductor_ior(&mut properties)?;
+                let roughness = properties.roughness(if ty == "conductor" { 0.0 } else { 0.1 })?;
+                let material = MaterialDescription {
+                    albedo: properties.color("specular_reflectance", Vec3A::ONE)?,
+                    ..conductor(ior, roughness)
+                };
+
+                (material, false)
+            }
+            "dielectric" | "roughdielectric" | "thindielectric" => {
+                if ty == "thindielectric" {
+                    self.warn(node, "thin dielectrics are approximated by solid ones");
+                }
+
+                let ior =
+                    properties.ior("int_ior", 1.5046)? / properties.ior("ext_ior", 1.000277)?;
+                let material = MaterialDescription {
+                    brdf: Some(BrdfKind::Dielectric),
+                    ior,
+                    albedo: Vec3A::ONE,
+                    specular: specular_from_ior(ior),
+                    roughness: properties.roughness(if ty == "roughdielectric" {
+                        0.1
+                    } else {
+                        0.0
+                    })?,
+                    ..MaterialDescription::default()
+                };
+
+                (material, true)
+            }
+            "plastic" | "roughplastic" => {
+                let ior = properties.ior("int_ior", 1.49)? / properties.ior("ext_ior", 1.000277)?;
+                let material = MaterialDescription {
+                    brdf: Some(BrdfKind::Disney),
+                    albedo: properties.color("diffuse_reflectance", Vec3A::splat(0.5))?,
+                    specular: specular_from_ior(ior),
+                    roughness: properties.roughness(if ty == "plastic" { 0.0 } else { 0.1 })?,
+                    ..MaterialDescription::default()
+                };
+
+                (material, false)
+            }
+            "principled" => {
+                let specular = if properties.contains("eta") {
+                    specular_from_ior(properties.float("eta", 1.5)?)
+                } else {
+                    properties.float("specular", 0.5)?
+                };
+
+                if properties.float("spec_trans", 0.0)? > 0.0 {
+                    self.warn(
+                        node,
+                        "specular transmission of principled BSDFs is not supported",
+                    );
+                }
+
+                let material = MaterialDescription {
+                    brdf: Some(BrdfKind::Disney),
+                    albedo: properties.color("base_color", Vec3A::splat(0.5))?,
+                    metallic: properties.float("metallic", 0.0)?,
+                    specular,
+                    specular_tint: Vec3A::splat(properties.float("spec_tint", 0.0)?),
+                    roughness: properties.float("roughness", 0.5)?,
+                    anisotropic: properties.float("anisotropic", 0.0)?,
+                    sheen: properties.float("sheen", 0.0)?,
+                    sheen_tint: Vec3A::splat(properties.float("sheen_tint", 0.0)?),
+                    clearcoat: properties.float("clearcoat", 0.0)?,
+                    clearcoat_gloss: properties.float("clearcoat_gloss", 0.0)?,
+                    ..MaterialDescription::default()
+                };
+
+                (material, false)
+            }
+            _ => {
+                self.warn(
+                    node,
+                    format_args!("`{ty}` BSDFs are not supported, using a diffuse one"),
+                );
+                (diffuse(Vec3A::splat(0.5)), false)
+            }
+        };
+
+        properties.finish();
+
+        Ok(Bsdf {
+            name,
+            material,
+            two_sided,
+        })
+    }
+
+    /// Returns the complex index of refraction of a conductor, given either by the name of its
+    /// material or directly.
+    fn conductor_ior(&self, properties: &mut Properties) -> Result<(Vec3A, Vec3A), MitsubaError> {
+        if let Some(material) = properties.string("material")? {
+            if material == "none" {
+                return Ok(ior_from_reflectance(Vec3A::ONE));
+            }
+
+            return Ok(metal_ior(&material).unwrap_or_else(|| {
+                self.warn(
+                    properties.node,
+                    format_args!("unknown conductor `{material}`, using copper"),
+                );
+                COPPER
+            }));
+        }
+
+        // without a material, conductors are perfect mirrors
+        if !properties.contains("eta") {
+            return Ok(ior_from_reflectance(Vec3A::ONE));
+        }
+
+        let eta = properties.color("eta", Vec3A::ONE)?;
+        let k = properties.color("k", Vec3A::ZERO)?;
+        Ok((eta, k))
+    }
+
+    fn shape(
+        &self,
+        node: Node<'a, 'input>,
+        bsdfs: &HashMap<String, Bsdf>,
+    ) -> Result<Option<Shape>, MitsubaError> {
+        let ty = self.required(node, "type")?;
+        let mut properties = Properties::new(self, node);
+
+        let kind = match ty.as_str() {
+            "sphere" => ShapeKind::Sphere {
+                center: properties.point("center")?.unwrap_or(Vec3A::ZERO),
+                radius: properties.float("radius", 1.0)?,
+            },
+            "rectangle" => ShapeKind::Rectangle,
+            "cube" => ShapeKind::Cube,
+            "obj" | "ply" => match properties.string("filename")? {
+                Some(filename) => ShapeKind::Mesh {
+                    path: PathBuf::from(filename),
+                },
+                None => return Err(self.error(node, "missing `filename` of the shape")),
+            },
+            _ => {
+                self.warn(node, format_args!("`{ty}` shapes are not supported"));
+                return Ok(None);
+            }
+        };
+
+        if properties.bool("flip_normals", false)? {
+            self.warn(node, "flipped normals are not supported");
+        }
+
+        let transform = properties.transform("to_world")?.unwrap_or(Mat4::IDENTITY);
+
+        let bsdf = match node
+            .children()
+            .find(|child| child.has_tag_name("bsdf") || child.has_tag_name("ref"))
+        {
+            Some(child) => self.bsdf(child, bsdfs)?,
+            None => Bsdf::default(),
+        };
+
+        let mut emission = None;
+        for emitter in node
+            .children()
+            .filter(|child| child.has_tag_name("emitter"))
+        {
+            let ty = self.required(emitter, "type")?;
+            let mut properties = Properties::new(self, emitter);
+
+            if ty == "area" {
+                emission = Some(properties.color("radiance", Vec3A::ONE)?);
+            } else {
+                self.warn(
+                    emitter,
+                    format_args!("`{ty}` emitters cannot be attached to shapes"),
+                );
+            }
+
+            properties.finish();
+        }
+
+        properties.finish();
+
+        Ok(Some(Shape {
+            kind,
+            transform,
+            bsdf,
+            emission,
+            location: self.location(node),
+        }))
+    }
+
+    fn emitter(
+        &self,
+        node: Node,
+        lights: &mut Vec<LightDescription>,
+        environment: &mut Option<EnvironmentDescription>,
+    ) -> Result<(), MitsubaError> {
+        let ty = self.required(node, "type")?;
+        let mut properties = Properties::new(self, node);
+        let transform = properties.transform("to_world")?;
+        let to_world = transform.unwrap_or(Mat4::IDENTITY);
+
+        match ty.as_str() {
+            "point" => {
+                let position = match properties.point("position")? {
+                    Some(position) => position,
+                    None => to_world.transform_point3(Vec3::ZERO).into(),
+                };
+
+                lights.push(LightDescription::Point {
+                    position,
+                    intensity: properties.color("intensity", Vec3A::ONE)?,
+                });
+            }
+            "spot" => {
+                let cone_angle = properties.float("cutoff_angle", 20.0)?;
+
+                lights.push(LightDescription::Spot {
+                    position: to_world.transform_point3(Vec3::ZERO).into(),
+                    direction: to_world.transform_vector3(Vec3::Z).into(),
+                    intensity: properties.color("intensity", Vec3A::ONE)?,
+                    falloff_start: properties.float("beam_width", cone_angle * 0.75)?,
+                    cone_angle,
+                });
+            }
+            "directional" => {
+                let direction = match properties.point("direction")? {
+                    Some(direction) => direction,
+                    None => to_world.transform_vector3(Vec3::Z).into(),
+                };
+
+                lights.push(LightDescription::Directional {
+                    direction,
+                    irradiance: properties.color("irradiance", Vec3A::ONE)?,
+                });
+            }
+            "constant" => {
+                let radiance = properties.color("radiance", Vec3A::ONE)?;
+                self.set_environment(
+                    node,
+                    environment,
+                    EnvironmentDescription::Uniform { radiance },
+                );
+            }
+            "envmap" => {
+                let Some(filename) = properties.string("filename")? else {
+                    return Err(self.error(node, "missing `filename` of the emitter"));
+                };
+                let is_hdr = Path::new(&filename)
+                    .extension()
+                    .is_some_and(|extension| extension.eq_ignore_ascii_case("hdr"));
+
+                if is_hdr {
+                    if transform.is_some() {
+                        self.warn(node, "environment maps cannot be rotated");
+                    }
+
+                    let map = EnvironmentDescription::Map {
+                        path: PathBuf::from(filename),
+                        intensity: properties.float("scale", 1.0)?,
+                    };
+                    self.set_environment(node, environment, map);
+                } else {
+                    self.warn(
+                        node,
+                        format_args!(
+                            "only Radiance `.hdr` environment maps are supported, ignoring \
+                             `{filename}`"
+                        ),
+                    );
+                }
+            }
+            "area" => self.warn(node, "area emitters have to be attached to a shape"),
+            _ => self.warn(node, format_args!("`{ty}` emitters are not supported")),
+        }
+
+        properties.finish();
+        Ok(())
+    }
+
+    fn set_environment(
+        &self,
+        node: Node,
+        environment: &mut Option<EnvironmentDescription>,
+        new: EnvironmentDescription,
+    ) {
+        if environment.is_some() {
+            self.warn(
+                node,
+                "only one environment emitter is supported, using the last one",
+            );
+        }
+
+        *environment = Some(new);
+    }
+
+    fn object(
+        &self,
+        shape: Shape,
+        mirror: Mat4,
+        viewpoint: Vec3A,
+        materials: &mut BTreeMap<String, MaterialDescription>,
+    ) -> ObjectDescription {
+        let Shape {
+            kind,
+            transform,
+            bsdf,
+            emission,
+            location,
+        } = shape;
+        let warn = |message: &str| {
+            self.warnings
+                .borrow_mut()
+                .push(format!("{location}: {message}"));
+        };
+
+        // only opaque surfaces that scatter light on both sides can be turned towards the camera
+        let turn_to_camera = bsdf.two_sided && emission.is_none();
+
+        let (name, material) = match emission {
+            Some(radiance) => (
+                format!("{}_emitter", bsdf.name),
+                MaterialDescription {
+                    emission: radiance,
+                    ..bsdf.material
+                },
+            ),
+            None => (bsdf.name, bsdf.material),
+        };
+        let material = intern_material(materials, name, material);
+        let transform = Affine3A::from_mat4(mirror * transform);
+
+        match kind {
+            ShapeKind::Sphere { center, radius } => {
+                let scales = Vec3A::new(
+                    transform.matrix3.x_axis.length(),
+                    transform.matrix3.y_axis.length(),
+                    transform.matrix3.z_axis.length(),
+                );
+                let scale = scales.element_sum() / 3.0;
+
+                if scales.max_element() - scales.min_element() > 1e-3 * scale {
+                    warn("spheres cannot be scaled non-uniformly, using the average scale");
+                }
+
+                ObjectDescription::Sphere {
+                    center: transform.transform_point3a(center),
+                    radius: radius * scale,
+                    material,
+                }
+            }
+            ShapeKind::Rectangle => {
+                let center = transform.transform_point3a(Vec3A::ZERO);
+                let x = transform.transform_vector3a(Vec3A::X);
+                let y = transform.transform_vector3a(Vec3A::Y);
+                let mut normal = (transform.matrix3.inverse().transpose() * Vec3A::Z).normalize();
+
+                if turn_to_camera && (viewpoint - center).dot(normal) < 0.0 {
+                    normal = -normal;
+                }
+
+                rectangle(center, x, y, normal, material)
+            }
+            ShapeKind::Cube => {
+                let (scale, rotation, translation) = transform.to_scale_rotation_translation();
+
+                if !Affine3A::from_scale_rotation_translation(scale, rotation, translation)
+                    .abs_diff_eq(transform, 1e-3)
+                {
+                    warn("cubes cannot be sheared, approximating the transform");
+                }
+
+                ObjectDescription::Box {
+                    center: translation.into(),
+                    size: (scale.abs() * 2.0).into(),
+                    rotation: degrees_from_rotation(rotation),
+                    material,
+                }
+            }
+            ShapeKind::Mesh { path } => {
+                let (scale, rotation, translation) = transform.to_scale_rotation_translation();
+
+                if !Affine3A::from_scale_rotation_translation(scale, rotation, translation)
+                    .abs_diff_eq(transform, 1e-3)
+                {
+                    warn("meshes cannot be sheared, approximating the transform");
+                }
+
+                if turn_to_camera {
+                    warn("two-sided meshes loaded from files are only shaded on their front");
+                }
+
+                ObjectDescription::Mesh {
+                    path,
+                    material: Some(material),
+                    translation: translation.into(),
+                    rotation: degrees_from_rotation(rotation),
+                    scale: scale.into(),
+                }
+            }
+        }
+    }
+}
+
+/// Describes a rectangle centered on `center` with the half edges `x` and `y` as a plain when its
+/// edges line up with the ones a plain facing `normal` has, or as two triangles otherwise.
+fn rectangle(
+    center: Vec3A,
+    x: Vec3A,
+    y: Vec3A,
+    normal: Vec3A,
+    material: String,
+) -> ObjectDescription {
+    let plain_axes = Plain {
+        center,
+        normal,
+        size: Vec2::ONE,
+        material: MaterialDescription::default().to_material(),
+    }
+    .rotation();
+    let is_parallel = |a: Vec3A, b: Vec3A| a.normalize().dot(b).abs() > 0.9999;
+    let is_square = (x.length() - y.length()).abs() < 1e-4 * x.length();
+
+    if x.dot(y).abs() < 1e-4 * x.length() * y.length() {
+        let size = if is_parallel(x, plain_axes.x_axis) || is_square {
+            Some(Vec2::new(x.length(), y.length()) * 2.0)
+        } else if is_parallel(x, plain_axes.y_axis) {
+            Some(Vec2::new(y.length(), x.length()) * 2.0)
+        } else {
+            None
+        };
+
+        if let Some(size) = size {
+            return ObjectDescription::Plain {
+                center,
+                normal,
+                size,
+                material,
+            };
+        }
+    }
+
+    let positions = vec![
+        center - x - y,
+        center + x - y,
+        center + x + y,
+        center - x + y,
+    ];
+    let mut triangles = vec![[0, 1, 2], [0, 2, 3]];
+
+    if faces_away_from(&positions, &triangles, center + normal) {
+        for triangle in &mut triangles {
+            triangle.swap(1, 2);
+        }
+    }
+
+    ObjectDescription::TriangleMesh {
+        positions,
+        normals: None,
+        uvs: None,
+        triangles,
+        material,
+    }
+}
+
+fn mirror_light(light: LightDescription, mirror: Mat4) -> LightDescription {
+    let point = |point: Vec3A| mirror.transform_point3(point.into()).into();
+    let vector = |vector: Vec3A| mirror.transform_vector3(vector.into()).into();
+
+    match light {
+        LightDescription::Point {
+            position,
+            intensity,
+        } => LightDescription::Point {
+            position: point(position),
+            intensity,
+        },
+        LightDescription::Spot {
+            position,
+            direction,
+            intensity,
+            falloff_start,
+            cone_angle,
+        } => LightDescription::Spot {
+            position: point(position),
+            direction: vector(direction),
+            intensity,
+            falloff_start,
+            cone_angle,
+        },
+        LightDescription::Directional {
+            direction,
+            irradiance,
+        } => LightDescription::Directional {
+            direction: vector(direction),
+            irradiance,
+        },
+    }
+}
+
+/// Returns the index of refraction of a few materials Mitsuba knows by name.
+fn named_ior(name: &str) -> Option<f32> {
+    match name {
+        "vacuum" => Some(1.0),
+        "air" => Some(1.000277),
+        "water" => Some(1.333),
+        "water ice" => Some(1.31),
+        "fused quartz" => Some(1.458),
+        "pyrex" => Some(1.470),
+        "acrylic glass" => Some(1.49),
+        "polypropylene" => Some(1.49),
+        "bk7" => Some(1.5046),
+        "sodium chloride" => Some(1.544),
+        "amber" => Some(1.55),
+        "pet" => Some(1.575),
+        "diamond" => Some(2.419),
+        _ => None,
+    }
+}
+
+/// The properties of a plugin, such as `<float name="radius" value="2"/>`.
+///
+/// Lookups record which properties were used, so that the ones that were not can be reported when
+/// the plugin is done with.
+struct Properties<'i, 'a, 'input> {
+    importer: &'i Importer<'a, 'input>,
+    node: Node<'a, 'input>,
+    used: Vec<String>,
+}
+
+impl<'i, 'a, 'input> Properties<'i, 'a, 'input> {
+    fn new(importer: &'i Importer<'a, 'input>, node: Node<'a, 'input>) -> Self {
+        Self {
+            importer,
+            node,
+            used: Vec::new(),
+        }
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.node
+            .children()
+            .any(|child| child.attribute("name") == Some(name))
+    }
+
+    fn find(&mut self, name: &str) -> Option<Node<'a, 'input>> {
+        let property = self
+            .node
+            .children()
+            .find(|child| child.is_element() && child.attribute("name") == Some(name))?;
+        self.used.push(name.to_owned());
+        Some(property)
+    }
+
+    /// Reports a property that had to be replaced by its default value.
+    fn unsupported(&self, property: Node) {
+        self.importer.warn(
+            property,
+            format_args!(
+                "`{} {}` of `{} {}` is not supported, using a default",
+                property.tag_name().name(),
+                property.attribute("name").unwrap_or_default(),
+                self.node.tag_name().name(),
+                self.node.attribute("type").unwrap_or_default(),
+            ),
+        );
+    }
+
+    fn float(&mut self, name: &str, default: f32) -> Result<f32, MitsubaError> {
+        let Some(property) = self.find(name) else {
+            return Ok(default);
+        };
+
+        match property.tag_name().name() {
+            "float" | "integer" => Ok(self.importer.number(property, "value")?.unwrap_or(default)),
+            _ => {
+                self.unsupported(property);
+                Ok(default)
+            }
+        }
+    }
+
+    fn int(&mut self, name: &str, default: i64) -> Result<i64, MitsubaError> {
+        let Some(property) = self.find(name) else {
+            return Ok(default);
+        };
+
+        let value = self.importer.required(property, "value")?;
+        value.trim().parse().map_err(|_| {
+            self.importer
+                .error(property, format!("invalid integer `{value}`"))
+        })
+    }
+
+    fn bool(&mut self, name: &str, default: bool) -> Result<bool, MitsubaError> {
+        let Some(property) = self.find(name) else {
+            return Ok(default);
+        };
+
+        match self.importer.required(property, "value")?.as_str() {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            value => Err(self
+                .importer
+                .error(property, format!("invalid boolean `{value}`"))),
+        }
+    }
+
+    fn string(&mut self, name: &str) -> Result<Option<String>, MitsubaError> {
+        match self.find(name) {
+            Some(property) => self.importer.attribute(property, "value"),
+            None => Ok(None),
+        }
+    }
+
+    fn point(&mut self, name: &str) -> Result<Option<Vec3A>, MitsubaError> {
+        match self.find(name) {
+            Some(property) => Ok(Some(self.importer.vector(property, 0.0)?.into())),
+            None => Ok(None),
+        }
+    }
+
+    fn transform(&mut self, name: &str) -> Result<Option<Mat4>, MitsubaError> {
+        match self.find(name) {
+            Some(property) => Ok(Some(self.importer.transform(property)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns an RGB value; spectra other than constant ones and textures are replaced by
+    /// `default`.
+    fn color(&mut self, name: &str, default: Vec3A) -> Result<Vec3A, MitsubaError> {
+        let Some(property) = self.find(name) else {
+            return Ok(default);
+        };
+
+        let value = match property.tag_name().name() {
+            "rgb" | "float" | "spectrum" => self.importer.attribute(property, "value")?,
+            _ => None,
+        };
+        let numbers = match value {
+            // spectra sampled at wavelengths are written as `wavelength:value` pairs
+            Some(value) if !value.contains(':') => self.importer.numbers(property, &value)?,
+            _ => Vec::new(),
+        };
+
+        match numbers[..] {
+            [value] => Ok(Vec3A::splat(value)),
+            [r, g, b] if property.has_tag_name("rgb") => Ok(Vec3A::new(r, g, b)),
+            _ => {
+                self.unsupported(property);
+                Ok(default)
+            }
+        }
+    }
+
+    /// Returns an index of refraction, given either as a number or as the name of a material.
+    fn ior(&mut self, name: &str, default: f32) -> Result<f32, MitsubaError> {
+        let Some(property) = self.find(name) else {
+            return Ok(default);
+        };
+
+        match property.tag_name().name() {
+            "string" => {}
+            "float" | "integer" => {
+                return Ok(self.importer.number(property, "value")?.unwrap_or(default));
+            }
+            _ => {
+                self.unsupported(property);
+                return Ok(default);
+            }
+        }
+
+        let material = self.importer.required(property, "value")?;
+
+        Ok(named_ior(&material).unwrap_or_else(|| {
+            self.importer.warn(
+                property,
+                format_args!("unknown index of refraction `{material}`, using {default}"),
+            );
+            default
+        }))
+    }
+
+    /// Converts the microfacet alpha of a rough BSDF into our roughness, whose square is alpha.
+    ///
+    /// Anisotropic roughness is approximated by the average alpha of both directions, and
+    /// Beckmann distributions by GGX ones.
+    fn roughness(&mut self, default_alpha: f32) -> Result<f32, MitsubaError> {
+        let alpha = self.float("alpha", default_alpha)?;
+        let u = self.float("alpha_u", alpha)?;
+        let v = self.float("alpha_v", alpha)?;
+        self.string("distribution")?;
+
+        Ok(((u + v) / 2.0).max(0.0).sqrt().min(1.0))
+    }
+
+    /// Reports the properties that were never looked up.
+    fn finish(self) {
+        for property in self.node.children().filter(Node::is_element) {
+            let Some(name) = property.attribute("name") else {
+                continue;
+            };
+
+            if self.used.iter().any(|used| used == name)
+                || property.has_tag_name("bsdf")
+                || property.has_tag_name("ref")
+            {
+                continue;
+            }
+
+            self.importer.warn(
+                property,
+                format_args!(
+                    "ignoring `{} {}` of `{} {}`",
+                    property.tag_name().name(),
+                    name,
+                    self.node.tag_name().name(),
+                    self.node.attribute("type").unwrap_or_default(),
+                ),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Quat;
+
+    fn import(test: &str, source: &str) -> Result<ImportedScene, MitsubaError> {
+        let dir =
+            std::env::temp_dir().join(format!("raytracer-mitsuba-{}-{test}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("scene.xml"), source).unwrap();
+
+        import_mitsuba(dir.join("scene.xml"))
+    }
+
+    #[test]
+    fn fields_of_view_are_converted_to_vertical_ones() {
+        let cases = [
+            ("x", (200, 100), 32.20423),
+            ("y", (200, 100), 60.0),
+            ("diagonal", (200, 100), 28.95502),
+            ("smaller", (200, 100), 60.0),
+            ("larger", (200, 100), 32.20423),
+            ("smaller", (100, 200), 98.21321),
+            ("larger", (100, 200), 60.0),
+        ];
+
+        for (fov_axis, (width, height), expected) in cases {
+            let source = format!(
+                r#"<scene version="3.0.0">
+    <sensor type="perspective">
+        <float name="fov" value="60"/>
+        <string name="fov_axis" value="{fov_axis}"/>
+        <film type="hdrfilm">
+            <integer name="width" value="{width}"/>
+            <integer name="height" value="{height}"/>
+        </film>
+    </sensor>
+</scene>"#
+            );
+            let fov = import("fov_axis", &source).unwrap().description.camera.fov;
+
+            assert!(
+                (fov - expected).abs() < 1e-3,
+                "{fov_axis} of {width}x{height}: {fov}"
+            );
+        }
+    }
+
+    #[test]
+    fn rotated_cubes_become_boxes() {
+        let description = import(
+            "cube",
+            r#"<scene version="3.0.0">
+    <shape type="cube">
+        <transform name="to_world">
+            <scale x="1" y="2" z="0.5"/>
+            <rotate y="1" angle="30"/>
+            <translate x="1" z="-2"/>
+        </transform>
+    </shape>
+</scene>"#,
+        )
+        .unwrap()
+        .description;
+
+        match &description.objects[..] {
+            [
+                ObjectDescription::Box {
+                    center,
+                    size,
+                    rotation,
+                    ..
+                },
+            ] => {
+                // the cube spans from -1 to 1, so its size is twice its scale
+                assert!(center.abs_diff_eq(Vec3A::new(1.0, 0.0, -2.0), 1e-5));
+                assert!(size.abs_diff_eq(Vec3A::new(2.0, 4.0, 1.0), 1e-5));
+                assert!(rotation.abs_diff_eq(Vec3A::new(0.0, 30.0, 0.0), 1e-3));
+            }
+            objects => panic!("expected a box, got {objects:?}"),
+        }
+    }
+
+    #[test]
+    fn rectangles_with_the_edges_of_a_plain_become_plains() {
+        // a plain facing `+Z` has its width along `+X` and its height along `+Y`
+        let plain = |x: Vec3A, y: Vec3A| match rectangle(Vec3A::ONE, x, y, Vec3A::Z, String::new())
+        {
+            ObjectDescription::Plain {
+                center,
+                normal,
+                size,
+                ..
+            } => {
+                assert_eq!((center, normal), (Vec3A::ONE, Vec3A::Z));
+                size
+            }
+            object => panic!("expected a plain, got {object:?}"),
+        };
+
+        assert_eq!(plain(Vec3A::X * 2.0, Vec3A::Y), Vec2::new(4.0, 2.0));
+        assert_eq!(plain(Vec3A::Y * 2.0, Vec3A::NEG_X), Vec2::new(2.0, 4.0));
+
+        // squares look the same whichever way they are turned within their plane
+        let turned = Quat::from_rotation_z(0.3);
+        let size = plain(turned * Vec3A::X, turned * Vec3A::Y);
+        assert!(size.abs_diff_eq(Vec2::splat(2.0), 1e-5));
+    }
+
+    #[test]
+    fn turned_rectangles_become_triangle_meshes_facing_their_normal() {
+        let turned = Quat::from_rotation_z(0.3);
+        let (x, y) = (turned * Vec3A::X * 2.0, turned * Vec3A::Y);
+
+        for normal in [Vec3A::Z, Vec3A::NEG_Z] {
+            match rectangle(Vec3A::ZERO, x, y, normal, String::new()) {
+                ObjectDescription::TriangleMesh {
+                    positions,
+                    triangles,
+                    ..
+                } => {
+                    assert_eq!(positions, [-x - y, x - y, x + y, y - x]);
+
+                    for triangle in triangles {
+                        let [a, b, c] = triangle.map(|index| positions[index as usize]);
+                        assert!((b - a).cross(c - a).dot(normal) > 0.0);
+                    }
+                }
+                object => panic!("expected a triangle mesh, got {object:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn default_parameters_are_substituted() {
+        let source = r#"<scene version="3.0.0">
+    <default name="spp" value="64"/>
+    <default name="red" value="0.8"/>
+    <sensor type="perspective">
+        <sampler type="independent">
+            <integer name="sample_count" value="$spp"/>
+        </sampler>
+    </sensor>
+    <shape type="sphere">
+        <bsdf type="diffuse">
+            <rgb name="reflectance" value="$red, 0.4, 0.2"/>
+        </bsdf>
+    </shape>
+</scene>"#;
+        let description = import("defaults", source).unwrap().description;
+
+        assert_eq!(description.render.samples, 64);
+        let [ObjectDescription::Sphere { material, .. }] = &description.objects[..] else {
+            panic!("expected a sphere, got {:?}", description.objects);
+        };
+        assert_eq!(
+            description.materials[material].albedo,
+            Vec3A::new(0.8, 0.4, 0.2)
+        );
+
+        let undefined = source.replace("$red", "$green");
+        match import("undefined_default", &undefined) {
+            Err(MitsubaError::Parse { line, message, .. }) => {
+                assert_eq!(line, 11);
+                assert_eq!(message, "undefined parameter `$green`");
+            }
+            Err(error) => panic!("expected a parse error, got {error}"),
+            Ok(_) => panic!("expected a parse error"),
+        }
+    }
+}
@@ -0,0 +1,1232 @@
+mod params;
+mod tokenizer;
+
+use self::{
+    params::{Param, Params, Values},
+    tokenizer::{Token, tokenize},
+};
+use crate::{
+    description::{
+        BrdfKind, CameraDescription, EnvironmentDescription, LightDescription, MaterialDescription,
+        ObjectDescription, RenderSettings, SceneDescription, degrees_from_rotation,
+    },
+    import::{
+        COPPER, ImportedScene, conductor, diffuse, faces_away_from, intern_material,
+        ior_from_reflectance, metal_ior, specular_from_ior,
+    },
+};
+use glam::{Affine3A, Mat3A, Mat4, Vec2, Vec3, Vec3A};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug)]
+pub enum PbrtError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+impl Display for PbrtError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io { path, source } => {
+                write!(f, "failed to read `{}`: {}", path.display(), source)
+            }
+            Self::Parse {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", path.display(), line, message),
+        }
+    }
+}
+
+impl std::error::Error for PbrtError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Parse { .. } => None,
+        }
+    }
+}
+
+/// Imports a pbrt-v4 scene file, along with the files it includes.
+///
+/// Only a subset of the format is understood: perspective cameras, the film resolution, spheres,
+/// triangle meshes and PLY meshes, point, spot, distant and uniform infinite lights, diffuse area
+/// lights, and diffuse, conductor, dielectric and coated diffuse materials with constant values.
+/// Anything else is skipped or replaced by a default, and reported as a warning.
+///
+/// pbrt uses a left-handed coordinate system, so the scene is mirrored whenever that is needed
+/// for the image not to be.
+pub fn import_pbrt(path: impl AsRef<Path>) -> Result<ImportedScene, PbrtError> {
+    let path = path.as_ref();
+    let mut importer = Importer::new(path.parent().unwrap_or(Path::new("")));
+    importer.parse_file(path)?;
+
+    let name = path
+        .file_stem()
+        .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+
+    Ok(importer.finish(name))
+}
+
+/// The material of the objects defined next.
+#[derive(Debug, Clone)]
+enum MaterialRef {
+    Anonymous(Box<MaterialDescription>),
+    Named(String),
+}
+
+/// The state saved by `AttributeBegin` and restored by `AttributeEnd`.
+#[derive(Debug, Clone)]
+struct GraphicsState {
+    material: MaterialRef,
+    /// radiance of the shapes defined next, set by `AreaLightSource`
+    area_light: Option<Vec3A>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Block {
+    Attribute,
+    Transform,
+}
+
+#[derive(Debug, Clone)]
+enum ShapeKind {
+    Sphere {
+        radius: f32,
+    },
+    Triangles {
+        positions: Vec<Vec3A>,
+        normals: Option<Vec<Vec3A>>,
+        uvs: Option<Vec<Vec2>>,
+        triangles: Vec<[u32; 3]>,
+    },
+    PlyMesh {
+        path: PathBuf,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Shape {
+    kind: ShapeKind,
+    /// world from object, in pbrt's coordinate system
+    transform: Mat4,
+    /// the name the material is stored under if no other material took it
+    material_name: String,
+    material: MaterialDescription,
+    location: String,
+}
+
+struct Importer {
+    base_dir: PathBuf,
+    warnings: Vec<String>,
+    /// the canonical paths of the files being parsed, from the scene file to the innermost include
+    includes: Vec<PathBuf>,
+
+    ctm: Mat4,
+    graphics: GraphicsState,
+    stack: Vec<(Block, Mat4, GraphicsState)>,
+    coordinate_systems: HashMap<String, Mat4>,
+    named_materials: HashMap<String, MaterialDescription>,
+
+    camera_from_world: Mat4,
+    fov: f32,
+    render: RenderSettings,
+
+    shapes: Vec<Shape>,
+    /// the object being defined between `ObjectBegin` and `ObjectEnd`
+    instance: Option<(String, Vec<Shape>)>,
+    instances: HashMap<String, Vec<Shape>>,
+    lights: Vec<LightDescription>,
+    environment: Option<EnvironmentDescription>,
+}
+
+impl Importer {
+    fn new(base_dir: &Path) -> Self {
+        Self {
+            base_dir: base_dir.to_owned(),
+            warnings: Vec::new(),
+            includes: Vec::new(),
+            ctm: Mat4::IDENTITY,
+            graphics: GraphicsState {
+                material: MaterialRef::Anonymous(Box::new(diffuse(Vec3A::splat(0.5)))),
+                area_light: None,
+            },
+            stack: Vec::new(),
+            coordinate_systems: HashMap::new(),
+            named_materials: HashMap::new(),
+            camera_from_world: Mat4::IDENTITY,
+            fov: 90.0,
+            render: RenderSettings {
+                width: 1280,
+                height: 720,
+                samples: 16,
+                max_bounces: 5,
+                ..RenderSettings::default()
+            },
+            shapes: Vec::new(),
+            instance: None,
+            instances: HashMap::new(),
+            lights: Vec::new(),
+            environment: None,
+        }
+    }
+
+    fn parse_file(&mut self, path: &Path) -> Result<(), PbrtError> {
+        self.includes
+            .push(fs::canonicalize(path).unwrap_or_else(|_| path.to_owned()));
+        let result = self.parse_directives(path);
+        self.includes.pop();
+
+        result
+    }
+
+    fn parse_directives(&mut self, path: &Path) -> Result<(), PbrtError> {
+        let source = fs::read_to_string(path).map_err(|source| PbrtError::Io {
+            path: path.to_owned(),
+            source,
+        })?;
+        let tokens = tokenize(&source).map_err(|(line, message)| PbrtError::Parse {
+            path: path.to_owned(),
+            line,
+            message,
+        })?;
+        let mut tokens = Tokens {
+            tokens,
+            position: 0,
+            path,
+        };
+
+        while let Some((token, line)) = tokens.next() {
+            let Token::Identifier(directive) = token else {
+                return Err(tokens.error(line, "expected a directive"));
+            };
+
+            self.directive(&directive, line, &mut tokens)?;
+        }
+
+        Ok(())
+    }
+
+    fn warn(&mut self, location: &str, message: impl Display) {
+        self.warnings.push(format!("{location}: {message}"));
+    }
+
+    /// Reports the parameters of a directive that were not imported.
+    fn check_params(&mut self, params: &Params, location: &str, directive: &str) {
+        for description in params.unsupported() {
+            self.warn(
+                location,
+                format_args!("{description} of `{directive}` is not supported, using a default"),
+            );
+        }
+
+        for Param { ty, name, .. } in params.unused() {
+            self.warn(
+                location,
+                format_args!("ignoring `{ty} {name}` of `{directive}`"),
+            );
+        }
+    }
+
+    fn directive(
+        &mut self,
+        directive: &str,
+        line: usize,
+        tokens: &mut Tokens,
+    ) -> Result<(), PbrtError> {
+        let location = format!("{}:{}", tokens.path.display(), line);
+
+        match directive {
+            "Identity" => self.ctm = Mat4::IDENTITY,
+            "Translate" => {
+                let [x, y, z] = tokens.numbers(line)?;
+                self.ctm *= Mat4::from_translation(Vec3::new(x, y, z));
+            }
+            "Scale" => {
+                let [x, y, z] = tokens.numbers(line)?;
+                self.ctm *= Mat4::from_scale(Vec3::new(x, y, z));
+            }
+            "Rotate" => {
+                let [angle, x, y, z] = tokens.numbers(line)?;
+                let axis = Vec3::new(x, y, z);
+
+                if axis != Vec3::ZERO {
+                    self.ctm *= Mat4::from_axis_angle(axis.normalize(), angle.to_radians());
+                }
+            }
+            "LookAt" => {
+                let [ex, ey, ez, tx, ty, tz, ux, uy, uz] = tokens.numbers(line)?;
+                self.ctm *= Mat4::look_at_lh(
+                    Vec3::new(ex, ey, ez),
+                    Vec3::new(tx, ty, tz),
+                    Vec3::new(ux, uy, uz),
+                );
+            }
+            "Transform" => {
+                let values: [f32; 16] = tokens.numbers(line)?;
+                self.ctm = Mat4::from_cols_array(&values);
+            }
+            "ConcatTransform" => {
+                let values: [f32; 16] = tokens.numbers(line)?;
+                self.ctm *= Mat4::from_cols_array(&values);
+            }
+            "CoordinateSystem" => {
+                let name = tokens.string(line)?;
+                self.coordinate_systems.insert(name, self.ctm);
+            }
+            "CoordSysTransform" => {
+                let name = tokens.string(line)?;
+
+                match self.coordinate_systems.get(&name) {
+                    Some(transform) => self.ctm = *transform,
+                    None => self.warn(
+                        &location,
+                        format_args!("unknown coordinate system `{name}`"),
+                    ),
+                }
+            }
+            "AttributeBegin" | "TransformBegin" => {
+                let block = if directive == "AttributeBegin" {
+                    Block::Attribute
+                } else {
+                    Block::Transform
+                };
+                self.stack.push((block, self.ctm, self.graphics.clone()));
+            }
+            "AttributeEnd" | "TransformEnd" => match self.stack.pop() {
+                Some((Block::Attribute, ctm, graphics)) => {
+                    self.ctm = ctm;
+                    self.graphics = graphics;
+                }
+                Some((Block::Transform, ctm, _)) => self.ctm = ctm,
+                None => self.warn(&location, format_args!("unmatched `{directive}`")),
+            },
+            "WorldBegin" => {
+                self.ctm = Mat4::IDENTITY;
+                self.coordinate_systems
+                    .insert("world".to_owned(), Mat4::IDENTITY);
+            }
+            // pbrt-v3 files end with it, pbrt-v4 ones do not
+            "WorldEnd" => {}
+            "Camera" => {
+                let ty = tokens.string(line)?;
+                let mut params = tokens.params()?;
+
+                if ty != "perspective" {
+                    self.warn(
+                        &location,
+                        format_args!("`{ty}` cameras are not supported, using a perspective one"),
+                    );
+                }
+
+                self.camera_from_world = self.ctm;
+                self.fov = params.float("fov", 90.0);
+                self.coordinate_systems
+                    .insert("camera".to_owned(), self.ctm.inverse());
+                self.check_params(&params, &location, directive);
+            }
+            "Film" => {
+                let _ty = tokens.string(line)?;
+                let mut params = tokens.params()?;
+
+                self.render.width = params.int("xresolution", 1280).max(1) as u32;
+                self.render.height = params.int("yresolution", 720).max(1) as u32;
+                self.check_params(&params, &location, directive);
+            }
+            "Sampler" => {
+                let _ty = tokens.string(line)?;
+                let mut params = tokens.params()?;
+
+                self.render.samples = params.int("pixelsamples", 16).max(1) as u32;
+                self.check_params(&params, &location, directive);
+            }
+            "Integrator" => {
+                let ty = tokens.string(line)?;
+                let mut params = tokens.params()?;
+
+                if !matches!(ty.as_str(), "path" | "volpath") {
+                    self.warn(
+                        &location,
+                        format_args!("the `{ty}` integrator is not supported, using path tracing"),
+                    );
+                }
+
+                self.render.max_bounces = params.int("maxdepth", 5).max(0) as u32;
+                self.check_params(&params, &location, directive);
+            }
+            "Material" => {
+                let ty = tokens.string(line)?;
+                let mut params = tokens.params()?;
+                let material = self.material(&ty, &mut params, &location);
+
+                self.graphics.material = MaterialRef::Anonymous(Box::new(material));
+                self.check_params(&params, &location, directive);
+            }
+            "MakeNamedMaterial" => {
+                let name = tokens.string(line)?;
+                let mut params = tokens.params()?;
+                let ty = params.string("type").unwrap_or_default();
+                let material = self.material(&ty, &mut params, &location);
+
+                self.named_materials.insert(name, material);
+                self.check_params(&params, &location, directive);
+            }
+            "NamedMaterial" => {
+                let name = tokens.string(line)?;
+                self.graphics.material = MaterialRef::Named(name);
+            }
+            "LightSource" => {
+                let ty = tokens.string(line)?;
+                let mut params = tokens.params()?;
+
+                self.light(&ty, &mut params, &location);
+                self.check_params(&params, &location, directive);
+            }
+            "AreaLightSource" => {
+                let ty = tokens.string(line)?;
+                let mut params = tokens.params()?;
+
+                if ty == "diffuse" {
+                    let radiance = params.rgb("L", Vec3A::ONE) * params.float("scale", 1.0);
+                    self.graphics.area_light = Some(radiance);
+                } else {
+                    self.warn(
+                        &location,
+                        format_args!("`{ty}` area lights are not supported"),
+                    );
+                }
+
+                self.check_params(&params, &location, directive);
+            }
+            "Shape" => {
+                let ty = tokens.string(line)?;
+                let mut params = tokens.params()?;
+
+                self.shape(&ty, &mut params, &location);
+                self.check_params(&params, &location, directive);
+            }
+            "ObjectBegin" => {
+                let name = tokens.string(line)?;
+
+                if self.instance.is_some() {
+                    self.warn(&location, "objects cannot be defined inside other objects");
+                }
+
+                self.stack
+                    .push((Block::Attribute, self.ctm, self.graphics.clone()));
+                self.instance = Some((name, Vec::new()));
+            }
+            "ObjectEnd" => {
+                if let Some((name, shapes)) = self.instance.take() {
+                    self.instances.insert(name, shapes);
+                }
+
+                if let Some((_, ctm, graphics)) = self.stack.pop() {
+                    self.ctm = ctm;
+                    self.graphics = graphics;
+                }
+            }
+            "ObjectInstance" => {
+                let name = tokens.string(line)?;
+
+                match self.instances.get(&name) {
+                    Some(shapes) => {
+                        let instances = shapes
+                            .iter()
+                            .map(|shape| Shape {
+                                transform: self.ctm * shape.transform,
+                                ..shape.clone()
+                            })
+                            .collect::<Vec<_>>();
+                        self.shapes.extend(instances);
+                    }
+                    None => self.warn(&location, format_args!("unknown object `{name}`")),
+                }
+            }
+            "Include" | "Import" => {
+                let path = self.base_dir.join(tokens.string(line)?);
+
+                if let Ok(canonical) = fs::canonicalize(&path)
+                    && self.includes.contains(&canonical)
+                {
+                    return Err(tokens.error(
+                        line,
+                        format!("`{}` is already being included", path.display()),
+                    ));
+                }
+
+                self.parse_file(&path)?;
+            }
+            "ReverseOrientation" => {
+                self.warn(&location, "`ReverseOrientation` is not supported");
+            }
+            "Option" | "ColorSpace" | "PixelFilter" | "Accelerator" | "Texture"
+            | "MakeNamedMedium" | "MediumInterface" | "Attribute" | "TransformTimes"
+            | "ActiveTransform" => {
+                // skip the arguments, up to the next directive
+                while let Some(token) = tokens.peek()
+                    && !matches!(token, Token::Identifier(word) if is_directive(word))
+                {
+                    tokens.next();
+                }
+
+                self.warn(&location, format_args!("ignoring `{directive}`"));
+            }
+            _ => return Err(tokens.error(line, format!("unknown directive `{directive}`"))),
+        }
+
+        Ok(())
+    }
+
+    fn material(&mut self, ty: &str, params: &mut Params, location: &str) -> MaterialDescription {
+        match ty {
+            "diffuse" => diffuse(params.rgb("reflectance", Vec3A::splat(0.5))),
+            "conductor" => {
+                let ior = if params.type_of("reflectance").is_some() {
+                    ior_from_reflectance(params.rgb("reflectance", Vec3A::ONE))
+                } else {
+                    self.conductor_ior(params, location)
+                };
+
+                conductor(ior, roughness(params))
+            }
+            "dielectric" => {
+                let ior = params.float("eta", 1.5);
+
+                MaterialDescription {
+                    brdf: Some(BrdfKind::Dielectric),
+                    ior,
+                    albedo: Vec3A::ONE,
+                    specular: specular_from_ior(ior),
+                    roughness: roughness(params),
+                    ..MaterialDescription::default()
+                }
+            }
+            "coateddiffuse" => {
+                // the coating becomes the clearcoat lobe over a diffuse base, and the roughness of
+                // the coating is mapped onto the range of the clearcoat
+                if (params.float("eta", 1.5) - 1.5).abs() > 1e-3 {
+                    self.warn(
+                        location,
+                        "the coating of `coateddiffuse` always has an index of refraction of 1.5",
+                    );
+                }
+
+                let alpha = roughness(params).powi(2);
+                let clearcoat_gloss = ((0.1 - alpha) / (0.1 - 0.001)).clamp(0.0, 1.0);
+
+                MaterialDescription {
+                    brdf: Some(BrdfKind::Disney),
+                    albedo: params.rgb("reflectance", Vec3A::splat(0.5)),
+                    specular: 0.0,
+                    roughness: 1.0,
+                    clearcoat: 1.0,
+                    clearcoat_gloss,
+                    ..MaterialDescription::default()
+                }
+            }
+            _ => {
+                self.warn(
+                    location,
+                    format_args!("`{ty}` materials are not supported, using a diffuse one"),
+                );
+                diffuse(Vec3A::splat(0.5))
+            }
+        }
+    }
+
+    /// Returns the complex index of refraction of a conductor, given either as RGB values or as
+    /// one of the named metal spectra.
+    fn conductor_ior(&mut self, params: &mut Params, location: &str) -> (Vec3A, Vec3A) {
+        if params.type_of("eta") == Some("spectrum") {
+            let eta = params.string("eta").unwrap_or_default();
+            let _k = params.string("k");
+            let metal = eta
+                .strip_prefix("metal-")
+                .and_then(|name| name.strip_suffix("-eta"));
+
+            return match metal.and_then(metal_ior) {
+                Some(ior) => ior,
+                None => {
+                    self.warn(
+                        location,
+                        format_args!("unknown conductor spectrum `{eta}`, using copper"),
+                    );
+                    COPPER
+                }
+            };
+        }
+
+        if params.type_of("eta").is_none() {
+            return COPPER;
+        }
+
+        (params.rgb("eta", Vec3A::ONE), params.rgb("k", Vec3A::ZERO))
+    }
+
+    fn light(&mut self, ty: &str, params: &mut Params, location: &str) {
+        let scale = params.float("scale", 1.0);
+
+        match ty {
+            "point" => {
+                let position = params.point3("from", Vec3A::ZERO);
+
+                self.lights.push(LightDescription::Point {
+                    position: self.ctm.transform_point3(position.into()).into(),
+                    intensity: params.rgb("I", Vec3A::ONE) * scale,
+                });
+            }
+            "spot" => {
+                let from = params.point3("from", Vec3A::ZERO);
+                let to = params.point3("to", Vec3A::Z);
+                let cone_angle = params.float("coneangle", 30.0);
+                let cone_delta = params.float("conedeltaangle", 5.0);
+
+                self.lights.push(LightDescription::Spot {
+                    position: self.ctm.transform_point3(from.into()).into(),
+                    direction: self.ctm.transform_vector3((to - from).into()).into(),
+                    intensity: params.rgb("I", Vec3A::ONE) * scale,
+                    falloff_start: (cone_angle - cone_delta).max(0.0),
+                    cone_angle,
+                });
+            }
+            "distant" => {
+                let from = params.point3("from", Vec3A::ZERO);
+                let to = params.point3("to", Vec3A::Z);
+
+                self.lights.push(LightDescription::Directional {
+                    direction: self.ctm.transform_vector3((to - from).into()).into(),
+                    irradiance: params.rgb("L", Vec3A::ONE) * scale,
+                });
+            }
+            "infinite" => {
+                if let Some(filename) = params.string("filename") {
+                    self.warn(
+                        location,
+                        format_args!(
+                            "image lights are not supported, ignoring `{filename}`; render with \
+                             `--environment` instead"
+                        ),
+                    );
+                    return;
+                }
+
+                if self.environment.is_some() {
+                    self.warn(location, "only one infinite light is supported");
+                }
+
+                self.environment = Some(EnvironmentDescription::Uniform {
+                    radiance: params.rgb("L", Vec3A::ONE) * scale,
+                });
+            }
+            _ => self.warn(location, format_args!("`{ty}` lights are not supported")),
+        }
+    }
+
+    fn shape(&mut self, ty: &str, params: &mut Params, location: &str) {
+        let kind = match ty {
+            "sphere" => ShapeKind::Sphere {
+                radius: params.float("radius", 1.0),
+            },
+            "trianglemesh" => {
+                let Some(positions) = params.point3s("P") else {
+                    self.warn(location, "triangle mesh without `point3 P`");
+                    return;
+                };
+                let indices = match params.ints("indices") {
+                    Some(indices) => indices,
+                    None if positions.len() == 3 => vec![0, 1, 2],
+                    None => {
+                        self.warn(location, "triangle mesh without `integer indices`");
+                        return;
+                    }
+                };
+
+                if indices.len() % 3 != 0
+                    || indices
+                        .iter()
+                        .any(|&index| index < 0 || index as usize >= positions.len())
+                {
+                    self.warn(location, "triangle mesh with invalid `integer indices`");
+                    return;
+                }
+
+                let mut normals = params.point3s("N");
+                if normals
+                    .as_ref()
+                    .is_some_and(|normals| normals.len() != positions.len())
+                {
+                    self.warn(location, "ignoring `normal N`, which is not one per vertex");
+                    normals = None;
+                }
+
+                let mut uvs = params.point2s("uv");
+                if uvs.as_ref().is_some_and(|uvs| uvs.len() != positions.len()) {
+                    self.warn(
+                        location,
+                        "ignoring `point2 uv`, which is not one per vertex",
+                    );
+                    uvs = None;
+                }
+
+                ShapeKind::Triangles {
+                    positions,
+                    normals,
+                    uvs,
+                    triangles: indices
+                        .chunks_exact(3)
+                        .map(|triangle| {
+                            [triangle[0] as u32, triangle[1] as u32, triangle[2] as u32]
+                        })
+                        .collect(),
+                }
+            }
+            "plymesh" => {
+                let Some(filename) = params.string("filename") else {
+                    self.warn(location, "PLY mesh without `string filename`");
+                    return;
+                };
+
+                ShapeKind::PlyMesh {
+                    path: PathBuf::from(filename),
+                }
+            }
+            _ => {
+                self.warn(location, format_args!("`{ty}` shapes are not supported"));
+                return;
+            }
+        };
+
+        let (material_name, mut material) = match &self.graphics.material {
+            MaterialRef::Anonymous(material) => ("material".to_owned(), material.as_ref().clone()),
+            MaterialRef::Named(name) => match self.named_materials.get(name) {
+                Some(material) => (name.clone(), material.clone()),
+                None => {
+                    let name = name.clone();
+                    self.warn(location, format_args!("undefined material `{name}`"));
+                    ("material".to_owned(), diffuse(Vec3A::splat(0.5)))
+                }
+            },
+        };
+
+        let material_name = match self.graphics.area_light {
+            Some(radiance) => {
+                material.emission = radiance;
+                format!("{material_name}_emitter")
+            }
+            None => material_name,
+        };
+
+        let shape = Shape {
+            kind,
+            transform: self.ctm,
+            material_name,
+            material,
+            location: location.to_owned(),
+        };
+
+        match &mut self.instance {
+            Some((_, shapes)) => shapes.push(shape),
+            None => self.shapes.push(shape),
+        }
+    }
+
+    fn finish(mut self, name: String) -> ImportedScene {
+        let world_from_camera = self.camera_from_world.inverse();
+        let position = world_from_camera.transform_point3(Vec3::ZERO);
+        let direction = world_from_camera.transform_vector3(Vec3::Z);
+        let up = world_from_camera.transform_vector3(Vec3::Y);
+        let right = world_from_camera.transform_vector3(Vec3::X);
+
+        // the renderer puts `direction × up` on the right of the image, which is the opposite of
+        // pbrt unless the scene already mirrors its left-handed camera space
+        let mirror = if direction.cross(up).dot(right) < 0.0 {
+            Mat4::from_scale(Vec3::new(-1.0, 1.0, 1.0))
+        } else {
+            Mat4::IDENTITY
+        };
+
+        // pbrt's field of view spans the shorter side of the image
+        let fov = if self.render.width < self.render.height {
+            let aspect_ratio = self.render.height as f32 / self.render.width as f32;
+            2.0 * ((self.fov.to_radians() / 2.0).tan() * aspect_ratio)
+                .atan()
+                .to_degrees()
+        } else {
+            self.fov
+        };
+
+        let camera = CameraDescription {
+            position: mirror.transform_point3(position).into(),
+            target: mirror.transform_point3(position + direction).into(),
+            up: mirror.transform_vector3(up).normalize().into(),
+            fov,
+        };
+
+        let mut materials = BTreeMap::new();
+        let mut objects = Vec::with_capacity(self.shapes.len());
+
+        for shape in std::mem::take(&mut self.shapes) {
+            let material =
+                intern_material(&mut materials, shape.material_name, shape.material.clone());
+            let transform = mirror * shape.transform;
+
+            match shape.kind {
+                ShapeKind::Sphere { radius } => {
+                    let axes = Mat3A::from_mat4(transform);
+                    let scales = Vec3A::new(
+                        axes.x_axis.length(),
+                        axes.y_axis.length(),
+                        axes.z_axis.length(),
+                    );
+                    let scale = scales.element_sum() / 3.0;
+
+                    if scales.max_element() - scales.min_element() > 1e-3 * scale {
+                        self.warn(
+                            &shape.location,
+                            "spheres cannot be scaled non-uniformly, using the average scale",
+                        );
+                    }
+
+                    objects.push(ObjectDescription::Sphere {
+                        center: transform.transform_point3(Vec3::ZERO).into(),
+                        radius: radius * scale,
+                        material,
+                    });
+                }
+                ShapeKind::Triangles {
+                    positions,
+                    normals,
+                    uvs,
+                    mut triangles,
+                } => {
+                    let transform = Affine3A::from_mat4(transform);
+                    let normal_matrix = transform.matrix3.inverse().transpose();
+                    let positions: Vec<_> = positions
+                        .into_iter()
+                        .map(|position| transform.transform_point3a(position))
+                        .collect();
+
+                    // area lights emit from the side the mesh faces, which has to be kept when
+                    // the transform mirrors it; other meshes are shaded on both sides by pbrt but
+                    // only on their front by the renderer, so they are turned towards the camera
+                    let reverse = if shape.material.emission == Vec3A::ZERO {
+                        faces_away_from(&positions, &triangles, camera.position)
+                    } else {
+                        transform.matrix3.determinant() < 0.0
+                    };
+
+                    if reverse {
+                        for triangle in &mut triangles {
+                            triangle.swap(1, 2);
+                        }
+                    }
+
+                    objects.push(ObjectDescription::TriangleMesh {
+                        positions,
+                        normals: normals.map(|normals| {
+                            normals
+                                .into_iter()
+                                .map(|normal| (normal_matrix * normal).normalize_or_zero())
+                                .collect()
+                        }),
+                        uvs,
+                        triangles,
+                        material,
+                    });
+                }
+                ShapeKind::PlyMesh { path } => {
+                    let (scale, rotation, translation) = transform.to_scale_rotation_translation();
+
+                    if !Mat4::from_scale_rotation_translation(scale, rotation, translation)
+                        .abs_diff_eq(transform, 1e-3)
+                    {
+                        self.warn(
+                            &shape.location,
+                            "meshes cannot be sheared, approximating the transform",
+                        );
+                    }
+
+                    objects.push(ObjectDescription::Mesh {
+                        path,
+                        material: Some(material),
+                        translation: translation.into(),
+                        rotation: degrees_from_rotation(rotation),
+                        scale: scale.into(),
+                    });
+                }
+            }
+        }
+
+        let lights = self
+            .lights
+            .into_iter()
+            .map(|light| match light {
+                LightDescription::Point {
+                    position,
+                    intensity,
+                } => LightDescription::Point {
+                    position: mirror.transform_point3(position.into()).into(),
+                    intensity,
+                },
+                LightDescription::Spot {
+                    position,
+                    direction,
+                    intensity,
+                    falloff_start,
+                    cone_angle,
+                } => LightDescription::Spot {
+                    position: mirror.transform_point3(position.into()).into(),
+                    direction: mirror.transform_vector3(direction.into()).into(),
+                    intensity,
+                    falloff_start,
+                    cone_angle,
+                },
+                LightDescription::Directional {
+                    direction,
+                    irradiance,
+                } => LightDescription::Directional {
+                    direction: mirror.transform_vector3(direction.into()).into(),
+                    irradiance,
+                },
+            })
+            .collect();
+
+        ImportedScene {
+            description: SceneDescription {
+                name,
+                camera,
+                render: self.render,
+                environment: self.environment,
+                materials,
+                objects,
+                lights,
+            },
+            warnings: self.warnings,
+        }
+    }
+}
+
+/// Converts pbrt's roughness, which is remapped to the microfacet alpha by a square root unless
+/// `remaproughness` is false, into ours, whose square is alpha.
+///
+/// Anisotropic roughness is approximated by the average alpha of both directions.
+fn roughness(params: &mut Params) -> f32 {
+    let roughness = params.float("roughness", 0.0);
+    let u = params.float("uroughness", roughness);
+    let v = params.float("vroughness", roughness);
+    let remap = params.bool("remaproughness", true);
+    let alpha = |roughness: f32| {
+        if remap {
+            roughness.max(0.0).sqrt()
+        } else {
+            roughness.max(0.0)
+        }
+    };
+    let alpha = (alpha(u) + alpha(v)) / 2.0;
+
+    alpha.sqrt().clamp(0.0, 1.0)
+}
+
+fn is_directive(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_ascii_uppercase())
+}
+
+struct Tokens<'a> {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    path: &'a Path,
+}
+
+impl Tokens<'_> {
+    fn next(&mut self) -> Option<(Token, usize)> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn line(&self, fallback: usize) -> usize {
+        self.tokens
+            .get(self.position)
+            .map_or(fallback, |(_, line)| *line)
+    }
+
+    fn error(&self, line: usize, message: impl Into<String>) -> PbrtError {
+        PbrtError::Parse {
+            path: self.path.to_owned(),
+            line,
+            message: message.into(),
+        }
+    }
+
+    /// Reads the string argument of the directive on `line`.
+    fn string(&mut self, line: usize) -> Result<String, PbrtError> {
+        match self.next() {
+            Some((Token::String(string), _)) => Ok(string),
+            Some((_, line)) => Err(self.error(line, "expected a string")),
+            None => Err(self.error(line, "expected a string")),
+        }
+    }
+
+    /// Reads the numeric arguments of the directive on `line`, which may be in brackets.
+    fn numbers<const N: usize>(&mut self, line: usize) -> Result<[f32; N], PbrtError> {
+        let bracketed = self.peek() == Some(&Token::OpenBracket);
+        if bracketed {
+            self.next();
+        }
+
+        let mut numbers = [0.0; N];
+
+        for number in &mut numbers {
+            match self.next() {
+                Some((Token::Number(value), _)) => *number = value as f32,
+                _ => return Err(self.error(self.line(line), format!("expected {N} numbers"))),
+            }
+        }
+
+        if bracketed && !matches!(self.next(), Some((Token::CloseBracket, _))) {
+            return Err(self.error(self.line(line), format!("expected {N} numbers")));
+        }
+
+        Ok(numbers)
+    }
+
+    /// Reads the parameter list following the arguments of a directive.
+    fn params(&mut self) -> Result<Params, PbrtError> {
+        let mut params = Vec::new();
+
+        while let Some((Token::String(declaration), line)) = self.tokens.get(self.position).cloned()
+        {
+            self.next();
+
+            let mut words = declaration.split_whitespace();
+            let (Some(ty), Some(name), None) = (words.next(), words.next(), words.next()) else {
+                return Err(self.error(
+                    line,
+                    format!("expected a parameter declaration, found `\"{declaration}\"`"),
+                ));
+            };
+
+            let mut tokens = Vec::new();
+            match self.next() {
+                Some((Token::OpenBracket, _)) => loop {
+                    match self.next() {
+                        Some((Token::CloseBracket, _)) => break,
+                        Some((token, _)) => tokens.push(token),
+                        None => return Err(self.error(line, "unterminated parameter list")),
+                    }
+                },
+                Some((token, _)) => tokens.push(token),
+                None => return Err(self.error(line, format!("missing value of `{name}`"))),
+            }
+
+            let values = if ty == "bool" {
+                tokens
+                    .iter()
+                    .map(|token| match token {
+                        Token::Identifier(word) | Token::String(word) if word == "true" => {
+                            Some(true)
+                        }
+                        Token::Identifier(word) | Token::String(word) if word == "false" => {
+                            Some(false)
+                        }
+                        _ => None,
+                    })
+                    .collect::<Option<_>>()
+                    .map(Values::Bools)
+            } else if let Some(Token::String(_)) = tokens.first() {
+                tokens
+                    .iter()
+                    .map(|token| match token {
+                        Token::String(string) => Some(string.clone()),
+                        _ => None,
+                    })
+                    .collect::<Option<_>>()
+                    .map(Values::Strings)
+            } else {
+                tokens
+                    .iter()
+                    .map(|token| match token {
+                        Token::Number(number) => Some(*number),
+                        _ => None,
+                    })
+                    .collect::<Option<_>>()
+                    .map(Values::Numbers)
+            };
+
+            let Some(values) = values else {
+                return Err(self.error(line, format!("invalid value of `{name}`")));
+            };
+
+            params.push(Param {
+                ty: ty.to_owned(),
+                name: name.to_owned(),
+                values,
+            });
+        }
+
+        Ok(Params::new(params))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes the files of a test into a directory of their own and returns its path.
+    fn write_files(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("raytracer-pbrt-{}-{test}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        for (name, source) in files {
+            fs::write(dir.join(name), source).unwrap();
+        }
+
+        dir
+    }
+
+    fn import(test: &str, source: &str) -> SceneDescription {
+        let dir = write_files(test, &[("scene.pbrt", source)]);
+        import_pbrt(dir.join("scene.pbrt")).unwrap().description
+    }
+
+    #[test]
+    fn left_handed_cameras_are_mirrored_with_the_scene() {
+        let scene = |prefix: &str| {
+            format!(
+                r#"{prefix}
+LookAt 1 2 5  0 0 0  0 1 0
+Camera "perspective" "float fov" 45
+Film "rgb" "integer xresolution" 200 "integer yresolution" 400
+WorldBegin
+Translate 1 0 0
+Shape "sphere" "float radius" 0.5
+"#
+            )
+        };
+
+        // pbrt puts `+X` on the left of the image of this camera, and the renderer on its right
+        let mirrored = import("mirrored", &scene(""));
+        // a mirrored camera space already puts `+X` on the right
+        let unmirrored = import("unmirrored", &scene("Scale -1 1 1"));
+
+        for (description, x) in [(mirrored, -1.0), (unmirrored, 1.0)] {
+            let camera = &description.camera;
+            let position = Vec3A::new(x, 2.0, 5.0);
+            assert!(camera.position.abs_diff_eq(position, 1e-5));
+            // the target is a point along the view direction, rather than the one looked at
+            assert!(
+                (camera.target - camera.position)
+                    .normalize()
+                    .abs_diff_eq(-position.normalize(), 1e-5)
+            );
+            assert!(camera.up.dot(Vec3A::Y) > 0.9);
+
+            match &description.objects[..] {
+                [ObjectDescription::Sphere { center, radius, .. }] => {
+                    assert!(center.abs_diff_eq(Vec3A::new(x, 0.0, 0.0), 1e-5));
+                    assert!((radius - 0.5).abs() < 1e-5);
+                }
+                objects => panic!("expected a sphere, got {objects:?}"),
+            }
+
+            // the field of view of pbrt spans the width of this portrait image
+            assert!((camera.fov - 79.2786).abs() < 1e-3, "{}", camera.fov);
+            assert_eq!(
+                (description.render.width, description.render.height),
+                (200, 400)
+            );
+        }
+    }
+
+    #[test]
+    fn area_lights_make_triangle_meshes_emissive() {
+        let description = import(
+            "area_light",
+            r#"
+WorldBegin
+AttributeBegin
+  AreaLightSource "diffuse" "rgb L" [4 2 1] "float scale" 2
+  Material "diffuse" "rgb reflectance" [0.2 0.4 0.6]
+  Shape "trianglemesh" "point3 P" [0 0 0  1 0 0  0 1 0] "integer indices" [0 1 2]
+AttributeEnd
+Shape "sphere"
+"#,
+        );
+
+        let [
+            ObjectDescription::TriangleMesh {
+                positions,
+                triangles,
+                material: emitter,
+                ..
+            },
+            ObjectDescription::Sphere { material, .. },
+        ] = &description.objects[..]
+        else {
+            panic!(
+                "expected a mesh and a sphere, got {:?}",
+                description.objects
+            );
+        };
+
+        assert_eq!(emitter, "material_emitter");
+        let emitter = &description.materials[emitter];
+        assert_eq!(emitter.emission, Vec3A::new(8.0, 4.0, 2.0));
+        assert_eq!(emitter.albedo, Vec3A::new(0.2, 0.4, 0.6));
+
+        // the light is scoped to the attribute block
+        assert_eq!(description.materials[material].emission, Vec3A::ZERO);
+
+        // the default camera is mirrored, and so is the mesh, whose winding is reversed to keep
+        // emitting from the same side
+        assert_eq!(positions, &[Vec3A::ZERO, Vec3A::NEG_X, Vec3A::Y]);
+        assert_eq!(triangles, &[[0, 2, 1]]);
+    }
+
+    #[test]
+    fn include_cycles_are_errors() {
+        let dir = write_files(
+            "include_cycle",
+            &[
+                ("scene.pbrt", "WorldBegin\nInclude \"shapes.pbrt\"\n"),
+                (
+                    "shapes.pbrt",
+                    "Shape \"sphere\"\n\nInclude \"scene.pbrt\"\n",
+                ),
+            ],
+        );
+
+        match import_pbrt(dir.join("scene.pbrt")) {
+            Err(PbrtError::Parse {
+                path,
+                line,
+                message,
+            }) => {
+                assert_eq!(path, dir.join("shapes.pbrt"));
+                assert_eq!(line, 3);
+                assert!(message.ends_with("is already being included"), "{message}");
+            }
+            Err(error) => panic!("expected a parse error, got {error}"),
+            Ok(_) => panic!("expected a parse error"),
+        }
+    }
+}
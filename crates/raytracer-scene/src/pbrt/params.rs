@@ -0,0 +1,162 @@
+use glam::{Vec2, Vec3A};
+
+/// A parameter of a directive, such as `"float radius" [ 2 ]`.
+#[derive(Debug, Clone)]
+pub(super) struct Param {
+    pub ty: String,
+    pub name: String,
+    pub values: Values,
+}
+
+#[derive(Debug, Clone)]
+pub(super) enum Values {
+    Numbers(Vec<f64>),
+    Strings(Vec<String>),
+    Bools(Vec<bool>),
+}
+
+/// The parameter list of a directive.
+///
+/// Lookups record which parameters were used, so that the ones that were not can be reported,
+/// along with the ones that were used but had a type that could not be, such as textures.
+#[derive(Debug, Default)]
+pub(super) struct Params {
+    params: Vec<Param>,
+    used: Vec<bool>,
+    unsupported: Vec<String>,
+}
+
+impl Params {
+    pub fn new(params: Vec<Param>) -> Self {
+        Self {
+            used: vec![false; params.len()],
+            params,
+            unsupported: Vec::new(),
+        }
+    }
+
+    fn find_index(&mut self, name: &str) -> Option<usize> {
+        let index = self.params.iter().position(|param| param.name == name)?;
+        self.used[index] = true;
+        Some(index)
+    }
+
+    fn find(&mut self, name: &str) -> Option<&Param> {
+        let index = self.find_index(name)?;
+        Some(&self.params[index])
+    }
+
+    fn numbers(&mut self, name: &str) -> Option<&[f64]> {
+        let index = self.find_index(name)?;
+        let param = &self.params[index];
+
+        match &param.values {
+            Values::Numbers(numbers) => Some(numbers),
+            _ => {
+                let description = format!("`{} {}`", param.ty, param.name);
+                self.unsupported.push(description);
+                None
+            }
+        }
+    }
+
+    pub fn float(&mut self, name: &str, default: f32) -> f32 {
+        self.numbers(name)
+            .and_then(|numbers| numbers.first())
+            .map_or(default, |&value| value as f32)
+    }
+
+    pub fn int(&mut self, name: &str, default: i64) -> i64 {
+        self.numbers(name)
+            .and_then(|numbers| numbers.first())
+            .map_or(default, |&value| value as i64)
+    }
+
+    pub fn ints(&mut self, name: &str) -> Option<Vec<i64>> {
+        self.numbers(name)
+            .map(|numbers| numbers.iter().map(|&value| value as i64).collect())
+    }
+
+    pub fn point3(&mut self, name: &str, default: Vec3A) -> Vec3A {
+        match self.numbers(name) {
+            Some([x, y, z, ..]) => Vec3A::new(*x as f32, *y as f32, *z as f32),
+            _ => default,
+        }
+    }
+
+    pub fn point3s(&mut self, name: &str) -> Option<Vec<Vec3A>> {
+        self.numbers(name).map(|numbers| {
+            numbers
+                .chunks_exact(3)
+                .map(|xyz| Vec3A::new(xyz[0] as f32, xyz[1] as f32, xyz[2] as f32))
+                .collect()
+        })
+    }
+
+    pub fn point2s(&mut self, name: &str) -> Option<Vec<Vec2>> {
+        self.numbers(name).map(|numbers| {
+            numbers
+                .chunks_exact(2)
+                .map(|xy| Vec2::new(xy[0] as f32, xy[1] as f32))
+                .collect()
+        })
+    }
+
+    pub fn string(&mut self, name: &str) -> Option<String> {
+        match &self.find(name)?.values {
+            Values::Strings(strings) => strings.first().cloned(),
+            _ => None,
+        }
+    }
+
+    pub fn bool(&mut self, name: &str, default: bool) -> bool {
+        match self.find(name).map(|param| &param.values) {
+            Some(Values::Bools(bools)) => bools.first().copied().unwrap_or(default),
+            _ => default,
+        }
+    }
+
+    /// Returns the type of a parameter, such as `rgb` or `texture`, without using it.
+    pub fn type_of(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|param| param.name == name)
+            .map(|param| param.ty.as_str())
+    }
+
+    /// Returns an RGB value; spectra and textures are replaced by `default`.
+    pub fn rgb(&mut self, name: &str, default: Vec3A) -> Vec3A {
+        let Some(index) = self.find_index(name) else {
+            return default;
+        };
+        let param = &self.params[index];
+
+        match (param.ty.as_str(), &param.values) {
+            ("rgb" | "color", Values::Numbers(numbers)) if numbers.len() >= 3 => {
+                Vec3A::new(numbers[0] as f32, numbers[1] as f32, numbers[2] as f32)
+            }
+            ("float", Values::Numbers(numbers)) if !numbers.is_empty() => {
+                Vec3A::splat(numbers[0] as f32)
+            }
+            _ => {
+                let description = format!("`{} {}`", param.ty, param.name);
+                self.unsupported.push(description);
+                default
+            }
+        }
+    }
+
+    /// Returns the parameters that were looked up, but whose values had to be replaced by defaults.
+    pub fn unsupported(&self) -> &[String] {
+        &self.unsupported
+    }
+
+    /// Returns the parameters that were never looked up.
+    pub fn unused(&self) -> impl Iterator<Item = &Param> {
+        self.params
+            .iter()
+            .zip(&self.used)
+            .filter(|(_, used)| !**used)
+            .map(|(param, _)| param)
+    }
+}
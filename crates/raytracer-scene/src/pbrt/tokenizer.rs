@@ -0,0 +1,89 @@
+/// A token of a pbrt scene file, with the line it starts on.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Token {
+    /// directives, such as `Shape`, along with the unquoted `true` and `false`
+    Identifier(String),
+    String(String),
+    Number(f64),
+    OpenBracket,
+    CloseBracket,
+}
+
+pub(super) fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, (usize, String)> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    let mut line = 1;
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            '\n' => {
+                line += 1;
+                chars.next();
+            }
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '#' => while chars.next_if(|&c| c != '\n').is_some() {},
+            '[' => {
+                chars.next();
+                tokens.push((Token::OpenBracket, line));
+            }
+            ']' => {
+                chars.next();
+                tokens.push((Token::CloseBracket, line));
+            }
+            '"' => {
+                let start_line = line;
+                let mut string = String::new();
+                chars.next();
+
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => string.push('\n'),
+                            Some('t') => string.push('\t'),
+                            Some(c) => string.push(c),
+                            None => break,
+                        },
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            string.push(c);
+                        }
+                        None => return Err((start_line, "unterminated string".to_owned())),
+                    }
+                }
+
+                tokens.push((Token::String(string), start_line));
+            }
+            c if c.is_ascii_digit() || matches!(c, '-' | '+' | '.') => {
+                let mut number = String::new();
+
+                while let Some(c) =
+                    chars.next_if(|&c| c.is_ascii_alphanumeric() || matches!(c, '-' | '+' | '.'))
+                {
+                    number.push(c);
+                }
+
+                let value = number
+                    .parse()
+                    .map_err(|_| (line, format!("invalid number `{number}`")))?;
+                tokens.push((Token::Number(value), line));
+            }
+            c if c.is_ascii_alphabetic() => {
+                let mut identifier = String::new();
+
+                while let Some(c) = chars.next_if(|&c| c.is_ascii_alphanumeric() || c == '_') {
+                    identifier.push(c);
+                }
+
+                tokens.push((Token::Identifier(identifier), line));
+            }
+            c => return Err((line, format!("unexpected character `{c}`"))),
+        }
+    }
+
+    Ok(tokens)
+}
@@ -1,4 +1,4 @@
-use crate::commands::render::RenderCommand;
+use crate::commands::{render::RenderCommand, scene::SceneCommand};
 use clap::{Parser, Subcommand};
 
 #[derive(Parser, Debug)]
@@ -13,4 +13,5 @@ pub struct Cli {
 #[derive(Subcommand, Debug)]
 pub enum Command {
     Render(RenderCommand),
+    Scene(SceneCommand),
 }
@@ -1,39 +1,90 @@
-use crate::scenes::*;
+use crate::scenes::ScenePreset;
 use clap::{Args, ValueEnum};
 use raytracer_core::{camera::Camera, scene::Scene};
 use raytracer_cpu_renderer::{
     brdf::Brdf,
     brdfs::{disney::DisneyBrdf, lambertian::LambertianBrdf},
+    environment::EnvironmentMap,
+    integrators::{ambient_occlusion::AmbientOcclusion, naive::NaivePathTracer, path::PathTracer},
+    light_sampler::LightSampling,
+    lights::SceneLights,
     renderer::{CpuRenderer, CpuRendererConfig},
+    shading::SceneShading,
+    sky::{SUN_ANGULAR_RADIUS, SunSky, SunSkyConfig},
+};
+use raytracer_scene::{
+    LoadedScene, RenderSettings, SceneDescription, gltf::import_gltf, mitsuba::import_mitsuba,
+    pbrt::import_pbrt,
+};
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
 };
-use std::{fs::File, io::BufWriter, time::Instant};
 
 #[derive(Args, Debug)]
-#[command(about = "Render a scene preset using given options")]
+#[command(about = "Render a scene preset or a scene file using given options")]
 #[command(arg_required_else_help = true)]
 pub struct RenderCommand {
     device: Device,
 
+    /// overrides the setting of the scene, like the other render options
     #[arg(short = 'w', long)]
-    image_width: u32,
+    image_width: Option<u32>,
     #[arg(short = 'h', long)]
-    image_height: u32,
+    image_height: Option<u32>,
 
     #[arg(long, default_value = "disney")]
     brdf: BrdfName,
 
     #[arg(short = 's', long)]
-    sample_per_pixel: u32,
-    #[arg(short = 'b', long, default_value = "8")]
-    max_ray_bounces: u32,
+    sample_per_pixel: Option<u32>,
+    #[arg(short = 'b', long)]
+    max_ray_bounces: Option<u32>,
 
-    #[arg(long, default_value = "1.0")]
-    exposure: f32,
-    #[arg(long, default_value = "2.2")]
-    gamma: f32,
+    #[arg(long)]
+    exposure: Option<f32>,
+    #[arg(long)]
+    gamma: Option<f32>,
 
     #[arg(short = 'p', long, default_value = "cornell-box")]
     scene_preset: ScenePreset,
+    /// TOML, pbrt-v4, Mitsuba 3 XML or glTF scene file to render instead of a preset
+    #[arg(long, conflicts_with = "scene_preset")]
+    scene: Option<PathBuf>,
+
+    /// equirectangular Radiance `.hdr` image lighting the scene from all directions
+    #[arg(long)]
+    environment: Option<PathBuf>,
+    #[arg(long, default_value = "1.0")]
+    environment_intensity: f32,
+
+    /// light the scene with a procedural daylight sky and sun
+    #[arg(long, conflicts_with = "environment")]
+    sky: bool,
+    /// angle of the sun above the horizon, in degrees
+    #[arg(long, default_value = "45.0", allow_negative_numbers = true)]
+    sun_elevation: f32,
+    /// angle of the sun clockwise from `-Z` towards `+X`, in degrees
+    #[arg(long, default_value = "0.0", allow_negative_numbers = true)]
+    sun_azimuth: f32,
+    #[arg(long, default_value = "3.0")]
+    turbidity: f32,
+    #[arg(long, default_value = "1.0")]
+    sky_intensity: f32,
+
+    /// how emissive objects are chosen for direct lighting
+    #[arg(long, default_value = "power")]
+    light_sampling: LightSamplingName,
+
+    /// light transport algorithm; `naive` only follows BRDF samples, as a reference for `path`
+    #[arg(long, default_value = "path")]
+    integrator: IntegratorName,
+    /// how far away geometry still occludes a point, for the `ambient-occlusion` integrator
+    #[arg(long, default_value = "1.0")]
+    occlusion_distance: f32,
 
     #[arg(short = 'o', long, default_value = "./output.png")]
     output: String,
@@ -52,28 +103,75 @@ pub enum BrdfName {
 }
 
 #[derive(ValueEnum, Debug, Clone, Copy)]
-pub enum ScenePreset {
-    CornellBox,
+pub enum LightSamplingName {
+    Uniform,
+    Power,
+    Bvh,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum IntegratorName {
+    Path,
+    Naive,
+    AmbientOcclusion,
 }
 
 pub fn handle_render_command(cmd: RenderCommand) -> Result<(), Box<dyn std::error::Error>> {
-    let (scene, camera) = match cmd.scene_preset {
-        ScenePreset::CornellBox => cornell_box::create_cornell_box(),
+    let brdf: Arc<dyn Brdf> = match cmd.brdf {
+        BrdfName::Disney => Arc::new(DisneyBrdf),
+        BrdfName::Lambertian => Arc::new(LambertianBrdf),
+    };
+
+    let (description, base_dir) = match &cmd.scene {
+        Some(path) => (
+            load_scene_file(path)?,
+            path.parent().unwrap_or(Path::new("")),
+        ),
+        None => (cmd.scene_preset.describe(), Path::new("")),
     };
-    let brdf: Box<dyn Brdf> = match cmd.brdf {
-        BrdfName::Disney => Box::new(DisneyBrdf) as Box<dyn Brdf>,
-        BrdfName::Lambertian => Box::new(LambertianBrdf) as Box<dyn Brdf>,
+    let LoadedScene {
+        scene,
+        camera,
+        shading,
+        mut lights,
+    } = description.build(base_dir, brdf)?;
+    let settings = &description.render;
+    let settings = RenderSettings {
+        width: cmd.image_width.unwrap_or(settings.width),
+        height: cmd.image_height.unwrap_or(settings.height),
+        samples: cmd.sample_per_pixel.unwrap_or(settings.samples),
+        max_bounces: cmd.max_ray_bounces.unwrap_or(settings.max_bounces),
+        exposure: cmd.exposure.unwrap_or(settings.exposure),
+        gamma: cmd.gamma.unwrap_or(settings.gamma),
     };
 
+    if let Some(path) = &cmd.environment {
+        let environment = EnvironmentMap::load(path)?.with_intensity(cmd.environment_intensity);
+        lights = lights.with_environment(Arc::new(environment));
+    }
+
+    if cmd.sky {
+        let sun_sky = SunSky::new(SunSkyConfig {
+            sun_direction: SunSkyConfig::sun_direction_from_angles(
+                cmd.sun_elevation,
+                cmd.sun_azimuth,
+            ),
+            turbidity: cmd.turbidity,
+            intensity: cmd.sky_intensity,
+            sun_angular_radius: SUN_ANGULAR_RADIUS,
+        });
+        lights = lights.with_environment(Arc::new(sun_sky));
+    }
+
     let frame_buffer = match cmd.device {
-        Device::Cpu => render_cpu(scene, camera, &cmd, brdf),
-        Device::Gpu => render_gpu(scene, camera, &cmd, brdf),
+        Device::Cpu => render_cpu(scene, camera, &cmd, &settings, shading, lights),
+        Device::Gpu => render_gpu(scene, camera, &cmd, &settings, shading, lights),
     };
 
     let file = File::create(cmd.output)?;
     let w = BufWriter::new(file);
 
-    let mut encoder = png::Encoder::new(w, cmd.image_width, cmd.image_height);
+    let mut encoder = png::Encoder::new(w, settings.width, settings.height);
     encoder.set_color(png::ColorType::Rgba);
     encoder.set_depth(png::BitDepth::Eight);
 
@@ -83,19 +181,57 @@ pub fn handle_render_command(cmd: RenderCommand) -> Result<(), Box<dyn std::erro
     Ok(())
 }
 
-fn render_cpu(scene: Scene, camera: Camera, cmd: &RenderCommand, brdf: Box<dyn Brdf>) -> Vec<u8> {
+/// Loads a TOML scene file, or imports a pbrt, Mitsuba or glTF one and prints what could not be
+/// imported.
+fn load_scene_file(path: &Path) -> Result<SceneDescription, Box<dyn std::error::Error>> {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+
+    let import = match extension.as_deref() {
+        Some("pbrt") => import_pbrt(path)?,
+        Some("xml") => import_mitsuba(path)?,
+        Some("gltf" | "glb") => import_gltf(path)?,
+        _ => return Ok(SceneDescription::load(path)?),
+    };
+
+    for warning in &import.warnings {
+        eprintln!("warning: {warning}");
+    }
+
+    Ok(import.description)
+}
+
+fn render_cpu(
+    scene: Scene,
+    camera: Camera,
+    cmd: &RenderCommand,
+    settings: &RenderSettings,
+    shading: SceneShading,
+    lights: SceneLights,
+) -> Vec<u8> {
     println!("rendering the {} with CPU", scene.name());
 
     let started_at = Instant::now();
     let renderer = CpuRenderer::new(CpuRendererConfig {
-        screen_width: cmd.image_width,
-        screen_height: cmd.image_height,
-        sample_per_pixel: cmd.sample_per_pixel,
-        max_ray_bounces: cmd.max_ray_bounces,
-        exposure: cmd.exposure,
-        gamma: cmd.gamma,
+        screen_width: settings.width,
+        screen_height: settings.height,
+        sample_per_pixel: settings.samples,
+        max_ray_bounces: settings.max_bounces,
+        exposure: settings.exposure,
+        gamma: settings.gamma,
+        light_sampling: match cmd.light_sampling {
+            LightSamplingName::Uniform => LightSampling::Uniform,
+            LightSamplingName::Power => LightSampling::Power,
+            LightSamplingName::Bvh => LightSampling::Bvh,
+        },
+    })
+    .with_integrator(match cmd.integrator {
+        IntegratorName::Path => Arc::new(PathTracer),
+        IntegratorName::Naive => Arc::new(NaivePathTracer),
+        IntegratorName::AmbientOcclusion => Arc::new(AmbientOcclusion::new(cmd.occlusion_distance)),
     });
-    let frame_buffer = renderer.render(&scene, &camera, brdf.as_ref());
+    let frame_buffer = renderer.render(&scene, &camera, &shading, &lights);
 
     let finished_at = Instant::now();
     let render_time = finished_at.duration_since(started_at);
@@ -109,7 +245,9 @@ fn render_gpu(
     _scene: Scene,
     _camera: Camera,
     _cmd: &RenderCommand,
-    _brdf: Box<dyn Brdf>,
+    _settings: &RenderSettings,
+    _shading: SceneShading,
+    _lights: SceneLights,
 ) -> Vec<u8> {
     panic!("GPU is not supported yet");
 }
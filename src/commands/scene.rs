@@ -0,0 +1,40 @@
+use crate::scenes::ScenePreset;
+use clap::{Args, Subcommand};
+use std::path::PathBuf;
+
+#[derive(Args, Debug)]
+#[command(about = "Work with scene files")]
+pub struct SceneCommand {
+    #[command(subcommand)]
+    command: SceneSubcommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum SceneSubcommand {
+    Export(ExportCommand),
+}
+
+#[derive(Args, Debug)]
+#[command(about = "Write a scene preset as a TOML scene file")]
+struct ExportCommand {
+    preset: ScenePreset,
+
+    /// the scene is printed if no output is given
+    #[arg(short = 'o', long)]
+    output: Option<PathBuf>,
+}
+
+pub fn handle_scene_command(cmd: SceneCommand) -> Result<(), Box<dyn std::error::Error>> {
+    match cmd.command {
+        SceneSubcommand::Export(cmd) => {
+            let description = cmd.preset.describe();
+
+            match &cmd.output {
+                Some(path) => description.save(path)?,
+                None => print!("{}", description.to_toml()?),
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -3,15 +3,21 @@ mod commands;
 mod scenes;
 
 use clap::Parser;
+use std::process::ExitCode;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() -> ExitCode {
     let args = cli::Cli::parse();
 
-    match args.command {
-        cli::Command::Render(cmd) => {
-            commands::render::handle_render_command(cmd)?;
+    let result = match args.command {
+        cli::Command::Render(cmd) => commands::render::handle_render_command(cmd),
+        cli::Command::Scene(cmd) => commands::scene::handle_scene_command(cmd),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
         }
     }
-
-    Ok(())
 }
@@ -1 +1,34 @@
 pub mod cornell_box;
+
+use clap::ValueEnum;
+use raytracer_scene::SceneDescription;
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum ScenePreset {
+    CornellBox,
+}
+
+impl ScenePreset {
+    pub fn describe(self) -> SceneDescription {
+        match self {
+            Self::CornellBox => cornell_box::create_cornell_box(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn presets_survive_a_toml_round_trip() {
+        for preset in ScenePreset::value_variants() {
+            let description = preset.describe();
+            let toml = description.to_toml().unwrap();
+            let parsed = SceneDescription::parse(&toml, Path::new("preset.toml")).unwrap();
+
+            assert_eq!(parsed, description, "{preset:?} changed through TOML");
+        }
+    }
+}
@@ -1,6 +1,8 @@
-use glam::{Quat, Vec2, Vec3A};
-use raytracer_core::{camera::Camera, material::Material, scene::Scene};
-use raytracer_primitives::{Box, Plain};
+use glam::{Vec2, Vec3A};
+use raytracer_core::material::Material;
+use raytracer_scene::{
+    CameraDescription, MaterialDescription, ObjectDescription, RenderSettings, SceneDescription,
+};
 
 const MATERIAL_WHITE: Material = Material {
     is_emissive: false,
@@ -97,71 +99,93 @@ const BOX_THICKNESS: f32 = 0.1;
 const BOX_OFFSET: f32 = (BOX_SIZE + BOX_THICKNESS) * 0.5;
 const LIGHT_SIZE: f32 = 0.5;
 
-pub fn create_cornell_box() -> (Scene, Camera) {
-    let mut scene = Scene::new("Cornell Box");
-
-    // Walls
-    scene.add_object(Box {
-        center: Vec3A::new(0.0, -BOX_OFFSET, 0.0),
-        size: Vec3A::new(BOX_SIZE, BOX_THICKNESS, BOX_SIZE),
-        rotation: Quat::IDENTITY,
-        material: MATERIAL_WHITE.clone(),
-    });
-    scene.add_object(Box {
-        center: Vec3A::new(0.0, 0.0, -BOX_OFFSET),
-        size: Vec3A::new(BOX_SIZE, BOX_SIZE, BOX_THICKNESS),
-        rotation: Quat::IDENTITY,
-        material: MATERIAL_WHITE.clone(),
-    });
-    scene.add_object(Box {
-        center: Vec3A::new(0.0, BOX_OFFSET, 0.0),
-        size: Vec3A::new(BOX_SIZE, BOX_THICKNESS, BOX_SIZE),
-        rotation: Quat::IDENTITY,
-        material: MATERIAL_WHITE.clone(),
-    });
-
-    // Colored Walls
-    scene.add_object(Box {
-        center: Vec3A::new(-BOX_OFFSET, 0.0, 0.0),
-        size: Vec3A::new(BOX_THICKNESS, BOX_SIZE, BOX_SIZE),
-        rotation: Quat::IDENTITY,
-        material: MATERIAL_RED.clone(),
-    });
-    scene.add_object(Box {
-        center: Vec3A::new(BOX_OFFSET, 0.0, 0.0),
-        size: Vec3A::new(BOX_THICKNESS, BOX_SIZE, BOX_SIZE),
-        rotation: Quat::IDENTITY,
-        material: MATERIAL_GREEN.clone(),
-    });
-
-    // Light
-    scene.add_object(Plain {
-        center: Vec3A::new(0.0, BOX_OFFSET - BOX_THICKNESS * 0.5 - 1e-3, 0.0),
-        normal: Vec3A::NEG_Y,
-        size: Vec2::new(LIGHT_SIZE, LIGHT_SIZE),
-        material: MATERIAL_LIGHT.clone(),
-    });
+pub fn create_cornell_box() -> SceneDescription {
+    let materials = [
+        ("white", MATERIAL_WHITE),
+        ("red", MATERIAL_RED),
+        ("green", MATERIAL_GREEN),
+        ("light", MATERIAL_LIGHT),
+        ("box_1", MATERIAL_BOX_1),
+        ("box_2", MATERIAL_BOX_2),
+    ]
+    .into_iter()
+    .map(|(name, material)| {
+        (
+            name.to_owned(),
+            MaterialDescription::from_material(&material),
+        )
+    })
+    .collect();
 
-    // Two Boxes
-    scene.add_object(Box {
-        center: Vec3A::new(-0.35, -BOX_OFFSET + 0.8, -0.35),
-        size: Vec3A::new(0.8, 1.6, 0.8),
-        rotation: Quat::from_rotation_y(20.0f32.to_radians()),
-        material: MATERIAL_BOX_1.clone(),
-    });
-    scene.add_object(Box {
-        center: Vec3A::new(0.45, -BOX_OFFSET + 0.35, 0.35),
-        size: Vec3A::new(0.7, 0.7, 0.7),
-        rotation: Quat::from_rotation_y(-20.0f32.to_radians()),
-        material: MATERIAL_BOX_2.clone(),
-    });
+    let wall = |center, size, material: &str| ObjectDescription::Box {
+        center,
+        size,
+        rotation: Vec3A::ZERO,
+        material: material.to_owned(),
+    };
 
-    let camera = Camera::look_at(
-        Vec3A::new(0.0, 0.0, 3.25),
-        Vec3A::new(0.0, 0.0, 0.0),
-        Vec3A::new(0.0, 1.0, 0.0),
-        60.0,
-    );
+    let objects = vec![
+        // Walls
+        wall(
+            Vec3A::new(0.0, -BOX_OFFSET, 0.0),
+            Vec3A::new(BOX_SIZE, BOX_THICKNESS, BOX_SIZE),
+            "white",
+        ),
+        wall(
+            Vec3A::new(0.0, 0.0, -BOX_OFFSET),
+            Vec3A::new(BOX_SIZE, BOX_SIZE, BOX_THICKNESS),
+            "white",
+        ),
+        wall(
+            Vec3A::new(0.0, BOX_OFFSET, 0.0),
+            Vec3A::new(BOX_SIZE, BOX_THICKNESS, BOX_SIZE),
+            "white",
+        ),
+        // Colored Walls
+        wall(
+            Vec3A::new(-BOX_OFFSET, 0.0, 0.0),
+            Vec3A::new(BOX_THICKNESS, BOX_SIZE, BOX_SIZE),
+            "red",
+        ),
+        wall(
+            Vec3A::new(BOX_OFFSET, 0.0, 0.0),
+            Vec3A::new(BOX_THICKNESS, BOX_SIZE, BOX_SIZE),
+            "green",
+        ),
+        // Light
+        ObjectDescription::Plain {
+            center: Vec3A::new(0.0, BOX_OFFSET - BOX_THICKNESS * 0.5 - 1e-3, 0.0),
+            normal: Vec3A::NEG_Y,
+            size: Vec2::new(LIGHT_SIZE, LIGHT_SIZE),
+            material: "light".to_owned(),
+        },
+        // Two Boxes
+        ObjectDescription::Box {
+            center: Vec3A::new(-0.35, -BOX_OFFSET + 0.8, -0.35),
+            size: Vec3A::new(0.8, 1.6, 0.8),
+            rotation: Vec3A::new(0.0, 20.0, 0.0),
+            material: "box_1".to_owned(),
+        },
+        ObjectDescription::Box {
+            center: Vec3A::new(0.45, -BOX_OFFSET + 0.35, 0.35),
+            size: Vec3A::new(0.7, 0.7, 0.7),
+            rotation: Vec3A::new(0.0, -20.0, 0.0),
+            material: "box_2".to_owned(),
+        },
+    ];
 
-    (scene, camera)
+    SceneDescription {
+        name: "Cornell Box".to_owned(),
+        camera: CameraDescription {
+            position: Vec3A::new(0.0, 0.0, 3.25),
+            target: Vec3A::new(0.0, 0.0, 0.0),
+            up: Vec3A::new(0.0, 1.0, 0.0),
+            fov: 60.0,
+        },
+        render: RenderSettings::default(),
+        environment: None,
+        materials,
+        objects,
+        lights: Vec::new(),
+    }
 }